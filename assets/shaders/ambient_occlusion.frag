@@ -6,9 +6,10 @@ out vec4 color;
 uniform float aspect_ratio;
 uniform float tile_depth_inverse;
 uniform sampler2D screen_texture;
-uniform sampler2D data_texture;
 uniform sampler2D depth_texture;
 
+uniform float strength;
+
 const vec3 kernel[12] = vec3[](
 	vec3(-1.0,  0.0, 1.0),
 	vec3( 1.0,  0.0, 1.0),
@@ -34,5 +35,5 @@ void main() {
 		shade += clamp(texture(depth_texture, screen_position + kernel[i].xy * 0.0015 * vec2(1.0, aspect_ratio)).x - z, 0.0, 0.2) * kernel[i].z;
 	}
 	
-	color = vec4(c.rgb * (1.0 - shade * tile_depth_inverse * 0.25), c.a);
+	color = vec4(c.rgb * (1.0 - shade * tile_depth_inverse * 0.25 * strength), c.a);
 }
\ No newline at end of file
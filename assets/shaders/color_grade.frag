@@ -0,0 +1,27 @@
+#version 150
+
+in vec2 screen_position;
+out vec4 color;
+
+uniform sampler2D screen_texture;
+
+uniform sampler2D lut;
+uniform float strength;
+
+// The LUT is a row of square slices, red across each slice, green down it and blue from slice to slice
+vec3 grade(vec3 c) {
+	float size = float(textureSize(lut, 0).y);
+	vec3 scaled = clamp(c, 0.0, 1.0) * (size - 1.0);
+	float slice = floor(scaled.b);
+	float next = min(slice + 1.0, size - 1.0);
+	
+	vec2 uv = (scaled.rg + 0.5) / vec2(size * size, size);
+	vec3 a = texture(lut, uv + vec2(slice / size, 0.0)).rgb;
+	vec3 b = texture(lut, uv + vec2(next / size, 0.0)).rgb;
+	return mix(a, b, scaled.b - slice);
+}
+
+void main() {
+	vec4 c = texture(screen_texture, screen_position);
+	color = vec4(mix(c.rgb, grade(c.rgb), strength), c.a);
+}
@@ -0,0 +1,16 @@
+// Shared by the world shaders
+
+const vec3 light_direction = normalize(vec3(2, 1, 3));
+
+float diffuse_shade(vec3 normal) {
+	return max(dot(normal, light_direction), 0.4);
+}
+
+// What goes in the data buffer, read back by the post-processing passes
+vec4 encode_data(vec3 normal) {
+	return vec4(normal * 0.5 + 0.5, 1);
+}
+
+vec3 decode_normal(vec4 data) {
+	return data.xyz * 2.0 - 1.0;
+}
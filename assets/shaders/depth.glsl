@@ -0,0 +1,14 @@
+// Shared by the post-processing passes that read the depth buffer
+
+uniform bool perspective;
+uniform float near_plane;
+uniform float tile_depth_inverse;
+uniform sampler2D depth_texture;
+
+// Distance in tiles from the camera, undoing the reversed depth of both projections
+float view_distance(vec2 p) {
+	float d = texture(depth_texture, p).x;
+	if (d <= 0.0) return 1e6;
+	if (perspective) return 0.5 * near_plane / max(d - 0.5, 1e-6);
+	return (0.5 - d) * tile_depth_inverse;
+}
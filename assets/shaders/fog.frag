@@ -0,0 +1,20 @@
+#version 150
+
+#include "depth.glsl"
+
+in vec2 screen_position;
+out vec4 color;
+
+uniform sampler2D screen_texture;
+
+uniform float fog_start;
+uniform float fog_end;
+uniform vec3 fog_color;
+
+void main() {
+	vec4 c = texture(screen_texture, screen_position);
+	float fog = clamp((view_distance(screen_position) - fog_start) / max(fog_end - fog_start, 1e-6), 0.0, 1.0);
+	
+	// Nothing was drawn where alpha is zero, and that stays see through
+	color = vec4(mix(c.rgb, fog_color, fog * c.a), c.a);
+}
@@ -0,0 +1,38 @@
+#version 150
+
+#include "common.glsl"
+#include "depth.glsl"
+
+in vec2 screen_position;
+out vec4 color;
+
+uniform vec2 resolution;
+uniform sampler2D screen_texture;
+uniform sampler2D data_texture;
+
+uniform float thickness;
+uniform float depth_threshold;
+uniform float normal_threshold;
+uniform vec3 outline_color;
+
+const vec2 offsets[4] = vec2[](vec2(-1.0, 0.0), vec2(1.0, 0.0), vec2(0.0, -1.0), vec2(0.0, 1.0));
+
+void main() {
+	vec4 c = texture(screen_texture, screen_position);
+	vec4 data = texture(data_texture, screen_position);
+	float z = view_distance(screen_position);
+	
+	// Only the nearer side of a depth step is outlined, and further away the step has to be larger
+	float threshold = perspective ? depth_threshold * max(z, 1.0) : depth_threshold;
+	
+	float edge = 0.0;
+	for (int i = 0; i < 4; i++) {
+		vec2 p = screen_position + offsets[i] * thickness / resolution;
+		if (view_distance(p) - z > threshold) edge = 1.0;
+		
+		vec4 neighbour = texture(data_texture, p);
+		if (data.a > 0.0 && neighbour.a > 0.0 && 1.0 - dot(decode_normal(data), decode_normal(neighbour)) > normal_threshold) edge = 1.0;
+	}
+	
+	color = vec4(mix(c.rgb, outline_color, edge * data.a), c.a);
+}
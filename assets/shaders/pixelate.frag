@@ -0,0 +1,14 @@
+#version 150
+
+in vec2 screen_position;
+out vec4 color;
+
+uniform vec2 resolution;
+uniform sampler2D screen_texture;
+
+uniform float pixel_size;
+
+void main() {
+	vec2 cells = resolution / pixel_size;
+	color = texture(screen_texture, (floor(screen_position * cells) + 0.5) / cells);
+}
@@ -0,0 +1,26 @@
+#version 150
+
+#include "common.glsl"
+
+flat in vec3 normalf;
+#ifdef VERTEX_COLOR
+in vec3 colorf;
+#else
+in vec2 uvf;
+#endif
+out vec4 color;
+out vec4 data;
+
+uniform sampler2D tex;
+
+void main() {
+#ifdef VERTEX_COLOR
+	vec4 c = vec4(colorf, 1.0);
+#else
+	vec4 c = texture(tex, uvf);
+	if (c.a < 0.1) discard;
+#endif
+	
+	color = vec4(c.rgb * diffuse_shade(normalf), c.a);
+	data = encode_data(normalf);
+}
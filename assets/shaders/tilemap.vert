@@ -0,0 +1,38 @@
+#version 150
+
+in vec3 position;
+in vec3 normal;
+#ifdef VERTEX_COLOR
+in vec3 color;
+out vec3 colorf;
+#else
+in vec2 uv;
+out vec2 uvf;
+#endif
+flat out vec3 normalf;
+
+uniform vec3 render_position;
+uniform mat4 view_projection;
+#ifdef SPRITE
+uniform vec4 frame; // Corners of the current frame in the sprite sheet
+uniform mat3 orientation; // Turns billboards towards the camera
+#endif
+
+void main() {
+#ifdef SPRITE
+	vec3 world_position = orientation * position;
+	normalf = orientation * normal;
+#else
+	vec3 world_position = position;
+	normalf = normal;
+#endif
+#ifdef VERTEX_COLOR
+	colorf = color;
+#elif defined(SPRITE)
+	uvf = mix(frame.xy, frame.zw, uv);
+#else
+	uvf = uv;
+#endif
+	
+	gl_Position = view_projection * vec4(world_position + render_position, 1);
+}
@@ -0,0 +1,22 @@
+#version 150
+
+in vec2 screen_position;
+out vec4 color;
+
+uniform float aspect_ratio;
+uniform sampler2D screen_texture;
+
+uniform float strength;
+uniform float radius;
+uniform float softness;
+
+void main() {
+	vec4 c = texture(screen_texture, screen_position);
+	
+	// 0 in the center and 1 in the corners, round rather than stretched with the window
+	vec2 p = (screen_position - 0.5) * vec2(aspect_ratio, 1.0);
+	float d = length(p) / length(vec2(aspect_ratio, 1.0) * 0.5);
+	
+	float v = smoothstep(radius, radius + softness, d);
+	color = vec4(c.rgb * (1.0 - v * strength), c.a);
+}
@@ -0,0 +1,33 @@
+// With the `embed-assets` feature, lists every file under assets/ so they can be included in the binary
+use std::{env, fs, path::{Path, PathBuf}};
+
+fn main() {
+	println!("cargo:rerun-if-changed=assets");
+	
+	let mut entries = String::new();
+	if env::var_os("CARGO_FEATURE_EMBED_ASSETS").is_some() {
+		let root = Path::new(&env::var("CARGO_MANIFEST_DIR").unwrap()).join("assets");
+		let mut files = Vec::new();
+		collect_files(&root, &mut files);
+		files.sort();
+		
+		for path in files {
+			let name = path.strip_prefix(&root).unwrap().to_string_lossy().replace('\\', "/");
+			entries += &format!("\t({name:?}, include_bytes!({:?})),\n", path.display().to_string());
+		}
+	}
+	
+	let out = Path::new(&env::var("OUT_DIR").unwrap()).join("embedded_assets.rs");
+	fs::write(out, format!("pub static EMBEDDED_ASSETS: &[(&str, &[u8])] = &[\n{entries}];\n")).unwrap();
+}
+
+fn collect_files(dir: &Path, files: &mut Vec<PathBuf>) {
+	for entry in fs::read_dir(dir).unwrap().flatten() {
+		let path = entry.path();
+		if path.is_dir() {
+			collect_files(&path, files);
+		} else {
+			files.push(path);
+		}
+	}
+}
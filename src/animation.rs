@@ -0,0 +1,269 @@
+use std::collections::HashMap;
+
+use glium::texture::SrgbTexture2d;
+
+use crate::*;
+
+
+// Below these speeds in tiles per second an entity stands still, or is just stepping down rather than in the air
+const WALK_SPEED: f64 = 0.5;
+const AIRBORNE_SPEED: f64 = 1.0;
+
+
+#[derive(Copy, Clone, Debug, PartialEq, Eq, Hash)]
+pub enum Animation {
+	Idle,
+	Walk,
+	Jump,
+	Swim,
+}
+
+impl Animation {
+	pub const ALL: [Self; 4] = [Self::Idle, Self::Walk, Self::Jump, Self::Swim];
+	
+	pub fn name(self) -> &'static str {
+		match self {
+			Self::Idle => "idle",
+			Self::Walk => "walk",
+			Self::Jump => "jump",
+			Self::Swim => "swim",
+		}
+	}
+	
+	pub fn from_name(name: &str) -> Option<Self> {
+		Self::ALL.into_iter().find(|animation| animation.name() == name)
+	}
+}
+
+
+#[derive(Copy, Clone, Debug, PartialEq, Eq)]
+pub enum Playback {
+	Loop,
+	Once, // Stops on the last frame
+}
+
+#[derive(Copy, Clone, Debug, PartialEq)]
+pub struct AnimationClip {
+	pub row: u32, // The first, for the up direction when the sheet has more than one
+	pub frames: u32,
+	pub frame_duration: f64,
+	pub playback: Playback,
+}
+
+impl AnimationClip {
+	pub fn frame_at(&self, time: f64) -> u32 {
+		let frame = (time.max(0.0) / self.frame_duration) as u32;
+		match self.playback {
+			Playback::Loop => frame % self.frames,
+			Playback::Once => frame.min(self.frames - 1),
+		}
+	}
+}
+
+
+
+// MARK: Sheets
+
+#[derive(Debug)]
+pub struct SheetLayoutError {
+	pub line: usize,
+	pub message: String,
+}
+
+impl std::fmt::Display for SheetLayoutError {
+	fn fmt(&self, f: &mut std::fmt::Formatter<'_>) -> std::fmt::Result {
+		write!(f, "line {}: {}", self.line, self.message)
+	}
+}
+
+// Where the frames of each animation are in a sprite sheet, read from the `.anim` file next to it
+#[derive(Clone, Debug, PartialEq)]
+pub struct SheetLayout {
+	pub frame_size: Vec2<u32>,
+	pub directions: u32, // 1, 4 or 8
+	pub clips: HashMap<Animation, AnimationClip>, // Always has idle
+}
+
+impl SheetLayout {
+	pub fn parse(text: &str) -> Result<Self, SheetLayoutError> {
+		let mut layout = Self { frame_size: Vec2(0, 0), directions: 1, clips: HashMap::new() };
+		
+		for (i, line) in text.lines().enumerate() {
+			let error = |message: String| SheetLayoutError { line: i + 1, message };
+			
+			let line = line.split('#').next().unwrap().trim();
+			if line.is_empty() { continue }
+			
+			let (name, value) = line.split_once('=').ok_or_else(|| error(format!("expected {line} = ...")))?;
+			let (name, value) = (name.trim(), value.trim());
+			let number = |value: &str| value.parse::<u32>().map_err(|_| error(format!("expected a whole number, not {value:?}")));
+			
+			match name {
+				"frame" => {
+					let (width, height) = value.split_once(char::is_whitespace).ok_or_else(|| error("expected frame = <width> <height>".into()))?;
+					layout.frame_size = Vec2(number(width.trim())?, number(height.trim())?);
+				}
+				"directions" => layout.directions = match value {
+					"1" | "4" | "8" => number(value)?,
+					_ => return Err(error(format!("directions should be 1, 4 or 8, not {value}"))),
+				},
+				name => {
+					let animation = Animation::from_name(name).ok_or_else(|| error(format!("unknown animation {name:?}")))?;
+					let mut clip = AnimationClip { row: 0, frames: 1, frame_duration: 1.0, playback: Playback::Loop };
+					
+					for parameter in value.split(',').map(str::trim).filter(|p| !p.is_empty()) {
+						let (parameter, value) = parameter.split_once(char::is_whitespace).map(|(p, v)| (p, v.trim())).unwrap_or((parameter, ""));
+						match parameter {
+							"row" => clip.row = number(value)?,
+							"frames" => clip.frames = number(value)?.max(1),
+							"duration" => clip.frame_duration = value.parse::<f64>().ok().filter(|d| *d > 0.0)
+								.ok_or_else(|| error(format!("expected a duration in seconds, not {value:?}")))?,
+							"once" => clip.playback = Playback::Once,
+							"loop" => clip.playback = Playback::Loop,
+							_ => return Err(error(format!("unknown parameter {parameter:?} for {name}"))),
+						}
+					}
+					
+					layout.clips.insert(animation, clip);
+				}
+			}
+		}
+		
+		let error = |message: &str| SheetLayoutError { line: text.lines().count(), message: message.into() };
+		if layout.frame_size.x() == 0 || layout.frame_size.y() == 0 { return Err(error("missing frame size")) }
+		if !layout.clips.contains_key(&Animation::Idle) { return Err(error("missing idle animation")) }
+		Ok(layout)
+	}
+	
+	// Animations the sheet doesn't have fall back to idle
+	pub fn clip(&self, animation: Animation) -> &AnimationClip {
+		self.clips.get(&animation).unwrap_or(&self.clips[&Animation::Idle])
+	}
+	
+	pub fn frame_uv(&self, sheet_size: Vec2<u32>, animation: Animation, direction: FacingDirection, time: f64) -> UvRect {
+		let clip = self.clip(animation);
+		let row = clip.row + match self.directions {
+			4 => direction.four_way() as u32,
+			8 => direction as u32,
+			_ => 0,
+		};
+		let min = Vec2(clip.frame_at(time), row).scale(self.frame_size).as_type::<f32>();
+		let sheet_size = sheet_size.as_type::<f32>();
+		UvRect {
+			min: min.scale_divide(sheet_size),
+			max: (min + self.frame_size.as_type::<f32>()).scale_divide(sheet_size),
+		}
+	}
+}
+
+
+pub struct SpriteSheet {
+	pub texture: Handle<SrgbTexture2d>,
+	pub layout: SheetLayout,
+}
+
+impl std::fmt::Debug for SpriteSheet {
+	fn fmt(&self, f: &mut std::fmt::Formatter<'_>) -> std::fmt::Result {
+		f.debug_struct("SpriteSheet").field("texture", &self.texture).field("layout", &self.layout).finish()
+	}
+}
+
+impl SpriteSheet {
+	pub fn frame_uv(&self, animation: Animation, direction: FacingDirection, time: f64) -> UvRect {
+		self.layout.frame_uv(Vec2(self.texture.width(), self.texture.height()), animation, direction, time)
+	}
+}
+
+
+
+// MARK: State machine
+
+// The animation an entity is playing and for how long it has been
+#[derive(Copy, Clone, Debug, PartialEq)]
+pub struct AnimationState {
+	pub animation: Animation,
+	pub time: f64,
+}
+
+impl Default for AnimationState {
+	fn default() -> Self {
+		Self { animation: Animation::Idle, time: 0.0 }
+	}
+}
+
+impl AnimationState {
+	// Switching to another animation starts it from its first frame
+	pub fn update(&mut self, animation: Animation, dt: f64) {
+		match animation == self.animation {
+			true => self.time += dt,
+			false => *self = Self { animation, time: 0.0 },
+		}
+	}
+}
+
+// Short drops, like walking down a step, don't count as being in the air
+pub fn choose_animation(contact: ContactState, velocity: Vec3<f64>) -> Animation {
+	match contact {
+		ContactState::Swimming => Animation::Swim,
+		ContactState::Airborne if velocity.z().abs() > AIRBORNE_SPEED => Animation::Jump,
+		_ if velocity.with_z(0.0).length() > WALK_SPEED => Animation::Walk,
+		_ => Animation::Idle,
+	}
+}
+
+
+
+#[cfg(test)]
+mod tests {
+	use super::*;
+	
+	const PLAYER: &str = "frame = 12 12\ndirections = 4\n\nidle = row 0, frames 2, duration 0.5 # Breathing\njump = row 8, frames 2, duration 0.1, once\n";
+	
+	#[test]
+	fn layouts_parse() {
+		let layout = SheetLayout::parse(PLAYER).unwrap();
+		assert_eq!(layout.frame_size, Vec2(12, 12));
+		assert_eq!(layout.directions, 4);
+		assert_eq!(layout.clip(Animation::Jump), &AnimationClip { row: 8, frames: 2, frame_duration: 0.1, playback: Playback::Once });
+		assert_eq!(layout.clip(Animation::Walk), layout.clip(Animation::Idle));
+		
+		// Each direction has its own row, and frames go across
+		let uv = layout.frame_uv(Vec2(48, 192), Animation::Jump, FacingDirection::Left, 0.15);
+		assert_eq!(uv, UvRect { min: Vec2(12.0 / 48.0, 120.0 / 192.0), max: Vec2(24.0 / 48.0, 132.0 / 192.0) });
+		assert_eq!(layout.frame_uv(Vec2(48, 192), Animation::Jump, FacingDirection::DownLeft, 0.15), uv);
+		
+		// With eight directions they go clockwise from up
+		let layout = SheetLayout::parse(&PLAYER.replace("directions = 4", "directions = 8")).unwrap();
+		assert_eq!(layout.frame_uv(Vec2(48, 192), Animation::Idle, FacingDirection::DownLeft, 0.0).min, Vec2(0.0, 60.0 / 192.0));
+		
+		for (text, line) in [("frame = 12\nidle = row 0", 1), ("frame = 1 1\nidle = row 0\nrun = row 1", 3), ("frame = 1 1\nidle = duration -1", 2), ("frame = 1 1", 1)] {
+			assert_eq!(SheetLayout::parse(text).unwrap_err().line, line, "{text}");
+		}
+		
+		let text = std::fs::read_to_string(std::path::Path::new(env!("CARGO_MANIFEST_DIR")).join("assets/textures/player.anim")).unwrap();
+		assert_eq!(SheetLayout::parse(&text).unwrap().clips.len(), Animation::ALL.len());
+	}
+	
+	#[test]
+	fn clips_loop_or_stop() {
+		let mut clip = AnimationClip { row: 0, frames: 3, frame_duration: 0.1, playback: Playback::Loop };
+		assert_eq!([0.0, 0.15, 0.25, 0.35].map(|t| clip.frame_at(t)), [0, 1, 2, 0]);
+		clip.playback = Playback::Once;
+		assert_eq!([0.0, 0.15, 0.25, 0.35, 10.0].map(|t| clip.frame_at(t)), [0, 1, 2, 2, 2]);
+	}
+	
+	#[test]
+	fn state_follows_movement_and_contact() {
+		assert_eq!(choose_animation(ContactState::Grounded, Vec3(0.1, 0.0, 0.0)), Animation::Idle);
+		assert_eq!(choose_animation(ContactState::Grounded, Vec3(3.0, 0.0, 0.0)), Animation::Walk);
+		assert_eq!(choose_animation(ContactState::Airborne, Vec3(3.0, 0.0, 5.0)), Animation::Jump);
+		assert_eq!(choose_animation(ContactState::Airborne, Vec3(3.0, 0.0, -0.5)), Animation::Walk);
+		assert_eq!(choose_animation(ContactState::Swimming, Vec3(0.0, 0.0, 0.0)), Animation::Swim);
+		
+		let mut state = AnimationState::default();
+		state.update(Animation::Idle, 0.3);
+		assert_eq!(state.time, 0.3);
+		state.update(Animation::Walk, 0.3);
+		assert_eq!(state, AnimationState { animation: Animation::Walk, time: 0.0 });
+	}
+}
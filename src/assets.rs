@@ -0,0 +1,332 @@
+use std::{borrow::Cow, collections::HashMap, ops::Deref, path::{Path, PathBuf}, rc::{Rc, Weak}};
+
+use glium::{texture::{MipmapsOption, RawImage2d, SrgbFormat, SrgbTexture2d}, Display, Texture2d};
+use image::RgbaImage;
+
+use crate::*;
+
+
+// Set to a directory to load assets from there instead of looking for them
+pub const ASSETS_VARIABLE: &str = "ENGINE2D_ASSETS";
+
+#[cfg(feature = "embed-assets")]
+mod embedded {
+	include!(concat!(env!("OUT_DIR"), "/embedded_assets.rs"));
+}
+
+
+#[derive(Debug)]
+pub enum AssetError {
+	Io { path: String, error: std::io::Error },
+	Image { path: String, error: image::ImageError },
+	Invalid { path: String, message: String },
+}
+
+impl AssetError {
+	pub fn path(&self) -> &str {
+		match self {
+			Self::Io { path, .. } | Self::Image { path, .. } | Self::Invalid { path, .. } => path,
+		}
+	}
+	
+	pub fn is_not_found(&self) -> bool {
+		matches!(self, Self::Io { error, .. } if error.kind() == std::io::ErrorKind::NotFound)
+	}
+}
+
+impl std::fmt::Display for AssetError {
+	fn fmt(&self, f: &mut std::fmt::Formatter<'_>) -> std::fmt::Result {
+		match self {
+			Self::Io { path, error } => write!(f, "could not read {path}: {error}"),
+			Self::Image { path, error } => write!(f, "could not decode {path}: {error}"),
+			Self::Invalid { path, message } => write!(f, "error in {path}: {message}"),
+		}
+	}
+}
+
+
+
+// MARK: Handles
+
+// A loaded asset, shared between everything that asked for it by the same name. Cloning only bumps the reference count.
+pub struct Handle<T>(Rc<T>);
+
+impl<T> Handle<T> {
+	pub fn new(asset: T) -> Self {
+		Self(Rc::new(asset))
+	}
+	
+	pub fn ptr_eq(a: &Self, b: &Self) -> bool {
+		Rc::ptr_eq(&a.0, &b.0)
+	}
+}
+
+impl<T> Clone for Handle<T> {
+	fn clone(&self) -> Self { Self(self.0.clone()) }
+}
+
+impl<T> Deref for Handle<T> {
+	type Target = T;
+	fn deref(&self) -> &T { &self.0 }
+}
+
+impl<T> std::fmt::Debug for Handle<T> {
+	fn fmt(&self, f: &mut std::fmt::Formatter<'_>) -> std::fmt::Result {
+		write!(f, "Handle({:p})", Rc::as_ptr(&self.0))
+	}
+}
+
+
+// Assets by name, kept only as long as some handle to them is
+pub struct Cache<T> {
+	entries: HashMap<String, Weak<T>>,
+}
+
+impl<T> Default for Cache<T> {
+	fn default() -> Self { Self { entries: HashMap::new() } }
+}
+
+impl<T> Cache<T> {
+	pub fn get(&self, name: &str) -> Option<Handle<T>> {
+		self.entries.get(name)?.upgrade().map(Handle)
+	}
+	
+	pub fn get_or_load(&mut self, name: &str, load: impl FnOnce() -> Result<T, AssetError>) -> Result<Handle<T>, AssetError> {
+		if let Some(handle) = self.get(name) {
+			return Ok(handle)
+		}
+		let handle = Handle::new(load()?);
+		self.entries.insert(name.to_string(), Rc::downgrade(&handle.0));
+		Ok(handle)
+	}
+	
+	// Makes the next `get_or_load` load the asset again, while existing handles keep the old one
+	pub fn forget(&mut self, name: &str) {
+		self.entries.remove(name);
+	}
+	
+	// Number of assets still in use, forgetting the rest
+	pub fn prune(&mut self) -> usize {
+		self.entries.retain(|_, asset| asset.strong_count() > 0);
+		self.entries.len()
+	}
+}
+
+
+
+// MARK: Assets
+
+pub enum AssetSource {
+	Directory(PathBuf),
+	#[cfg(feature = "embed-assets")]
+	Embedded,
+}
+
+impl AssetSource {
+	pub fn read(&self, path: &str) -> Result<Cow<'static, [u8]>, AssetError> {
+		match self {
+			Self::Directory(directory) => std::fs::read(directory.join(path)).map(Cow::Owned)
+				.map_err(|error| AssetError::Io { path: path.to_string(), error }),
+			#[cfg(feature = "embed-assets")]
+			Self::Embedded => embedded::EMBEDDED_ASSETS.iter().find(|(name, _)| *name == path).map(|(_, data)| Cow::Borrowed(*data))
+				.ok_or_else(|| AssetError::Io { path: path.to_string(), error: std::io::ErrorKind::NotFound.into() }),
+		}
+	}
+	
+	pub fn exists(&self, path: &str) -> bool {
+		match self {
+			Self::Directory(directory) => directory.join(path).is_file(),
+			#[cfg(feature = "embed-assets")]
+			Self::Embedded => embedded::EMBEDDED_ASSETS.iter().any(|(name, _)| *name == path),
+		}
+	}
+	
+	fn image(&self, path: &str) -> Result<RgbaImage, AssetError> {
+		Ok(image::load_from_memory(&self.read(path)?).map_err(|error| AssetError::Image { path: path.to_string(), error })?.into_rgba8())
+	}
+}
+
+pub fn raw_image(image: RgbaImage) -> RawImage2d<'static, u8> {
+	let dimensions = image.dimensions();
+	RawImage2d::from_raw_rgba(image.into_raw(), dimensions)
+}
+
+
+// Everything loaded at run time, by paths relative to the asset root like "textures/tiles/grass.png"
+pub struct Assets {
+	source: AssetSource,
+	textures: Cache<SrgbTexture2d>,
+	linear_textures: Cache<Texture2d>,
+	sprite_sheets: Cache<SpriteSheet>,
+	sprite_kinds: HashMap<String, SpriteKind>,
+}
+
+// How a sprite set is laid out in the textures, remembered so it's only looked up once
+#[derive(Copy, Clone, Debug, PartialEq, Eq)]
+enum SpriteKind {
+	Sheet, // `{name}.png` with `{name}.anim` describing its animations
+	Static, // Just `{name}.png`
+	Directional, // A `{name}` directory with an image for each direction
+}
+
+impl Assets {
+	pub fn new(source: AssetSource) -> Self {
+		Self { source, textures: Cache::default(), linear_textures: Cache::default(), sprite_sheets: Cache::default(), sprite_kinds: HashMap::new() }
+	}
+	
+	// The directory in ENGINE2D_ASSETS if it's set, then the embedded assets if there are any, then an assets
+	// directory next to the executable or one of its parents, and finally the one in the source tree
+	pub fn locate() -> Self {
+		if let Some(directory) = std::env::var_os(ASSETS_VARIABLE) {
+			return Self::new(AssetSource::Directory(directory.into()))
+		}
+		
+		#[cfg(feature = "embed-assets")]
+		return Self::new(AssetSource::Embedded);
+		
+		#[allow(unreachable_code)]
+		let beside_executable = std::env::current_exe().ok().and_then(|exe| {
+			exe.ancestors().skip(1).map(|dir| dir.join("assets")).find(|dir| dir.is_dir())
+		});
+		Self::new(AssetSource::Directory(beside_executable.unwrap_or_else(|| Path::new(env!("CARGO_MANIFEST_DIR")).join("assets"))))
+	}
+	
+	// Where the assets are read from, unless they're embedded
+	pub fn directory(&self) -> Option<&Path> {
+		match &self.source {
+			AssetSource::Directory(directory) => Some(directory),
+			#[cfg(feature = "embed-assets")]
+			AssetSource::Embedded => None,
+		}
+	}
+	
+	pub fn read(&self, path: &str) -> Result<Cow<'static, [u8]>, AssetError> {
+		self.source.read(path)
+	}
+	
+	pub fn read_to_string(&self, path: &str) -> Result<String, AssetError> {
+		String::from_utf8(self.read(path)?.into_owned())
+			.map_err(|e| AssetError::Io { path: path.to_string(), error: std::io::Error::new(std::io::ErrorKind::InvalidData, e) })
+	}
+	
+	pub fn exists(&self, path: &str) -> bool {
+		self.source.exists(path)
+	}
+	
+	pub fn image(&self, path: &str) -> Result<RgbaImage, AssetError> {
+		self.source.image(path)
+	}
+	
+	// `textures/{name}.png`, as sRGB colors
+	pub fn texture(&mut self, display: &Display, name: &str) -> Result<Handle<SrgbTexture2d>, AssetError> {
+		self.textures.get_or_load(name, || {
+			let image = raw_image(self.source.image(&format!("textures/{name}.png"))?);
+			Ok(SrgbTexture2d::with_format(display, image, SrgbFormat::U8U8U8U8, MipmapsOption::NoMipmap).unwrap())
+		})
+	}
+	
+	// `textures/{name}.png` for textures that hold data rather than colors, like color grading LUTs, so sampling them doesn't convert from sRGB
+	pub fn linear_texture(&mut self, display: &Display, name: &str) -> Result<Handle<Texture2d>, AssetError> {
+		self.linear_textures.get_or_load(name, || {
+			let image = raw_image(self.source.image(&format!("textures/{name}.png"))?);
+			Ok(Texture2d::with_mipmaps(display, image, MipmapsOption::NoMipmap).unwrap())
+		})
+	}
+	
+	// A sprite sheet, a single image, or a directory with one image for each direction, all under `textures`
+	pub fn sprites(&mut self, display: &Display, name: &str) -> Result<SpriteSet, AssetError> {
+		let kind = *self.sprite_kinds.entry(name.to_string()).or_insert_with(|| {
+			match (self.source.exists(&format!("textures/{name}.anim")), self.source.exists(&format!("textures/{name}.png"))) {
+				(true, _) => SpriteKind::Sheet,
+				(false, true) => SpriteKind::Static,
+				(false, false) => SpriteKind::Directional,
+			}
+		});
+		
+		match kind {
+			SpriteKind::Sheet => Ok(SpriteSet::Animated(self.sprite_sheet(display, name)?)),
+			SpriteKind::Static => Ok(SpriteSet::Static(self.texture(display, name)?)),
+			SpriteKind::Directional => Ok(SpriteSet::Directional([
+				self.texture(display, &format!("{name}/up"))?,
+				self.texture(display, &format!("{name}/down"))?,
+				self.texture(display, &format!("{name}/left"))?,
+				self.texture(display, &format!("{name}/right"))?,
+			])),
+		}
+	}
+	
+	// `textures/{name}.png`, with the animations in it described by `textures/{name}.anim`
+	pub fn sprite_sheet(&mut self, display: &Display, name: &str) -> Result<Handle<SpriteSheet>, AssetError> {
+		if let Some(sheet) = self.sprite_sheets.get(name) {
+			return Ok(sheet)
+		}
+		let path = format!("textures/{name}.anim");
+		let layout = SheetLayout::parse(&self.read_to_string(&path)?).map_err(|e| AssetError::Invalid { path, message: e.to_string() })?;
+		let texture = self.texture(display, name)?;
+		self.sprite_sheets.get_or_load(name, || Ok(SpriteSheet { texture, layout }))
+	}
+	
+	// Called when the file at `path` changes, so the next request for it loads it again
+	pub fn changed(&mut self, path: &str) {
+		let Some(name) = path.strip_prefix("textures/") else { return };
+		if let Some(name) = name.strip_suffix(".png") {
+			self.textures.forget(name);
+			self.linear_textures.forget(name);
+			self.sprite_sheets.forget(name);
+			self.sprite_kinds.clear();
+		}
+		if let Some(name) = name.strip_suffix(".anim") {
+			self.sprite_sheets.forget(name);
+			self.sprite_kinds.clear();
+		}
+	}
+}
+
+
+
+#[cfg(test)]
+mod tests {
+	use super::*;
+	
+	#[test]
+	fn cache_shares_until_dropped() {
+		let mut cache = Cache::default();
+		let loads = std::cell::Cell::new(0);
+		let load = |cache: &mut Cache<String>| cache.get_or_load("player", || { loads.set(loads.get() + 1); Ok("sprite".to_string()) }).unwrap();
+		
+		let a = load(&mut cache);
+		let b = load(&mut cache);
+		assert!(Handle::ptr_eq(&a, &b));
+		assert_eq!(cache.prune(), 1);
+		
+		// Once every handle is gone the asset is too, and it's loaded again when asked for
+		drop((a, b));
+		assert_eq!(cache.prune(), 0);
+		let c = load(&mut cache);
+		
+		// Forgetting leaves existing handles alone
+		cache.forget("player");
+		let d = load(&mut cache);
+		assert!(!Handle::ptr_eq(&c, &d));
+		assert_eq!(*c, *d);
+		assert_eq!(loads.get(), 3);
+	}
+	
+	#[test]
+	fn load_errors_are_returned() {
+		let mut cache = Cache::<String>::default();
+		let error = cache.get_or_load("missing", || Err(AssetError::Io { path: "textures/missing.png".into(), error: std::io::ErrorKind::NotFound.into() })).unwrap_err();
+		assert!(error.is_not_found());
+		assert_eq!(error.path(), "textures/missing.png");
+		assert!(cache.get("missing").is_none());
+	}
+	
+	#[test]
+	fn assets_are_found_from_anywhere() {
+		let assets = Assets::locate();
+		assert!(assets.exists("config/controls.cfg"));
+		assert!(assets.exists("shaders/tilemap.frag"));
+		assert_eq!(assets.read_to_string("config/controls.cfg").unwrap(), DEFAULT_CONTROLS);
+		assert!(assets.read("config/missing.cfg").unwrap_err().is_not_found());
+	}
+}
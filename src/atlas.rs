@@ -0,0 +1,244 @@
+use std::collections::HashMap;
+
+use glium::{texture::{MipmapsOption, SrgbFormat, SrgbTexture2d}, Display};
+use image::RgbaImage;
+
+use crate::*;
+
+
+// Within the textures, holding the images named by `Material::texture`
+pub const TILE_TEXTURE_DIRECTORY: &str = "tiles";
+
+// Edge pixels repeated around each image. As images start on multiples of it, this many mipmap levels never mix two of them.
+const ATLAS_PADDING: u32 = 4;
+
+
+// Part of a texture, in texture coordinates
+#[derive(Copy, Clone, Debug, PartialEq)]
+pub struct UvRect {
+	pub min: Vec2<f32>,
+	pub max: Vec2<f32>,
+}
+
+impl UvRect {
+	pub const FULL: Self = Self { min: Vec2(0.0, 0.0), max: Vec2(1.0, 1.0) };
+	
+	// `uv` going from 0 to 1 across the rectangle
+	pub fn at(&self, uv: Vec2<f32>) -> Vec2<f32> {
+		self.min + (self.max - self.min).scale(uv)
+	}
+}
+
+
+// Where each face of each material is in the tile atlas. Faces that aren't listed cover the whole texture,
+// which is all meshes built without a window need.
+#[derive(Clone, Debug, Default)]
+pub struct TileUvs {
+	rects: HashMap<(Material, TileFace), UvRect>,
+}
+
+impl TileUvs {
+	pub fn get(&self, material: Material, face: TileFace) -> UvRect {
+		self.rects.get(&(material, face)).copied().unwrap_or(UvRect::FULL)
+	}
+}
+
+impl FromIterator<((Material, TileFace), UvRect)> for TileUvs {
+	fn from_iter<I: IntoIterator<Item = ((Material, TileFace), UvRect)>>(iter: I) -> Self {
+		Self { rects: iter.into_iter().collect() }
+	}
+}
+
+
+
+// MARK: Packing
+
+// Where images of the given sizes go in an atlas. They're placed in rows, tallest first, in the smallest power of two
+// sized atlas they fit in, and each one starts a multiple of `padding` pixels after the padding around it.
+#[derive(Clone, Debug, PartialEq, Eq)]
+pub struct AtlasLayout {
+	pub size: Vec2<u32>,
+	pub positions: Vec<Vec2<u32>>, // Of the images themselves, inside their padding
+	pub sizes: Vec<Vec2<u32>>,
+	pub padding: u32,
+}
+
+impl AtlasLayout {
+	pub fn pack(sizes: &[Vec2<u32>], padding: u32) -> Self {
+		let padded = sizes.iter().map(|size| size.map(|v| (v + 2 * padding).next_multiple_of(padding.max(1)))).collect::<Vec<_>>();
+		let mut order = (0..sizes.len()).collect::<Vec<_>>();
+		order.sort_by_key(|i| std::cmp::Reverse(padded[*i].y()));
+		
+		let mut size = Vec2(1, 1);
+		loop {
+			if let Some(positions) = place_in_rows(&padded, &order, size) {
+				return Self {
+					size,
+					positions: positions.into_iter().map(|position| position + Vec2(padding, padding)).collect(),
+					sizes: sizes.to_vec(),
+					padding,
+				}
+			}
+			if size.x() <= size.y() { size.0 *= 2 } else { size.1 *= 2 }
+		}
+	}
+	
+	pub fn uv(&self, index: usize) -> UvRect {
+		let atlas_size = self.size.as_type::<f32>();
+		let min = self.positions[index].as_type::<f32>();
+		UvRect {
+			min: min.scale_divide(atlas_size),
+			max: (min + self.sizes[index].as_type::<f32>()).scale_divide(atlas_size),
+		}
+	}
+	
+	// Draws the images where they were packed, their edges repeated out over the padding
+	pub fn compose(&self, images: &[RgbaImage]) -> RgbaImage {
+		let mut atlas = RgbaImage::new(self.size.x(), self.size.y());
+		for (image, position) in images.iter().zip(&self.positions) {
+			let (width, height) = image.dimensions();
+			for y in 0..height + 2 * self.padding {
+				for x in 0..width + 2 * self.padding {
+					let pixel = *image.get_pixel(x.saturating_sub(self.padding).min(width - 1), y.saturating_sub(self.padding).min(height - 1));
+					atlas.put_pixel(position.x() + x - self.padding, position.y() + y - self.padding, pixel);
+				}
+			}
+		}
+		atlas
+	}
+}
+
+// Positions of the top left corners, or None if they don't fit
+fn place_in_rows(padded: &[Vec2<u32>], order: &[usize], size: Vec2<u32>) -> Option<Vec<Vec2<u32>>> {
+	let mut positions = vec![Vec2(0, 0); padded.len()];
+	let (mut x, mut y, mut row_height) = (0, 0, 0);
+	
+	for &i in order {
+		if padded[i].x() > size.x() { return None }
+		if x + padded[i].x() > size.x() {
+			(x, y, row_height) = (0, y + row_height, 0);
+		}
+		if y + padded[i].y() > size.y() { return None }
+		
+		positions[i] = Vec2(x, y);
+		x += padded[i].x();
+		row_height = row_height.max(padded[i].y());
+	}
+	
+	Some(positions)
+}
+
+
+
+// MARK: Tile atlas
+
+pub struct TileAtlas {
+	pub texture: SrgbTexture2d,
+	pub uvs: TileUvs,
+}
+
+impl TileAtlas {
+	pub fn load(display: &Display, assets: &Assets) -> Result<Self, AssetError> {
+		let (image, uvs) = build_tile_atlas(assets)?;
+		let texture = SrgbTexture2d::with_format(display, raw_image(image), SrgbFormat::U8U8U8U8, MipmapsOption::AutoGeneratedMipmapsMax(ATLAS_PADDING.ilog2())).unwrap();
+		Ok(Self { texture, uvs })
+	}
+}
+
+// Packs the textures of every face of every material, each image once however many faces use it
+pub fn build_tile_atlas(assets: &Assets) -> Result<(RgbaImage, TileUvs), AssetError> {
+	let mut names = vec![];
+	let mut faces = vec![];
+	
+	for material in Material::ALL {
+		for face in TileFace::ALL {
+			let name = material.texture(face);
+			let index = names.iter().position(|n| *n == name).unwrap_or_else(|| {
+				names.push(name);
+				names.len() - 1
+			});
+			faces.push((material, face, index));
+		}
+	}
+	
+	let images = names.iter().map(|name| {
+		let path = format!("textures/{TILE_TEXTURE_DIRECTORY}/{name}.png");
+		let image = assets.image(&path)?;
+		if image.width() == 0 || image.height() == 0 {
+			return Err(AssetError::Invalid { path, message: "tile texture is empty".into() })
+		}
+		Ok(image)
+	}).collect::<Result<Vec<_>, _>>()?;
+	let layout = AtlasLayout::pack(&images.iter().map(|image| Vec2(image.width(), image.height())).collect::<Vec<_>>(), ATLAS_PADDING);
+	let uvs = faces.into_iter().map(|(material, face, index)| ((material, face), layout.uv(index))).collect();
+	Ok((layout.compose(&images), uvs))
+}
+
+
+
+#[cfg(test)]
+mod tests {
+	use super::*;
+	
+	#[test]
+	fn packed_images_are_aligned_and_apart() {
+		let sizes = [Vec2(16, 16), Vec2(16, 32), Vec2(8, 8), Vec2(16, 16), Vec2(30, 10), Vec2(16, 16)];
+		let layout = AtlasLayout::pack(&sizes, 4);
+		assert!(layout.size.x().is_power_of_two() && layout.size.y().is_power_of_two());
+		
+		let padded = |i: usize| (layout.positions[i] - Vec2(4, 4), layout.positions[i] + sizes[i] + Vec2(4, 4));
+		for i in 0..sizes.len() {
+			let (min, max) = padded(i);
+			assert!(min.x() % 4 == 0 && min.y() % 4 == 0, "{i}");
+			assert!(max.x() <= layout.size.x() && max.y() <= layout.size.y(), "{i}");
+			
+			for j in 0..i {
+				let (other_min, other_max) = padded(j);
+				assert!(max.x() <= other_min.x() || other_max.x() <= min.x() || max.y() <= other_min.y() || other_max.y() <= min.y(), "{i} overlaps {j}");
+			}
+		}
+		
+		let uv = layout.uv(1);
+		assert_eq!(uv.min.scale(layout.size.as_type::<f32>()), layout.positions[1].as_type::<f32>());
+		assert_eq!(uv.at(Vec2(1.0, 1.0)).scale(layout.size.as_type::<f32>()), (layout.positions[1] + sizes[1]).as_type::<f32>());
+	}
+	
+	#[test]
+	fn padding_repeats_edges() {
+		let mut image = RgbaImage::new(2, 2);
+		image.put_pixel(0, 0, image::Rgba([255, 0, 0, 255]));
+		image.put_pixel(1, 1, image::Rgba([0, 0, 255, 255]));
+		
+		let layout = AtlasLayout::pack(&[Vec2(2, 2)], 2);
+		assert_eq!(layout.size, Vec2(8, 8));
+		assert_eq!(layout.positions, [Vec2(2, 2)]);
+		
+		let atlas = layout.compose(&[image]);
+		assert_eq!(atlas.get_pixel(0, 0).0, [255, 0, 0, 255]);
+		assert_eq!(atlas.get_pixel(2, 0).0, [255, 0, 0, 255]);
+		assert_eq!(atlas.get_pixel(5, 5).0, [0, 0, 255, 255]);
+		assert_eq!(atlas.get_pixel(3, 0).0, [0, 0, 0, 0]);
+	}
+	
+	#[test]
+	fn every_material_is_in_the_atlas() {
+		let assets = Assets::new(AssetSource::Directory(std::path::Path::new(env!("CARGO_MANIFEST_DIR")).join("assets")));
+		let (image, uvs) = build_tile_atlas(&assets).unwrap_or_else(|e| panic!("{e}"));
+		
+		for material in Material::ALL {
+			let rect = uvs.get(material, TileFace::Side);
+			assert_ne!(rect, UvRect::FULL, "{material:?}");
+			assert!(rect.min.x() >= 0.0 && rect.min.y() >= 0.0 && rect.max.x() <= 1.0 && rect.max.y() <= 1.0, "{material:?}");
+			assert!(rect.min.x() < rect.max.x() && rect.min.y() < rect.max.y(), "{material:?}");
+		}
+		assert_ne!(uvs.get(Grass, TileFace::Side), uvs.get(Stone, TileFace::Side));
+		
+		// Faces sharing an image share its place in the atlas
+		assert_ne!(uvs.get(Grass, TileFace::Top), uvs.get(Grass, TileFace::Side));
+		assert_eq!(uvs.get(Grass, TileFace::Top), uvs.get(Grass, TileFace::Slope));
+		assert_eq!(uvs.get(Grass, TileFace::Bottom), uvs.get(Dirt, TileFace::Top));
+		assert!(image.width().is_power_of_two() && image.height().is_power_of_two());
+		
+		assert_eq!(TileUvs::default().get(Grass, TileFace::Top), UvRect::FULL);
+	}
+}
@@ -0,0 +1,297 @@
+use std::f32::consts::PI;
+
+use crate::*;
+
+
+// Pitch that makes the three axes appear the same length on screen
+pub const ISOMETRIC_PITCH: f32 = 0.615_479_7; // atan(1 / sqrt(2))
+
+pub const NEAR_PLANE: f32 = 0.05;
+const FOLLOW_MARGIN: f64 = 0.2;
+
+
+#[derive(Copy, Clone, Debug, PartialEq, Eq)]
+pub enum CameraMode {
+	FirstPerson,
+	Follow,
+	TopDown,
+	Isometric,
+}
+
+impl CameraMode {
+	pub fn next(self) -> Self {
+		match self {
+			Self::FirstPerson => Self::Follow,
+			Self::Follow => Self::TopDown,
+			Self::TopDown => Self::Isometric,
+			Self::Isometric => Self::FirstPerson,
+		}
+	}
+	
+	pub fn is_perspective(self) -> bool {
+		matches!(self, Self::FirstPerson | Self::Follow)
+	}
+}
+
+
+// Where the world is viewed from. Rendering is done relative to `position` so f32 precision holds up far
+// from the origin, which is why the view matrix only rotates.
+pub struct Camera {
+	pub mode: CameraMode,
+	pub position: Vec3<f64>,
+	pub yaw: f32,
+	pub pitch: f32,
+	pub aspect_ratio: f32,
+	
+	pub fov: f32, // Vertical, in radians
+	pub tile_size: f32, // Orthographic zoom, as a fraction of the screen width per tile
+	pub tile_depth: f32, // Orthographic depth range, as a fraction of the depth buffer per tile
+	
+	pub follow_distance: f64,
+	pub follow_smoothing: f64, // Rate at which the follow camera closes the distance to where it should be, per second
+	pub view_distance: f64, // Cells further than this many tiles away aren't drawn
+	pub lod_distance: f64, // Distance in tiles at which cells switch to the next coarser mesh
+	snap: bool,
+}
+
+impl Camera {
+	pub fn new(mode: CameraMode, aspect_ratio: f32) -> Self {
+		Self {
+			mode,
+			position: Vec3(0.0, 0.0, 0.0),
+			yaw: 0.0,
+			pitch: 0.0,
+			aspect_ratio,
+			
+			fov: 80.0 * PI / 180.0,
+			tile_size: 1.0 / 30.0,
+			tile_depth: 1.0 / 128.0,
+			
+			follow_distance: 6.0,
+			follow_smoothing: 10.0,
+			view_distance: LOAD_DISTANCE * CELL_WIDTH as f64,
+			lod_distance: 48.0,
+			snap: true,
+		}
+	}
+	
+	pub fn set_mode(&mut self, mode: CameraMode) {
+		self.mode = mode;
+		self.snap = true;
+	}
+	
+	pub fn zoom(&mut self, factor: f32) {
+		match self.mode {
+			CameraMode::FirstPerson => self.fov = (self.fov / factor).clamp(10.0 * PI / 180.0, 120.0 * PI / 180.0),
+			CameraMode::Follow => self.follow_distance = (self.follow_distance / factor as f64).clamp(1.0, 30.0),
+			CameraMode::TopDown | CameraMode::Isometric => self.tile_size *= factor,
+		}
+	}
+	
+	// Moves the camera to view the entity at `focus` and `height` tall with the given view angles, `dt` being the time since the last update
+	pub fn update(&mut self, cells: &HashMap<Vec3<isize>, Cell>, focus: Vec3<f64>, height: f64, yaw: f32, pitch: f32, dt: f64) {
+		let eye = focus + Vec3(0.0, 0.0, height * 0.8);
+		self.yaw = yaw;
+		
+		match self.mode {
+			CameraMode::FirstPerson => {
+				self.pitch = pitch;
+				self.position = eye;
+			}
+			CameraMode::TopDown => {
+				self.pitch = 0.5 * PI;
+				self.position = focus;
+			}
+			CameraMode::Isometric => {
+				self.pitch = ISOMETRIC_PITCH;
+				self.position = focus;
+			}
+			CameraMode::Follow => {
+				self.pitch = pitch;
+				let desired = eye - self.forward().as_type::<f64>() * self.follow_distance;
+				
+				self.position = match self.snap {
+					true => desired,
+					false => lerp(self.position, desired, 1.0 - (-self.follow_smoothing * dt).exp()),
+				};
+				
+				// Pull in in front of any tiles between the focus and the camera
+				let ray = Ray::between(eye, self.position);
+				if let Some((_, hit)) = raycast::cast_ray(cells, ray) {
+					let distance = (hit - eye).length();
+					self.position = eye + ray.direction.normalize_or_zero() * (distance - FOLLOW_MARGIN).max(0.0);
+				}
+			}
+		}
+		
+		self.snap = false;
+	}
+	
+	pub fn forward(&self) -> Vec3<f32> {
+		Vec3(
+			-self.yaw.sin() * self.pitch.cos(),
+			-self.yaw.cos() * self.pitch.cos(),
+			-self.pitch.sin(),
+		)
+	}
+	
+	pub fn right(&self) -> Vec3<f32> {
+		Vec3(self.yaw.cos(), -self.yaw.sin(), 0.0)
+	}
+	
+	pub fn up(&self) -> Vec3<f32> {
+		// World Y points down the screen, so the world is left handed and the cross product flips
+		self.forward().cross(self.right())
+	}
+	
+	// Position of a point relative to the camera, which is what's passed to shaders as `render_position`
+	pub fn relative(&self, position: Vec3<f64>) -> Vec3<f32> {
+		(position - self.position).as_type()
+	}
+	
+	// Turns something facing +Y to face the camera, staying upright
+	pub fn billboard_orientation(&self) -> Mat3<f32> {
+		let right = self.right();
+		Mat3::from_cols(right, Vec3(-right.y(), right.x(), 0.0), Vec3::Z)
+	}
+	
+	pub fn view_matrix(&self) -> Mat4<f32> {
+		Mat4::from_mat3(Mat3::from_rows(self.right(), self.up(), -self.forward()))
+	}
+	
+	// Depth is reversed, nearer points get larger values, to match the depth test and post processing
+	pub fn projection_matrix(&self) -> Mat4<f32> {
+		match self.mode.is_perspective() {
+			true => Mat4::perspective_infinite_reversed(self.fov, self.aspect_ratio, NEAR_PLANE),
+			false => Mat4::from_scale(Vec3(self.tile_size, self.tile_size * self.aspect_ratio, self.tile_depth) * 2.0),
+		}
+	}
+	
+	pub fn view_projection(&self) -> Mat4<f32> {
+		self.projection_matrix() * self.view_matrix()
+	}
+	
+	// In coordinates relative to the camera, like everything else that's drawn
+	pub fn frustum(&self) -> Frustum<f32> {
+		Frustum::from_matrix(self.view_projection())
+	}
+}
+
+
+
+#[cfg(test)]
+mod tests {
+	use super::*;
+	
+	const EPSILON: f32 = 1e-5;
+	
+	fn transform_point(m: Mat4<f32>, p: Vec3<f32>) -> [f32; 4] {
+		m.transform([p.0, p.1, p.2, 1.0])
+	}
+	
+	fn clip_to_ndc(clip: [f32; 4]) -> Vec3<f32> {
+		Vec3(clip[0], clip[1], clip[2]) / clip[3]
+	}
+	
+	fn camera(mode: CameraMode, yaw: f32, pitch: f32) -> Camera {
+		let mut camera = Camera::new(mode, 16.0 / 9.0);
+		camera.yaw = yaw;
+		camera.pitch = pitch;
+		camera
+	}
+	
+	#[test]
+	fn view_maps_forward_to_negative_z() {
+		for (yaw, pitch) in [(0.0, 0.0), (1.0, 0.3), (-2.5, -1.2), (0.4, 0.5 * PI)] {
+			let camera = camera(CameraMode::FirstPerson, yaw, pitch);
+			let view = camera.view_matrix();
+			
+			let forward = transform_point(view, camera.forward());
+			let right = transform_point(view, camera.right());
+			assert!((forward[2] + 1.0).abs() < EPSILON && forward[0].abs() < EPSILON && forward[1].abs() < EPSILON);
+			assert!((right[0] - 1.0).abs() < EPSILON);
+			
+			// Up on screen is always towards +Z in the world, unless looking straight down
+			if pitch.abs() < 1.5 {
+				assert!(transform_point(view, Vec3(0.0, 0.0, 1.0))[1] > 0.0);
+			}
+		}
+	}
+	
+	#[test]
+	fn top_down_matches_map_orientation() {
+		let camera = camera(CameraMode::TopDown, 0.0, 0.5 * PI);
+		let vp = camera.view_projection();
+		
+		// World Y increases down the screen and X to the right
+		assert!(transform_point(vp, Vec3(0.0, 1.0, 0.0))[1] < 0.0);
+		assert!(transform_point(vp, Vec3(1.0, 0.0, 0.0))[0] > 0.0);
+		assert!((transform_point(vp, Vec3(1.0, 0.0, 0.0))[0] - 2.0 * camera.tile_size).abs() < EPSILON);
+	}
+	
+	#[test]
+	fn nearer_points_have_greater_depth() {
+		for mode in [CameraMode::FirstPerson, CameraMode::Follow, CameraMode::TopDown, CameraMode::Isometric] {
+			let pitch = if mode.is_perspective() { 0.3 } else { 0.5 * PI };
+			let camera = camera(mode, 0.7, pitch);
+			let vp = camera.view_projection();
+			
+			let near = clip_to_ndc(transform_point(vp, camera.forward() * 2.0));
+			let far = clip_to_ndc(transform_point(vp, camera.forward() * 20.0));
+			assert!(near.z() > far.z(), "{mode:?}");
+			assert!(near.x().abs() < EPSILON && near.y().abs() < EPSILON, "{mode:?}");
+		}
+	}
+	
+	#[test]
+	fn perspective_fov_reaches_screen_edge() {
+		let camera = camera(CameraMode::FirstPerson, 0.0, 0.0);
+		let vp = camera.view_projection();
+		
+		let edge = camera.forward() + camera.up() * (0.5 * camera.fov).tan();
+		assert!((clip_to_ndc(transform_point(vp, edge)).y() - 1.0).abs() < EPSILON);
+	}
+	
+	#[test]
+	fn billboards_face_the_camera() {
+		for yaw in [0.0, 1.0, -2.5] {
+			let camera = camera(CameraMode::Follow, yaw, 0.4);
+			let orientation = Mat4::from_mat3(camera.billboard_orientation());
+			let normal = orientation.transform_vector(Vec3(0.0, 1.0, 0.0));
+			let horizontal_forward = camera.forward().with_z(0.0).normalize();
+			assert!((normal + horizontal_forward).length() < EPSILON, "{yaw}");
+			assert!((orientation.transform_vector(Vec3(1.0, 0.0, 0.0)) - camera.right()).length() < EPSILON);
+			assert_eq!(orientation.transform_vector(Vec3(0.0, 0.0, 1.0)), Vec3(0.0, 0.0, 1.0));
+		}
+	}
+	
+	#[test]
+	fn follow_camera_smooths_and_avoids_walls() {
+		let mut tiles = Cell::empty_tiles();
+		for x in 0..CELL_WIDTH {
+			for z in 0..CELL_HEIGHT {
+				tiles[Vec3(x, 20, z)] = Tile::full(Brick);
+			}
+		}
+		let mut cells = HashMap::new();
+		cells.insert(Vec3(0, 0, 0), Cell::new(tiles));
+		
+		let mut focus = Vec3(16.0, 10.0, 4.0);
+		let mut camera = Camera::new(CameraMode::Follow, 1.0);
+		
+		// Looking towards -Y, so the camera sits behind the focus at +Y, in open space
+		camera.update(&cells, focus, 1.75, 0.0, 0.0, 0.01);
+		assert!((camera.position.y() - 16.0).abs() < 1e-9);
+		
+		// Moving the focus only moves the camera part of the way
+		focus += Vec3(0.0, 1.0, 0.0);
+		camera.update(&cells, focus, 1.75, 0.0, 0.0, 0.01);
+		assert!(camera.position.y() > 16.0 && camera.position.y() < 17.0);
+		
+		// With the wall at y = 20 between the focus and where the camera wants to be, it stops in front of it
+		focus = Vec3(16.0, 17.0, 4.0);
+		camera.set_mode(CameraMode::Follow);
+		camera.update(&cells, focus, 1.75, 0.0, 0.0, 0.01);
+		assert!(camera.position.y() < 20.0 && camera.position.y() > 19.0);
+	}
+}
@@ -0,0 +1,132 @@
+use crate::*;
+
+
+// How many cells the last culling pass kept and skipped, and how many were drawn at each level of detail
+#[derive(Copy, Clone, Debug, Default, PartialEq, Eq)]
+pub struct CullStats {
+	pub drawn: usize,
+	pub culled: usize,
+	pub lods: [usize; LOD_FACTORS.len()],
+}
+
+pub struct VisibleCell<'a> {
+	pub location: Vec3<isize>,
+	pub cell: &'a Cell,
+	pub lod: usize,
+}
+
+// Cells within the camera's view distance that are at least partly inside its frustum, along with the
+// level of detail to draw them at. Only the cell bounds are looked at, so this runs without a GPU.
+pub fn visible_cells<'a>(cells: &'a HashMap<Vec3<isize>, Cell>, camera: &Camera) -> (Vec<VisibleCell<'a>>, CullStats) {
+	let frustum = camera.frustum();
+	let mut stats = CullStats::default();
+	
+	let visible = cells.iter().filter_map(|(location, cell)| {
+		let bounds = Cell::bounds(*location);
+		let distance = (bounds.closest_point(camera.position) - camera.position).length();
+		let in_view = distance <= camera.view_distance
+			&& frustum.intersects_aabb(Aabb::new(camera.relative(bounds.min), camera.relative(bounds.max)));
+		
+		if !in_view {
+			stats.culled += 1;
+			return None
+		}
+		
+		let lod = lod_for_distance(distance, camera.lod_distance);
+		stats.drawn += 1;
+		stats.lods[lod] += 1;
+		Some(VisibleCell { location: *location, cell, lod })
+	}).collect();
+	
+	(visible, stats)
+}
+
+
+
+#[cfg(test)]
+mod tests {
+	use super::*;
+	
+	// A square of empty cells centered on the origin cell
+	fn cells(radius: isize) -> HashMap<Vec3<isize>, Cell> {
+		Vec3Range::<isize, ZYX>::inclusive(Vec3(-radius, -radius, 0), Vec3(radius, radius, 0))
+			.map(|location| (location, Cell::new(Cell::empty_tiles())))
+			.collect()
+	}
+	
+	fn locations(visible: &[VisibleCell]) -> Vec<Vec3<isize>> {
+		visible.iter().map(|v| v.location).collect()
+	}
+	
+	#[test]
+	fn culls_cells_behind_the_camera() {
+		let cells = cells(2);
+		let mut camera = Camera::new(CameraMode::FirstPerson, 16.0 / 9.0);
+		camera.position = Vec3(16.0, 16.0, 8.0);
+		// Yaw 0 looks towards -Y
+		camera.yaw = 0.0;
+		camera.pitch = 0.0;
+		
+		let (visible, stats) = visible_cells(&cells, &camera);
+		let visible = locations(&visible);
+		assert_eq!(stats.drawn + stats.culled, cells.len());
+		assert_eq!(stats.drawn, visible.len());
+		
+		assert!(visible.contains(&Vec3(0, 0, 0)), "The cell the camera is in was culled");
+		assert!(visible.contains(&Vec3(0, -2, 0)));
+		assert!(!visible.contains(&Vec3(0, 2, 0)));
+		assert!(!visible.contains(&Vec3(-2, 2, 0)));
+		assert!(stats.culled > 0);
+	}
+	
+	#[test]
+	fn view_distance_limits_drawn_cells() {
+		let cells = cells(3);
+		let mut camera = Camera::new(CameraMode::FirstPerson, 1.0);
+		camera.position = Vec3(16.0, 16.0, 8.0);
+		camera.pitch = 0.0;
+		
+		let (far, _) = visible_cells(&cells, &camera);
+		assert!(locations(&far).contains(&Vec3(0, -3, 0)));
+		
+		camera.view_distance = 40.0;
+		let (near, stats) = visible_cells(&cells, &camera);
+		let near = locations(&near);
+		assert!(near.contains(&Vec3(0, -1, 0)));
+		assert!(!near.contains(&Vec3(0, -2, 0)), "Cell 50 tiles away should be out of range");
+		assert_eq!(stats.drawn + stats.culled, cells.len());
+	}
+	
+	#[test]
+	fn top_down_sees_surrounding_cells() {
+		let cells = cells(2);
+		let mut camera = Camera::new(CameraMode::TopDown, 16.0 / 9.0);
+		camera.position = Vec3(2.0, 3.0, 20.0);
+		camera.pitch = 0.5 * std::f32::consts::PI;
+		
+		// The default zoom shows 15 tiles either side, and less vertically, so near a corner of the
+		// center cell only that corner's neighbors show
+		let visible = locations(&visible_cells(&cells, &camera).0);
+		for location in [Vec3(0, 0, 0), Vec3(-1, 0, 0), Vec3(0, -1, 0), Vec3(-1, -1, 0)] {
+			assert!(visible.contains(&location), "{location:?} was culled");
+		}
+		assert!(!visible.contains(&Vec3(1, 0, 0)) && !visible.contains(&Vec3(0, 1, 0)));
+	}
+	
+	#[test]
+	fn farther_cells_get_coarser_detail() {
+		let cells = cells(3);
+		let mut camera = Camera::new(CameraMode::FirstPerson, 1.0);
+		camera.position = Vec3(16.0, 16.0, 8.0);
+		camera.pitch = 0.0;
+		camera.lod_distance = 20.0;
+		
+		let (visible, stats) = visible_cells(&cells, &camera);
+		let lod_of = |location| visible.iter().find(|v| v.location == location).unwrap().lod;
+		assert_eq!(lod_of(Vec3(0, 0, 0)), 0);
+		assert_eq!(lod_of(Vec3(0, -1, 0)), 0); // 16 tiles away
+		assert_eq!(lod_of(Vec3(1, -1, 0)), 1); // 22.6 tiles away
+		assert_eq!(lod_of(Vec3(0, -2, 0)), 2); // 48 tiles away
+		assert_eq!(stats.lods.iter().sum::<usize>(), stats.drawn);
+	}
+}
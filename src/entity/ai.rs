@@ -0,0 +1,359 @@
+use crate::*;
+
+
+
+const DEFAULT_SIGHT_RANGE: f64 = 24.0;
+const EYE_HEIGHT: f64 = 0.9; // Fraction of the body's height
+
+const WANDER_PAUSE_TICKS: u32 = 90; // Standing around after each walk
+const REPLAN_TICKS: u32 = 30; // Before trying again when there was no path
+
+// Utility scores. The highest scoring behavior runs, and the earliest in the list on ties.
+const FLEE_SCORE: f64 = 3.0;
+const FOLLOW_SCORE: f64 = 2.0;
+const ROAM_SCORE: f64 = 1.0;
+const IDLE_SCORE: f64 = 0.0;
+const UNAVAILABLE: f64 = f64::NEG_INFINITY;
+
+
+// One thing an AI can be doing
+#[derive(Clone, Debug, PartialEq)]
+#[cfg_attr(feature = "serde", derive(serde::Serialize, serde::Deserialize))]
+pub enum AiBehavior {
+	Idle,
+	Wander { home: Vec3<f64>, radius: f64 }, // Walks to random places within the radius of home, pausing in between
+	Follow { target: EntityId, distance: f64 }, // Walks towards the target while it can see it, until within the distance of it
+	Flee { from: EntityId, distance: f64 }, // Runs from the entity while it can see it within the distance
+	Patrol { waypoints: Vec<Vec3<f64>>, next: usize }, // Walks to each waypoint in turn, looping back to the first
+	FollowPath(PathFollower), // Stops at the end
+}
+
+// What an AI can find out about the world around it
+pub struct Surroundings<'a> {
+	pub cells: &'a HashMap<Vec3<isize>, Cell>,
+	pub transforms: &'a Storage<Transform>,
+	pub bodies: &'a Storage<Body>,
+}
+
+impl Surroundings<'_> {
+	fn eyes(&self, id: EntityId) -> Option<Vec3<f64>> {
+		let height = self.bodies.get(id).map_or(1.0, |body| body.size.z());
+		Some(self.transforms.get(id)?.position + Vec3(0.0, 0.0, height * EYE_HEIGHT))
+	}
+	
+	// Where `target` is, if `id` can see it from no further than `range`
+	pub fn sees(&self, id: EntityId, target: EntityId, range: f64) -> Option<Vec3<f64>> {
+		let (from, to) = (self.eyes(id)?, self.eyes(target)?);
+		let visible = (to - from).length() <= range && line_of_sight(self.cells, from, to);
+		visible.then(|| self.transforms.get(target).unwrap().position)
+	}
+}
+
+// Whether no tile is in the way between the two points. Unloaded tiles don't block it.
+pub fn line_of_sight(cells: &HashMap<Vec3<isize>, Cell>, from: Vec3<f64>, to: Vec3<f64>) -> bool {
+	raycast::cast_ray(cells, Ray::between(from, to)).is_none()
+}
+
+
+
+// MARK: Ai
+
+// Steers an entity by itself, scoring each of its behaviors every tick and running the best one
+#[derive(Clone, Debug, PartialEq)]
+#[cfg_attr(feature = "serde", derive(serde::Serialize, serde::Deserialize))]
+pub struct Ai {
+	pub behaviors: Vec<AiBehavior>,
+	pub sight_range: f64,
+	active: Option<usize>,
+	path: Option<PathFollower>, // Towards the active behavior's current goal
+	pause: u32, // Ticks left to stand still for
+	rolls: u64, // Random numbers drawn so far
+}
+
+impl Ai {
+	pub fn new(behaviors: Vec<AiBehavior>) -> Self {
+		Self { behaviors, sight_range: DEFAULT_SIGHT_RANGE, active: None, path: None, pause: 0, rolls: 0 }
+	}
+	
+	// The behavior that ran last tick
+	pub fn active(&self) -> Option<&AiBehavior> {
+		self.behaviors.get(self.active?)
+	}
+	
+	// Movement input and whether to jump, for the entity `id`
+	pub fn think(&mut self, id: EntityId, surroundings: &Surroundings) -> (Vec3<f64>, bool) {
+		let Some(transform) = surroundings.transforms.get(id) else { return (Vec3::ZERO, false) };
+		let position = transform.position;
+		let contact = surroundings.bodies.get(id).map_or(ContactState::Airborne, |body| body.contact);
+		
+		let best = (0..self.behaviors.len())
+			.map(|i| (i, self.score(i, id, surroundings)))
+			.fold(None, |best: Option<(usize, f64)>, (i, score)| if best.is_none_or(|(_, b)| score > b) { Some((i, score)) } else { best })
+			.filter(|(_, score)| *score > UNAVAILABLE)
+			.map(|(i, _)| i);
+		
+		// Whatever the previous behavior was walking to doesn't matter to the next one
+		if best != self.active {
+			self.active = best;
+			self.path = None;
+			self.pause = 0;
+		}
+		let Some(active) = best else { return (Vec3::ZERO, false) };
+		
+		match self.behaviors[active].clone() {
+			AiBehavior::Idle => (Vec3::ZERO, false),
+			AiBehavior::Follow { target, distance } => {
+				let Some(target) = surroundings.sees(id, target, self.sight_range) else { return (Vec3::ZERO, false) };
+				let offset = (target - position).with_z(0.0);
+				(if offset.length() > distance { offset.normalize() } else { Vec3::ZERO }, false)
+			}
+			AiBehavior::Flee { from, .. } => {
+				let Some(from) = surroundings.sees(id, from, self.sight_range) else { return (Vec3::ZERO, false) };
+				let away = (position - from).with_z(0.0);
+				(if away == Vec3::ZERO { Vec3::X } else { away.normalize() }, false)
+			}
+			AiBehavior::Wander { home, radius } => {
+				let planning = self.path.is_none() && self.pause == 0;
+				let goal = if planning { home + Vec3(self.random(id) * 2.0 - 1.0, self.random(id) * 2.0 - 1.0, 0.0) * radius } else { home };
+				let (steering, arrived) = self.walk_to(id, goal, surroundings);
+				if arrived {
+					self.pause = WANDER_PAUSE_TICKS;
+				}
+				steering
+			}
+			AiBehavior::Patrol { waypoints, next } => {
+				let Some(&goal) = waypoints.get(next) else { return (Vec3::ZERO, false) };
+				let (steering, arrived) = self.walk_to(id, goal, surroundings);
+				if arrived {
+					let AiBehavior::Patrol { next, .. } = &mut self.behaviors[active] else { unreachable!() };
+					*next = (*next + 1) % waypoints.len();
+				}
+				steering
+			}
+			AiBehavior::FollowPath(_) => {
+				let AiBehavior::FollowPath(follower) = &mut self.behaviors[active] else { unreachable!() };
+				follower.steer(position, contact)
+			}
+		}
+	}
+	
+	fn score(&self, i: usize, id: EntityId, surroundings: &Surroundings) -> f64 {
+		match &self.behaviors[i] {
+			AiBehavior::Idle => IDLE_SCORE,
+			AiBehavior::Follow { target, .. } => match surroundings.sees(id, *target, self.sight_range) {
+				Some(_) => FOLLOW_SCORE,
+				None => UNAVAILABLE,
+			},
+			AiBehavior::Flee { from, distance } => match surroundings.sees(id, *from, self.sight_range.min(*distance)) {
+				Some(_) => FLEE_SCORE,
+				None => UNAVAILABLE,
+			},
+			AiBehavior::Wander { .. } | AiBehavior::Patrol { .. } => ROAM_SCORE,
+			AiBehavior::FollowPath(follower) => if follower.finished() { UNAVAILABLE } else { ROAM_SCORE },
+		}
+	}
+	
+	// Steering along a path to `goal`, planning one first if there isn't one, and whether the end was reached this tick.
+	// `goal` is only used for planning, so it can change between calls. Waits a while before planning again if there was no path.
+	fn walk_to(&mut self, id: EntityId, goal: Vec3<f64>, surroundings: &Surroundings) -> ((Vec3<f64>, bool), bool) {
+		if self.pause > 0 {
+			self.pause -= 1;
+			return ((Vec3::ZERO, false), false)
+		}
+		let (Some(transform), Some(body)) = (surroundings.transforms.get(id), surroundings.bodies.get(id)) else { return ((Vec3::ZERO, false), false) };
+		
+		if self.path.is_none() {
+			match find_path(surroundings.cells, &NavAgent::new(body.size), transform.position, goal) {
+				Some(path) => self.path = Some(PathFollower::new(path)),
+				None => {
+					self.pause = REPLAN_TICKS;
+					return ((Vec3::ZERO, false), false)
+				}
+			}
+		}
+		
+		let follower = self.path.as_mut().unwrap();
+		let steering = follower.steer(transform.position, body.contact);
+		if follower.finished() {
+			self.path = None;
+		}
+		(steering, self.path.is_none())
+	}
+	
+	// Uniform in 0..1, the same for the same entity on every run
+	fn random(&mut self, id: EntityId) -> f64 {
+		self.rolls += 1;
+		let mut z = id.0.wrapping_mul(0x9E37_79B9_7F4A_7C15) ^ self.rolls.wrapping_mul(0xBF58_476D_1CE4_E5B9);
+		z = (z ^ (z >> 30)).wrapping_mul(0xBF58_476D_1CE4_E5B9);
+		z = (z ^ (z >> 27)).wrapping_mul(0x94D0_49BB_1331_11EB);
+		z ^= z >> 31;
+		(z >> 11) as f64 / (1u64 << 53) as f64
+	}
+	
+	pub fn hash_state(&self, hasher: &mut StateHasher) {
+		hasher.write_u64(self.behaviors.len() as u64);
+		for behavior in &self.behaviors {
+			match behavior {
+				AiBehavior::Idle => hasher.write_u8(0),
+				AiBehavior::Follow { target, distance } => {
+					hasher.write_u8(1);
+					hasher.write_u64(target.0);
+					hasher.write_f64(*distance);
+				}
+				AiBehavior::FollowPath(follower) => {
+					hasher.write_u8(2);
+					hash_follower(hasher, follower);
+				}
+				AiBehavior::Wander { home, radius } => {
+					hasher.write_u8(3);
+					hasher.write_vec3(*home);
+					hasher.write_f64(*radius);
+				}
+				AiBehavior::Flee { from, distance } => {
+					hasher.write_u8(4);
+					hasher.write_u64(from.0);
+					hasher.write_f64(*distance);
+				}
+				AiBehavior::Patrol { waypoints, next } => {
+					hasher.write_u8(5);
+					hasher.write_u64(waypoints.len() as u64);
+					waypoints.iter().for_each(|waypoint| hasher.write_vec3(*waypoint));
+					hasher.write_u64(*next as u64);
+				}
+			}
+		}
+		
+		hasher.write_f64(self.sight_range);
+		hasher.write_u64(self.active.map_or(u64::MAX, |active| active as u64));
+		hasher.write_bool(self.path.is_some());
+		if let Some(follower) = &self.path {
+			hash_follower(hasher, follower);
+		}
+		hasher.write_u64(self.pause as u64);
+		hasher.write_u64(self.rolls);
+	}
+}
+
+fn hash_follower(hasher: &mut StateHasher, follower: &PathFollower) {
+	hasher.write_u64(follower.path.waypoints.len() as u64);
+	hasher.write_u64(follower.next as u64);
+}
+
+
+
+#[cfg(test)]
+mod tests {
+	use super::*;
+	
+	const SIZE: Vec3<f64> = Vec3(0.7, 0.7, 1.75);
+	
+	// A stone floor at z = 0, with walls two tiles high in the given columns
+	fn floor(walls: &[Vec2<usize>]) -> HashMap<Vec3<isize>, Cell> {
+		let mut tiles = Cell::empty_tiles();
+		for pos in Vec3Range::<usize, ZYX>::exclusive(Vec3::ZERO, Vec3(CELL_WIDTH, CELL_WIDTH, 1)) {
+			tiles[pos] = Tile::full(Stone);
+		}
+		for wall in walls {
+			tiles[wall.with_z(1)] = Tile::full(Stone);
+			tiles[wall.with_z(2)] = Tile::full(Stone);
+		}
+		HashMap::from([(Vec3(0, 0, 0), Cell::new(tiles))])
+	}
+	
+	fn active(entities: &Entities, id: EntityId) -> Option<AiBehavior> {
+		match entities.controllers.get(id) {
+			Some(Controller::Ai(ai)) => ai.active().cloned(),
+			_ => None,
+		}
+	}
+	
+	fn position(entities: &Entities, id: EntityId) -> Vec3<f64> {
+		entities.transforms.get(id).unwrap().position
+	}
+	
+	#[test]
+	fn walls_block_sight() {
+		let wall = (10..20).map(|y| Vec2(16, y)).collect::<Vec<_>>();
+		let mut entities = Entities::default();
+		let target = entities.spawn_character(Vec3(20.5, 15.5, 1.0), SIZE, SpriteSet::None);
+		let follower = entities.spawn_character(Vec3(10.5, 15.5, 1.0), SIZE, SpriteSet::None);
+		entities.controllers.insert(follower, Controller::Ai(Ai::new(vec![AiBehavior::Idle, AiBehavior::Follow { target, distance: 1.0 }])));
+		
+		controller_system(&mut entities, &floor(&wall));
+		assert_eq!(active(&entities, follower), Some(AiBehavior::Idle));
+		assert_eq!(entities.movement.get(follower).unwrap().input, Vec3::ZERO);
+		
+		controller_system(&mut entities, &floor(&[]));
+		assert!(matches!(active(&entities, follower), Some(AiBehavior::Follow { .. })));
+		assert_eq!(entities.movement.get(follower).unwrap().input, Vec3::X);
+		
+		// Nor can anything past the sight range be seen
+		let surroundings = Surroundings { cells: &floor(&[]), transforms: &entities.transforms, bodies: &entities.bodies };
+		assert!(surroundings.sees(follower, target, 10.5).is_some());
+		assert!(surroundings.sees(follower, target, 9.5).is_none());
+	}
+	
+	#[test]
+	fn fleeing_comes_before_following() {
+		let cells = floor(&[]);
+		let mut entities = Entities::default();
+		let threat = entities.spawn_character(Vec3(10.5, 15.5, 1.0), SIZE, SpriteSet::None);
+		let npc = entities.spawn_character(Vec3(12.5, 15.5, 1.0), SIZE, SpriteSet::None);
+		let behaviors = vec![AiBehavior::Follow { target: threat, distance: 1.0 }, AiBehavior::Flee { from: threat, distance: 6.0 }];
+		entities.controllers.insert(npc, Controller::Ai(Ai::new(behaviors)));
+		
+		controller_system(&mut entities, &cells);
+		assert!(matches!(active(&entities, npc), Some(AiBehavior::Flee { .. })));
+		assert_eq!(entities.movement.get(npc).unwrap().input, Vec3::X);
+		
+		// It runs until out of the flee distance, then turns back to follow, so it ends up hovering around the edge of it
+		for _ in 0..300 {
+			run_systems(&mut entities, &cells, TICK_DT);
+		}
+		let distance = (position(&entities, npc) - position(&entities, threat)).length();
+		assert!(distance > 4.0 && distance < 8.0, "Ended up {distance} away");
+	}
+	
+	#[test]
+	fn patrols_loop_through_waypoints() {
+		let cells = floor(&[Vec2(10, 6), Vec2(10, 7), Vec2(10, 8)]);
+		let waypoints = vec![Vec3(6.5, 7.5, 1.0), Vec3(14.5, 7.5, 1.0)];
+		let mut entities = Entities::default();
+		let id = entities.spawn_character(Vec3(6.5, 7.5, 1.0), SIZE, SpriteSet::None);
+		entities.controllers.insert(id, Controller::Ai(Ai::new(vec![AiBehavior::Patrol { waypoints, next: 0 }])));
+		
+		// Starting on the first waypoint, then going around the wall in between each time
+		let mut visited = vec![];
+		for _ in 0..1500 {
+			run_systems(&mut entities, &cells, TICK_DT);
+			let Some(AiBehavior::Patrol { next, .. }) = active(&entities, id) else { panic!() };
+			if visited.last() != Some(&next) {
+				visited.push(next);
+			}
+		}
+		assert!(visited.starts_with(&[1, 0, 1, 0]), "Went to waypoints {visited:?}");
+	}
+	
+	#[test]
+	fn wandering_stays_near_home() {
+		let cells = floor(&[Vec2(15, 15)]);
+		let home = Vec3(15.5, 16.5, 1.0);
+		
+		let run = || {
+			let mut entities = Entities::default();
+			let id = entities.spawn_character(home, SIZE, SpriteSet::None);
+			entities.controllers.insert(id, Controller::Ai(Ai::new(vec![AiBehavior::Wander { home, radius: 5.0 }])));
+			
+			let mut furthest = 0.0f64;
+			for _ in 0..1200 {
+				run_systems(&mut entities, &cells, TICK_DT);
+				furthest = furthest.max((position(&entities, id) - home).length());
+			}
+			(furthest, entities.snapshot(id).unwrap())
+		};
+		
+		let (furthest, snapshot) = run();
+		assert!(furthest > 1.0 && furthest < 6.5, "Went up to {furthest} from home");
+		assert_eq!(run().1, snapshot);
+	}
+}
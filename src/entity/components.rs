@@ -0,0 +1,265 @@
+use std::f64::consts::PI;
+
+use glium::{texture::SrgbTexture2d, Display};
+
+use crate::*;
+
+
+
+#[derive(Copy, Clone, Debug, PartialEq, Eq)]
+#[cfg_attr(feature = "serde", derive(serde::Serialize, serde::Deserialize))]
+pub enum FacingDirection { Up, UpRight, Right, DownRight, Down, DownLeft, Left, UpLeft } // Clockwise from -Y, which is up the map
+
+impl FacingDirection {
+	pub const ALL: [Self; 8] = [Self::Up, Self::UpRight, Self::Right, Self::DownRight, Self::Down, Self::DownLeft, Self::Left, Self::UpLeft];
+	
+	// Clockwise from up, as seen from above
+	pub fn angle(self) -> f64 {
+		self as u8 as f64 * PI / 4.0
+	}
+	
+	fn nearest(angle: f64) -> Self {
+		Self::ALL[((angle / (PI / 4.0)).round() as isize).rem_euclid(8) as usize]
+	}
+	
+	// The closest direction to where a horizontal vector points
+	pub fn from_vector(v: Vec3<f64>) -> Self {
+		Self::nearest(v.x().atan2(-v.y()))
+	}
+	
+	// How the direction looks from a camera with the given yaw, so that Up is facing away from it and Right is facing right on screen
+	pub fn relative_to(self, yaw: f32) -> Self {
+		Self::nearest(self.angle() + yaw as f64)
+	}
+	
+	// Index into sprites that only have up, down, left and right. Diagonals use the side they lean towards.
+	pub fn four_way(self) -> usize {
+		match self {
+			Self::Up => 0,
+			Self::Down => 1,
+			Self::Left | Self::UpLeft | Self::DownLeft => 2,
+			Self::Right | Self::UpRight | Self::DownRight => 3,
+		}
+	}
+}
+
+// What the entity was touching at the end of its last physics step
+#[derive(Copy, Clone, Debug, PartialEq, Eq, Default)]
+#[cfg_attr(feature = "serde", derive(serde::Serialize, serde::Deserialize))]
+pub enum ContactState {
+	#[default]
+	Airborne,
+	Grounded,
+	Swimming,
+}
+
+// Sprites are shared through the asset cache, so any number of entities can use the same ones
+#[derive(Clone, Debug)]
+pub enum SpriteSet {
+	None,
+	Static(Handle<SrgbTexture2d>),
+	Directional([Handle<SrgbTexture2d>; 4]),
+	Animated(Handle<SpriteSheet>),
+}
+
+
+
+pub const LOW_CORNER: Vec3<f64> = Vec3(-0.5, -0.5, 0.0);
+pub const HIGH_CORNER: Vec3<f64> = Vec3(0.5, 0.5, 1.0);
+
+
+
+// MARK: Components
+
+// Where the entity is, at the middle of its feet, and which way it faces
+#[derive(Copy, Clone, Debug, PartialEq)]
+#[cfg_attr(feature = "serde", derive(serde::Serialize, serde::Deserialize))]
+pub struct Transform {
+	pub position: Vec3<f64>,
+	pub direction: FacingDirection,
+}
+
+impl Transform {
+	pub fn new(position: Vec3<f64>) -> Self {
+		Self { position, direction: FacingDirection::Down }
+	}
+}
+
+
+// Lets physics move the entity and collide it with the tiles
+#[derive(Copy, Clone, Debug, PartialEq)]
+#[cfg_attr(feature = "serde", derive(serde::Serialize, serde::Deserialize))]
+pub struct Body {
+	pub velocity: Vec3<f64>,
+	pub size: Vec3<f64>,
+	pub mass: f64,
+	pub contact: ContactState,
+}
+
+impl Body {
+	pub fn new(size: Vec3<f64>) -> Self {
+		Self { velocity: Vec3(0.0, 0.0, 0.0), size, mass: 70.0, contact: ContactState::Airborne }
+	}
+	
+	pub fn hitbox(&self, position: Vec3<f64>) -> Aabb<f64> {
+		Aabb::new(position + self.size.scale(LOW_CORNER), position + self.size.scale(HIGH_CORNER))
+	}
+}
+
+
+// Where the entity is trying to go, set by whatever controls it, and how well it can get there
+#[derive(Copy, Clone, Debug, PartialEq)]
+#[cfg_attr(feature = "serde", derive(serde::Serialize, serde::Deserialize))]
+pub struct Movement {
+	pub input: Vec3<f64>,
+	pub jump: bool,
+	pub ground_speed: f64,
+	pub air_speed: f64,
+	pub water_speed: f64,
+	pub ground_acceleration: f64,
+	pub air_acceleration: f64,
+	pub water_acceleration: f64,
+	pub air_resistance: f64,
+}
+
+impl Default for Movement {
+	fn default() -> Self {
+		Self {
+			input: Vec3(0.0, 0.0, 0.0),
+			jump: false,
+			ground_speed: 5.0,
+			air_speed: 12.0,
+			water_speed: 3.0,
+			ground_acceleration: 60.0,
+			air_acceleration: 4.0,
+			water_acceleration: 25.0,
+			air_resistance: 0.001,
+		}
+	}
+}
+
+
+// How the entity is drawn. Not part of the simulation, so it's left out of snapshots and the state hash.
+pub struct Sprite {
+	pub sprites: SpriteSet,
+	pub animation: AnimationState,
+	pub show: bool,
+	pub mesh_buffers: Option<(VertexBuffer<ModelVertex>, IndexBuffer<ModelIndex>)>, // Lying flat under the entity
+	pub billboard_buffers: Option<(VertexBuffer<ModelVertex>, IndexBuffer<ModelIndex>)>, // Standing up, to be turned towards the camera
+}
+
+impl Sprite {
+	pub fn new(sprites: SpriteSet) -> Self {
+		Self { sprites, animation: AnimationState::default(), show: true, mesh_buffers: None, billboard_buffers: None }
+	}
+	
+	// The texture to draw for an entity facing `direction` as seen from a camera with the given yaw, and the part of it with the current frame
+	pub fn current(&self, direction: FacingDirection, camera_yaw: f32) -> Option<(&SrgbTexture2d, UvRect)> {
+		let direction = direction.relative_to(camera_yaw);
+		match &self.sprites {
+			SpriteSet::None => None,
+			SpriteSet::Static(sprite) => Some((sprite, UvRect::FULL)),
+			SpriteSet::Directional(sprites) => Some((&sprites[direction.four_way()], UvRect::FULL)),
+			SpriteSet::Animated(sheet) => Some((&sheet.texture, sheet.frame_uv(self.animation.animation, direction, self.animation.time))),
+		}
+	}
+	
+	pub fn load_mesh_buffers(&mut self, display: &Display, size: Vec3<f64>) {
+		let l = size.scale(LOW_CORNER).as_type();
+		let h = size.scale(HIGH_CORNER).as_type();
+		
+		self.mesh_buffers = Some((
+			VertexBuffer::new(display, &[
+				ModelVertex { position: Vec3(l.x(), l.y(), l.z() + 0.01), normal: Vec3::Z, uv: Vec2(0.0, 0.0) },
+				ModelVertex { position: Vec3(l.x(), h.y(), l.z() + 0.01), normal: Vec3::Z, uv: Vec2(0.0, 1.0) },
+				ModelVertex { position: Vec3(h.x(), h.y(), l.z() + 0.01), normal: Vec3::Z, uv: Vec2(1.0, 1.0) },
+				ModelVertex { position: Vec3(h.x(), l.y(), l.z() + 0.01), normal: Vec3::Z, uv: Vec2(1.0, 0.0) },
+			]).unwrap(),
+			IndexBuffer::new(display, PrimitiveType::TrianglesList, &[0, 1, 2, 0, 2, 3]).unwrap(),
+		));
+		
+		// Facing +Y, which is towards a camera with no yaw
+		self.billboard_buffers = Some((
+			VertexBuffer::new(display, &[
+				ModelVertex { position: Vec3(l.x(), 0.0, h.z()), normal: Vec3::Y, uv: Vec2(0.0, 0.0) },
+				ModelVertex { position: Vec3(l.x(), 0.0, l.z()), normal: Vec3::Y, uv: Vec2(0.0, 1.0) },
+				ModelVertex { position: Vec3(h.x(), 0.0, l.z()), normal: Vec3::Y, uv: Vec2(1.0, 1.0) },
+				ModelVertex { position: Vec3(h.x(), 0.0, h.z()), normal: Vec3::Y, uv: Vec2(1.0, 0.0) },
+			]).unwrap(),
+			IndexBuffer::new(display, PrimitiveType::TrianglesList, &[0, 1, 2, 0, 2, 3]).unwrap(),
+		));
+	}
+}
+
+
+// Marks an entity the spawner made, with the name of the spawn rule it came from
+#[derive(Clone, Debug, PartialEq)]
+#[cfg_attr(feature = "serde", derive(serde::Serialize, serde::Deserialize))]
+pub struct Spawned {
+	pub rule: String,
+}
+
+
+// Moves recorded earlier, one per tick, as a world space direction and whether to jump. Stands still once they run out.
+#[derive(Clone, Debug, PartialEq)]
+#[cfg_attr(feature = "serde", derive(serde::Serialize, serde::Deserialize))]
+pub struct ReplayController {
+	pub moves: Vec<(Vec3<f64>, bool)>,
+	pub next: usize,
+}
+
+impl ReplayController {
+	pub fn new(moves: Vec<(Vec3<f64>, bool)>) -> Self {
+		Self { moves, next: 0 }
+	}
+	
+	pub fn advance(&mut self) -> (Vec3<f64>, bool) {
+		let next = self.moves.get(self.next).copied().unwrap_or((Vec3::ZERO, false));
+		self.next += 1;
+		next
+	}
+}
+
+
+// Where an entity's movement comes from. An entity has at most one, and one without any stands still.
+#[derive(Clone, Debug, PartialEq)]
+#[cfg_attr(feature = "serde", derive(serde::Serialize, serde::Deserialize))]
+pub enum Controller {
+	Local(usize), // The local player with this index, whose input `Game::tick` writes into the entity's movement
+	Ai(Ai),
+	Replay(ReplayController),
+}
+
+
+
+#[cfg(test)]
+mod tests {
+	use super::*;
+	
+	#[test]
+	fn facing_has_eight_directions() {
+		assert_eq!(FacingDirection::from_vector(Vec3(0.0, -1.0, 0.0)), FacingDirection::Up);
+		assert_eq!(FacingDirection::from_vector(Vec3(1.0, -1.0, 0.0)), FacingDirection::UpRight);
+		assert_eq!(FacingDirection::from_vector(Vec3(1.0, 0.2, 0.0)), FacingDirection::Right);
+		assert_eq!(FacingDirection::from_vector(Vec3(-0.7, 0.7, 0.0)), FacingDirection::DownLeft);
+		assert_eq!(FacingDirection::from_vector(Vec3(-1.0, -0.9, 0.0)), FacingDirection::UpLeft);
+	}
+	
+	#[test]
+	fn sprites_are_chosen_relative_to_the_camera() {
+		// With no yaw the camera looks up the map, so directions are seen as they are
+		for direction in FacingDirection::ALL {
+			assert_eq!(direction.relative_to(0.0), direction);
+		}
+		
+		// Looking towards -X, an entity facing the same way shows its back, and one facing +Y faces left on screen
+		let yaw = std::f32::consts::FRAC_PI_2;
+		assert_eq!(FacingDirection::Left.relative_to(yaw), FacingDirection::Up);
+		assert_eq!(FacingDirection::Right.relative_to(yaw), FacingDirection::Down);
+		assert_eq!(FacingDirection::Down.relative_to(yaw), FacingDirection::Left);
+		assert_eq!(FacingDirection::UpRight.relative_to(-yaw), FacingDirection::UpLeft);
+		
+		assert_eq!(FacingDirection::DownRight.four_way(), 3);
+		assert_eq!(FacingDirection::Down.four_way(), 1);
+	}
+}
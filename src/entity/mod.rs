@@ -0,0 +1,345 @@
+use std::collections::{BTreeMap, BTreeSet};
+
+use crate::*;
+
+pub mod ai;
+pub mod components;
+pub mod systems;
+pub use ai::*;
+pub use components::*;
+pub use systems::*;
+
+
+
+// Ids are never reused, so one kept after its entity is gone can't end up pointing at a different one
+#[derive(Copy, Clone, Debug, PartialEq, Eq, PartialOrd, Ord, Hash)]
+#[cfg_attr(feature = "serde", derive(serde::Serialize, serde::Deserialize))]
+pub struct EntityId(pub u64);
+
+impl std::fmt::Display for EntityId {
+	fn fmt(&self, f: &mut std::fmt::Formatter<'_>) -> std::fmt::Result {
+		write!(f, "#{}", self.0)
+	}
+}
+
+
+// One kind of component for the entities that have it. Kept in id order, so systems visit entities in the same order on every run.
+pub struct Storage<T> {
+	components: BTreeMap<EntityId, T>,
+}
+
+impl<T> Default for Storage<T> {
+	fn default() -> Self {
+		Self { components: BTreeMap::new() }
+	}
+}
+
+impl<T> Storage<T> {
+	pub fn get(&self, id: EntityId) -> Option<&T> {
+		self.components.get(&id)
+	}
+	
+	pub fn get_mut(&mut self, id: EntityId) -> Option<&mut T> {
+		self.components.get_mut(&id)
+	}
+	
+	pub fn contains(&self, id: EntityId) -> bool {
+		self.components.contains_key(&id)
+	}
+	
+	// Replaces and returns the component the entity already had
+	pub fn insert(&mut self, id: EntityId, component: T) -> Option<T> {
+		self.components.insert(id, component)
+	}
+	
+	pub fn remove(&mut self, id: EntityId) -> Option<T> {
+		self.components.remove(&id)
+	}
+	
+	pub fn len(&self) -> usize {
+		self.components.len()
+	}
+	
+	pub fn is_empty(&self) -> bool {
+		self.components.is_empty()
+	}
+	
+	pub fn ids(&self) -> impl Iterator<Item = EntityId> + '_ {
+		self.components.keys().copied()
+	}
+	
+	pub fn iter(&self) -> impl DoubleEndedIterator<Item = (EntityId, &T)> {
+		self.components.iter().map(|(id, component)| (*id, component))
+	}
+	
+	pub fn iter_mut(&mut self) -> impl DoubleEndedIterator<Item = (EntityId, &mut T)> {
+		self.components.iter_mut().map(|(id, component)| (*id, component))
+	}
+}
+
+
+
+// MARK: Entities
+
+// Every entity in a world, as a set of ids and a storage per kind of component.
+// Systems borrow the storages they need separately, so they can read one while changing another.
+#[derive(Default)]
+pub struct Entities {
+	next_id: u64,
+	ids: BTreeSet<EntityId>,
+	pub transforms: Storage<Transform>,
+	pub bodies: Storage<Body>,
+	pub movement: Storage<Movement>,
+	pub sprites: Storage<Sprite>,
+	pub controllers: Storage<Controller>,
+	pub spawned: Storage<Spawned>,
+}
+
+// The simulated state of an entity, without anything tied to the display, for saves and network messages
+#[derive(Copy, Clone, Debug, PartialEq)]
+#[cfg_attr(feature = "serde", derive(serde::Serialize, serde::Deserialize))]
+pub struct EntitySnapshot {
+	pub position: Vec3<f64>,
+	pub velocity: Vec3<f64>,
+	pub size: Vec3<f64>,
+	pub movement_input: Vec3<f64>,
+	pub jump_input: bool,
+	pub direction: FacingDirection,
+}
+
+impl Entities {
+	// A new entity with no components
+	pub fn spawn(&mut self) -> EntityId {
+		let id = EntityId(self.next_id);
+		self.next_id += 1;
+		self.ids.insert(id);
+		id
+	}
+	
+	// Something that walks, jumps and animates, with nothing deciding where it goes yet
+	pub fn spawn_character(&mut self, position: Vec3<f64>, size: Vec3<f64>, sprites: SpriteSet) -> EntityId {
+		let id = self.spawn();
+		self.transforms.insert(id, Transform::new(position));
+		self.bodies.insert(id, Body::new(size));
+		self.movement.insert(id, Movement::default());
+		self.sprites.insert(id, Sprite::new(sprites));
+		id
+	}
+	
+	// Removes the entity and all of its components, returning whether it existed
+	pub fn despawn(&mut self, id: EntityId) -> bool {
+		self.transforms.remove(id);
+		self.bodies.remove(id);
+		self.movement.remove(id);
+		self.sprites.remove(id);
+		self.controllers.remove(id);
+		self.spawned.remove(id);
+		self.ids.remove(&id)
+	}
+	
+	pub fn contains(&self, id: EntityId) -> bool {
+		self.ids.contains(&id)
+	}
+	
+	// The entity the local player with this index controls
+	pub fn controlled_by(&self, player: usize) -> Option<EntityId> {
+		self.controllers.iter().find(|(_, controller)| **controller == Controller::Local(player)).map(|(id, _)| id)
+	}
+	
+	pub fn ids(&self) -> impl Iterator<Item = EntityId> + '_ {
+		self.ids.iter().copied()
+	}
+	
+	pub fn len(&self) -> usize {
+		self.ids.len()
+	}
+	
+	pub fn is_empty(&self) -> bool {
+		self.ids.is_empty()
+	}
+	
+	// Only for entities with a transform, body and movement
+	pub fn snapshot(&self, id: EntityId) -> Option<EntitySnapshot> {
+		let (transform, body, movement) = (self.transforms.get(id)?, self.bodies.get(id)?, self.movement.get(id)?);
+		Some(EntitySnapshot {
+			position: transform.position,
+			velocity: body.velocity,
+			size: body.size,
+			movement_input: movement.input,
+			jump_input: movement.jump,
+			direction: transform.direction,
+		})
+	}
+	
+	// Returns false without changing anything if the entity is missing one of the components
+	pub fn restore(&mut self, id: EntityId, snapshot: &EntitySnapshot) -> bool {
+		let (Some(transform), Some(body), Some(movement)) = (self.transforms.get_mut(id), self.bodies.get_mut(id), self.movement.get_mut(id)) else { return false };
+		transform.position = snapshot.position;
+		transform.direction = snapshot.direction;
+		body.velocity = snapshot.velocity;
+		body.size = snapshot.size;
+		movement.input = snapshot.movement_input;
+		movement.jump = snapshot.jump_input;
+		true
+	}
+	
+	// Takes the entity out of the world, with all of its components
+	pub fn take(&mut self, id: EntityId) -> Option<SavedEntity> {
+		if !self.contains(id) { return None }
+		let saved = SavedEntity {
+			id,
+			transform: self.transforms.remove(id),
+			body: self.bodies.remove(id),
+			movement: self.movement.remove(id),
+			sprite: self.sprites.remove(id).is_some(),
+			controller: self.controllers.remove(id),
+			spawned: self.spawned.remove(id),
+		};
+		self.despawn(id);
+		Some(saved)
+	}
+	
+	// Puts a taken entity back under the id it had, so whatever refers to it still finds it.
+	// Ids are never handed out again, so it can't be in use.
+	pub fn put_back(&mut self, saved: SavedEntity) -> EntityId {
+		let id = saved.id;
+		debug_assert!(!self.contains(id) && id.0 < self.next_id, "{id} wasn't taken from these entities");
+		self.ids.insert(id);
+		if let Some(transform) = saved.transform { self.transforms.insert(id, transform); }
+		if let Some(body) = saved.body { self.bodies.insert(id, body); }
+		if let Some(movement) = saved.movement { self.movement.insert(id, movement); }
+		if saved.sprite { self.sprites.insert(id, Sprite::new(SpriteSet::None)); }
+		if let Some(controller) = saved.controller { self.controllers.insert(id, controller); }
+		if let Some(spawned) = saved.spawned { self.spawned.insert(id, spawned); }
+		id
+	}
+	
+	// Covers the simulated components. Sprites only follow the simulation, so they're left out.
+	pub fn hash_state(&self, hasher: &mut StateHasher) {
+		hasher.write_u64(self.next_id);
+		hasher.write_u64(self.ids.len() as u64);
+		
+		for id in self.ids() {
+			hasher.write_u64(id.0);
+			hash_components(hasher, self.transforms.get(id), self.bodies.get(id), self.movement.get(id), self.controllers.get(id), self.spawned.get(id));
+		}
+	}
+}
+
+
+// An entity taken out of the world, with everything needed to put it back.
+// Sprites aren't part of the simulation, so they come back empty for whatever gave the entity its sprites to fill in again.
+#[derive(Clone, Debug, PartialEq)]
+#[cfg_attr(feature = "serde", derive(serde::Serialize, serde::Deserialize))]
+pub struct SavedEntity {
+	pub id: EntityId,
+	pub transform: Option<Transform>,
+	pub body: Option<Body>,
+	pub movement: Option<Movement>,
+	pub sprite: bool,
+	pub controller: Option<Controller>,
+	pub spawned: Option<Spawned>,
+}
+
+impl SavedEntity {
+	pub fn hash_state(&self, hasher: &mut StateHasher) {
+		hasher.write_u64(self.id.0);
+		hash_components(hasher, self.transform.as_ref(), self.body.as_ref(), self.movement.as_ref(), self.controller.as_ref(), self.spawned.as_ref());
+	}
+}
+
+fn hash_components(hasher: &mut StateHasher, transform: Option<&Transform>, body: Option<&Body>, movement: Option<&Movement>, controller: Option<&Controller>, spawned: Option<&Spawned>) {
+	hasher.write_bool(transform.is_some());
+	if let Some(transform) = transform {
+		hasher.write_vec3(transform.position);
+		hasher.write_u8(transform.direction as u8);
+	}
+	
+	hasher.write_bool(body.is_some());
+	if let Some(body) = body {
+		hasher.write_vec3(body.velocity);
+		hasher.write_vec3(body.size);
+	}
+	
+	hasher.write_bool(movement.is_some());
+	if let Some(movement) = movement {
+		hasher.write_vec3(movement.input);
+		hasher.write_bool(movement.jump);
+	}
+	
+	match controller {
+		None => hasher.write_u8(0),
+		Some(Controller::Local(player)) => {
+			hasher.write_u8(1);
+			hasher.write_u64(*player as u64);
+		}
+		Some(Controller::Ai(ai)) => {
+			hasher.write_u8(2);
+			ai.hash_state(hasher);
+		}
+		Some(Controller::Replay(replay)) => {
+			hasher.write_u8(3);
+			hasher.write_u64(replay.moves.len() as u64);
+			hasher.write_u64(replay.next as u64);
+		}
+	}
+	
+	hasher.write_bool(spawned.is_some());
+	if let Some(spawned) = spawned {
+		hasher.write_u64(spawned.rule.len() as u64);
+		hasher.write(spawned.rule.as_bytes());
+	}
+}
+
+
+
+#[cfg(test)]
+mod tests {
+	use super::*;
+	
+	#[test]
+	fn ids_stay_with_their_entities() {
+		let mut entities = Entities::default();
+		let a = entities.spawn_character(Vec3(1.0, 0.0, 0.0), Vec3(1.0, 1.0, 1.0), SpriteSet::None);
+		let b = entities.spawn_character(Vec3(2.0, 0.0, 0.0), Vec3(1.0, 1.0, 1.0), SpriteSet::None);
+		
+		// Removing one doesn't move the others, and its id isn't handed out again
+		assert!(entities.despawn(a));
+		assert!(!entities.despawn(a));
+		let c = entities.spawn();
+		assert_ne!(c, a);
+		assert_eq!(entities.transforms.get(b).unwrap().position, Vec3(2.0, 0.0, 0.0));
+		assert!(entities.transforms.get(a).is_none() && entities.bodies.get(a).is_none());
+		
+		// Components are optional, and storages only hold the entities that have them
+		assert!(entities.contains(c) && !entities.transforms.contains(c));
+		assert_eq!(entities.ids().collect::<Vec<_>>(), [b, c]);
+		assert_eq!(entities.bodies.len(), 1);
+		assert!(entities.snapshot(c).is_none());
+	}
+	
+	#[test]
+	fn hash_covers_components() {
+		let hash = |entities: &Entities| {
+			let mut hasher = StateHasher::new();
+			entities.hash_state(&mut hasher);
+			hasher.finish()
+		};
+		
+		let mut entities = Entities::default();
+		let id = entities.spawn_character(Vec3(1.0, 0.0, 0.0), Vec3(1.0, 1.0, 1.0), SpriteSet::None);
+		let before = hash(&entities);
+		
+		entities.sprites.get_mut(id).unwrap().show = false;
+		assert_eq!(hash(&entities), before);
+		
+		entities.controllers.insert(id, Controller::Local(0));
+		assert_eq!(entities.controlled_by(0), Some(id));
+		assert_eq!(entities.controlled_by(1), None);
+		let with_player = hash(&entities);
+		assert_ne!(with_player, before);
+		
+		entities.movement.get_mut(id).unwrap().jump = true;
+		assert_ne!(hash(&entities), with_player);
+	}
+}
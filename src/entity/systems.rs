@@ -0,0 +1,132 @@
+use crate::*;
+
+
+
+// Everything that happens to entities in a tick, in order: controllers pick where to go, entities turn to face that way,
+// physics moves them, and animations follow what they ended up doing.
+// Local players' input is written into `Movement` before this runs.
+pub fn run_systems(entities: &mut Entities, cells: &HashMap<Vec3<isize>, Cell>, dt: f64) {
+	controller_system(entities, cells);
+	facing_system(entities);
+	physics_system(entities, cells, dt);
+	animation_system(entities, dt);
+}
+
+
+pub fn controller_system(entities: &mut Entities, cells: &HashMap<Vec3<isize>, Cell>) {
+	let surroundings = Surroundings { cells, transforms: &entities.transforms, bodies: &entities.bodies };
+	for (id, controller) in entities.controllers.iter_mut() {
+		let Some(movement) = entities.movement.get_mut(id) else { continue };
+		
+		match controller {
+			Controller::Local(_) => (),
+			Controller::Ai(ai) => (movement.input, movement.jump) = ai.think(id, &surroundings),
+			Controller::Replay(replay) => (movement.input, movement.jump) = replay.advance(),
+		}
+	}
+}
+
+// Entities face the way they're trying to move, and keep facing that way once they stop
+pub fn facing_system(entities: &mut Entities) {
+	for (id, movement) in entities.movement.iter() {
+		let Some(transform) = entities.transforms.get_mut(id) else { continue };
+		if movement.input.x() != 0.0 || movement.input.y() != 0.0 {
+			transform.direction = FacingDirection::from_vector(movement.input);
+		}
+	}
+}
+
+// Entities without movement still fall and collide, they just can't steer
+pub fn physics_system(entities: &mut Entities, cells: &HashMap<Vec3<isize>, Cell>, dt: f64) {
+	for (id, body) in entities.bodies.iter_mut() {
+		let Some(transform) = entities.transforms.get_mut(id) else { continue };
+		physics_step(transform, body, entities.movement.get(id), cells, dt);
+	}
+}
+
+pub fn animation_system(entities: &mut Entities, dt: f64) {
+	for (id, sprite) in entities.sprites.iter_mut() {
+		let animation = entities.bodies.get(id).map_or(Animation::Idle, |body| choose_animation(body.contact, body.velocity));
+		sprite.animation.update(animation, dt);
+	}
+}
+
+
+
+#[cfg(test)]
+mod tests {
+	use super::*;
+	
+	fn floor() -> HashMap<Vec3<isize>, Cell> {
+		let mut tiles = Cell::empty_tiles();
+		for pos in Vec3Range::<usize, ZYX>::exclusive(Vec3::ZERO, Vec3(CELL_WIDTH, CELL_WIDTH, 1)) {
+			tiles[pos] = Tile::full(Stone);
+		}
+		HashMap::from([(Vec3(0, 0, 0), Cell::new(tiles))])
+	}
+	
+	#[test]
+	fn facing_follows_movement() {
+		let mut entities = Entities::default();
+		let id = entities.spawn_character(Vec3(4.0, 4.0, 1.0), Vec3(1.0, 1.0, 1.0), SpriteSet::None);
+		
+		entities.movement.get_mut(id).unwrap().input = Vec3(0.0, -1.0, 0.0);
+		facing_system(&mut entities);
+		entities.movement.get_mut(id).unwrap().input = Vec3(0.0, 0.0, 0.0);
+		facing_system(&mut entities);
+		assert_eq!(entities.transforms.get(id).unwrap().direction, FacingDirection::Up);
+	}
+	
+	#[test]
+	fn ai_follows_its_target() {
+		let cells = floor();
+		let mut entities = Entities::default();
+		let target = entities.spawn_character(Vec3(20.0, 4.0, 1.0), Vec3(0.7, 0.7, 1.75), SpriteSet::None);
+		let follower = entities.spawn_character(Vec3(4.0, 4.0, 1.0), Vec3(0.7, 0.7, 1.75), SpriteSet::None);
+		entities.controllers.insert(follower, Controller::Ai(Ai::new(vec![AiBehavior::Follow { target, distance: 2.0 }])));
+		
+		for _ in 0..400 {
+			run_systems(&mut entities, &cells, TICK_DT);
+		}
+		
+		let position = entities.transforms.get(follower).unwrap().position;
+		assert!(position.x() > 15.0 && position.x() < 20.0, "Follower ended up at {position:?}");
+		assert_eq!(entities.transforms.get(follower).unwrap().direction, FacingDirection::Right);
+		assert_eq!(entities.transforms.get(target).unwrap().position.x(), 20.0);
+		
+		// Once the target is gone there's nothing to walk towards
+		entities.despawn(target);
+		controller_system(&mut entities, &cells);
+		assert_eq!(entities.movement.get(follower).unwrap().input, Vec3::ZERO);
+	}
+	
+	#[test]
+	fn replays_play_back_moves() {
+		let mut entities = Entities::default();
+		let id = entities.spawn_character(Vec3(4.0, 4.0, 1.0), Vec3(1.0, 1.0, 1.0), SpriteSet::None);
+		entities.controllers.insert(id, Controller::Replay(ReplayController::new(vec![(Vec3::X, false), (Vec3::Y, true)])));
+		
+		let mut played = vec![];
+		for _ in 0..3 {
+			controller_system(&mut entities, &HashMap::new());
+			let movement = entities.movement.get(id).unwrap();
+			played.push((movement.input, movement.jump));
+		}
+		assert_eq!(played, [(Vec3::X, false), (Vec3::Y, true), (Vec3::ZERO, false)]);
+	}
+	
+	#[test]
+	fn bodies_fall_without_movement() {
+		let cells = floor();
+		let mut entities = Entities::default();
+		let id = entities.spawn();
+		entities.transforms.insert(id, Transform::new(Vec3(4.0, 4.0, 5.0)));
+		entities.bodies.insert(id, Body::new(Vec3(1.0, 1.0, 1.0)));
+		
+		for _ in 0..200 {
+			run_systems(&mut entities, &cells, TICK_DT);
+		}
+		assert!((entities.transforms.get(id).unwrap().position.z() - 1.0).abs() < 1e-3);
+		assert_eq!(entities.bodies.get(id).unwrap().contact, ContactState::Grounded);
+	}
+}
@@ -0,0 +1,308 @@
+use std::f32::consts::PI;
+
+use crate::*;
+
+
+pub const PLAYER_SIZE: Vec3<f64> = Vec3(0.70, 0.70, 1.75);
+pub const PLAYER_SPAWN: Vec3<f64> = Vec3(0.5, 0.5, CELL_HEIGHT as f64);
+const PLAYER_SPACING: f64 = 2.0; // Between where each local player spawns
+
+
+// A player on this machine. Which entity they control is kept by its `Controller`, so it can change.
+#[derive(Copy, Clone, Debug, PartialEq)]
+pub struct LocalPlayer {
+	pub u: f32,
+	pub v: f32,
+}
+
+// The simulated part of the game: the world plus each local player's view angles, which steer movement.
+// Everything here only changes through `tick`, so it can run without a window.
+pub struct Game {
+	pub world: World,
+	pub players: Vec<LocalPlayer>,
+}
+
+impl Game {
+	// Starts with one local player
+	pub fn new(seed: u64) -> Self {
+		let mut game = Self {
+			world: World::new(seed),
+			players: vec![],
+		};
+		game.add_player();
+		game
+	}
+	
+	// Spawns an entity for a new local player and returns the player's index
+	pub fn add_player(&mut self) -> usize {
+		let player = self.players.len();
+		let position = self.world.place_player(player_spawn(player));
+		let entity = self.world.entities.spawn_character(position, PLAYER_SIZE, SpriteSet::None);
+		self.world.entities.controllers.insert(entity, Controller::Local(player));
+		self.players.push(LocalPlayer { u: 0.0, v: 0.0 });
+		player
+	}
+	
+	pub fn entity(&self, player: usize) -> Option<EntityId> {
+		self.world.entities.controlled_by(player)
+	}
+	
+	// Gives the player control of `entity`, and whatever controlled it before gets the player's old entity instead.
+	// Fails if another local player has it.
+	pub fn possess(&mut self, player: usize, entity: EntityId) -> bool {
+		let entities = &mut self.world.entities;
+		if !entities.contains(entity) { return false }
+		if matches!(entities.controllers.get(entity), Some(Controller::Local(other)) if *other != player) { return false }
+		
+		let current = entities.controlled_by(player);
+		let previous = entities.controllers.insert(entity, Controller::Local(player));
+		if let Some(current) = current.filter(|current| *current != entity) {
+			match previous {
+				Some(controller) => { entities.controllers.insert(current, controller); }
+				None => {
+					entities.controllers.remove(current);
+					if let Some(movement) = entities.movement.get_mut(current) {
+						(movement.input, movement.jump) = (Vec3::ZERO, false);
+					}
+				}
+			}
+		}
+		true
+	}
+	
+	// Moves the player on to the next entity that can move and isn't another local player's, in id order
+	pub fn switch_entity(&mut self, player: usize) {
+		let entities = &self.world.entities;
+		let current = self.entity(player);
+		let candidates = entities.movement.ids().filter(|id| !matches!(entities.controllers.get(*id), Some(Controller::Local(_)))).collect::<Vec<_>>();
+		let next = candidates.iter().find(|id| Some(**id) > current).or(candidates.first()).copied();
+		if let Some(next) = next {
+			self.possess(player, next);
+		}
+	}
+	
+	// Takes one input for each local player. Players without one stand still.
+	pub fn tick(&mut self, inputs: &[TickInput]) {
+		if inputs.iter().any(|input| input.new_world) {
+			self.world.regenerate();
+		}
+		
+		for player in 0..self.players.len() {
+			self.apply_input(player, &inputs.get(player).copied().unwrap_or_default());
+		}
+		
+		self.world.step();
+	}
+	
+	fn apply_input(&mut self, player: usize, input: &TickInput) {
+		if input.switch_entity {
+			self.switch_entity(player);
+		}
+		let entity = self.entity(player);
+		
+		if input.reset {
+			let spawn = self.world.place_player(player_spawn(player));
+			if let Some(transform) = entity.and_then(|entity| self.world.entities.transforms.get_mut(entity)) {
+				transform.position = spawn;
+			}
+			if let Some(body) = entity.and_then(|entity| self.world.entities.bodies.get_mut(entity)) {
+				body.velocity = Vec3(0.0, 0.0, 0.0);
+			}
+			
+			self.players[player].u = 0.0;
+			self.players[player].v = PI / 3.0;
+		}
+		
+		let view = &mut self.players[player];
+		view.u += input.look.x();
+		view.v = (view.v + input.look.y()).clamp(-0.5*PI, 0.5*PI);
+		let u = view.u;
+		
+		let Some(entity) = entity else { return };
+		if let Some(body) = self.world.entities.bodies.get_mut(entity) {
+			body.velocity += view_relative(u, input.impulse) * 30.0;
+		}
+		if let Some(movement) = self.world.entities.movement.get_mut(entity) {
+			// Analog sticks can ask for less than full speed, but combined inputs can't exceed it
+			let direction = view_relative(u, input.movement);
+			movement.input = if direction.length() > 1.0 { direction.normalize() } else { direction };
+			movement.jump = input.jump;
+		}
+	}
+	
+	pub fn look_direction(&self, player: usize) -> Vec3<f64> {
+		let LocalPlayer { u, v } = self.players[player];
+		Vec3(
+			-u.sin() * v.cos(),
+			-u.cos() * v.cos(),
+			-v.sin(),
+		).as_type()
+	}
+}
+
+
+fn player_spawn(player: usize) -> Vec3<f64> {
+	PLAYER_SPAWN + Vec3(player as f64 * PLAYER_SPACING, 0.0, 0.0)
+}
+
+
+// Turns a (right, forward) input relative to the view yaw into a world space direction
+pub fn view_relative(u: f32, input: Vec2<f32>) -> Vec3<f64> {
+	(Vec3(u.cos(), -u.sin(), 0.0) * input.x() + Vec3(-u.sin(), -u.cos(), 0.0) * input.y()).as_type::<f64>()
+}
+
+
+// MARK: Replay
+
+pub enum ReplayResult {
+	Finished { ticks: usize, state_hash: u64 },
+	Diverged { tick: usize, expected: u64, actual: u64 },
+	SpawnTableChanged { expected: u64, actual: u64 },
+}
+
+// Runs a recording without a window, checking the state hash after every tick that has one.
+// What spawns depends on the spawn table, so it has to be the one the recording was made with.
+pub fn replay(recording: &InputRecording, spawn_table: &SpawnTable) -> ReplayResult {
+	let actual = spawn_table.state_hash();
+	if actual != recording.spawn_table_hash {
+		return ReplayResult::SpawnTableChanged { expected: recording.spawn_table_hash, actual }
+	}
+	
+	let mut game = Game::new(recording.seed);
+	game.world.spawn_table = spawn_table.clone();
+	
+	for (i, tick) in recording.ticks.iter().enumerate() {
+		// Players are added just before their first input, like when recording
+		while game.players.len() < tick.inputs.len() {
+			game.add_player();
+		}
+		game.tick(&tick.inputs);
+		
+		if let Some(expected) = tick.state_hash {
+			let actual = game.world.state_hash();
+			if actual != expected {
+				return ReplayResult::Diverged { tick: i, expected, actual }
+			}
+		}
+	}
+	
+	ReplayResult::Finished { ticks: recording.ticks.len(), state_hash: game.world.state_hash() }
+}
+
+
+#[cfg(test)]
+mod tests {
+	use super::*;
+	
+	fn record(seed: u64, inputs: &[TickInput]) -> InputRecording {
+		let mut game = Game::new(seed);
+		let ticks = inputs.iter().map(|input| {
+			game.tick(&[*input]);
+			RecordedTick { inputs: vec![*input], state_hash: Some(game.world.state_hash()) }
+		}).collect();
+		InputRecording { seed, spawn_table_hash: SpawnTable::default().state_hash(), ticks }
+	}
+	
+	fn inputs() -> Vec<TickInput> {
+		(0..200).map(|i| TickInput {
+			movement: Vec2(0.0, 1.0),
+			look: Vec2(0.01, 0.0),
+			impulse: if i == 50 { Vec2(1.0, 0.0) } else { Vec2(0.0, 0.0) },
+			jump: i % 60 == 30,
+			reset: i == 150,
+			new_world: false,
+			switch_entity: false,
+		}).collect()
+	}
+	
+	#[test]
+	fn replay_matches_recording() {
+		let recording = record(7, &inputs());
+		match replay(&recording, &SpawnTable::default()) {
+			ReplayResult::Finished { ticks, state_hash } => {
+				assert_eq!(ticks, 200);
+				assert_eq!(Some(state_hash), recording.ticks.last().unwrap().state_hash);
+			}
+			ReplayResult::Diverged { tick, .. } => panic!("Replay diverged on tick {tick}"),
+			ReplayResult::SpawnTableChanged { .. } => panic!("Spawn table should match"),
+		}
+	}
+	
+	#[test]
+	fn local_players_control_their_own_entities() {
+		let mut game = Game::new(7);
+		let second = game.add_player();
+		let (a, b) = (game.entity(0).unwrap(), game.entity(second).unwrap());
+		assert_ne!(a, b);
+		
+		let start = |game: &Game, id| game.world.entities.transforms.get(id).unwrap().position;
+		let (start_a, start_b) = (start(&game, a), start(&game, b));
+		for _ in 0..100 {
+			game.tick(&[TickInput::default(), TickInput { movement: Vec2(1.0, 0.0), ..Default::default() }]);
+		}
+		assert!((start(&game, a) - start_a).with_z(0.0).length() < 0.01);
+		assert!(start(&game, b).x() > start_b.x() + 1.0);
+		
+		// Another player's entity can't be taken, but an uncontrolled one can, and the old one stops
+		let npc = game.world.entities.spawn_character(start_a + Vec3(0.0, 4.0, 0.0), PLAYER_SIZE, SpriteSet::None);
+		assert!(!game.possess(0, b));
+		game.tick(&[TickInput { movement: Vec2(1.0, 0.0), switch_entity: true, ..Default::default() }]);
+		assert_eq!(game.entity(0), Some(npc));
+		assert_eq!(game.entity(second), Some(b));
+		assert!(game.world.entities.controllers.get(a).is_none());
+		assert_eq!(game.world.entities.movement.get(a).unwrap().input, Vec3::ZERO);
+		
+		// Taking over an AI leaves it driving the player's old entity
+		let follow = vec![AiBehavior::Follow { target: b, distance: 1.0 }];
+		game.world.entities.controllers.insert(a, Controller::Ai(Ai::new(follow.clone())));
+		game.tick(&[TickInput { switch_entity: true, ..Default::default() }]);
+		assert_eq!(game.entity(0), Some(a));
+		assert!(matches!(game.world.entities.controllers.get(npc), Some(Controller::Ai(ai)) if ai.behaviors == follow));
+	}
+	
+	#[test]
+	fn replays_add_local_players() {
+		let mut game = Game::new(3);
+		let mut recording = InputRecording { seed: 3, spawn_table_hash: SpawnTable::default().state_hash(), ticks: vec![] };
+		for i in 0..100 {
+			if i == 20 { game.add_player(); }
+			let inputs = (0..game.players.len()).map(|player| TickInput { movement: Vec2(player as f32, 1.0), jump: i % 30 == player, ..Default::default() }).collect::<Vec<_>>();
+			game.tick(&inputs);
+			recording.ticks.push(RecordedTick { inputs, state_hash: Some(game.world.state_hash()) });
+		}
+		
+		match replay(&recording, &SpawnTable::default()) {
+			ReplayResult::Finished { state_hash, .. } => assert_eq!(state_hash, game.world.state_hash()),
+			ReplayResult::Diverged { tick, .. } => panic!("Replay diverged on tick {tick}"),
+			ReplayResult::SpawnTableChanged { .. } => panic!("Spawn table should match"),
+		}
+	}
+	
+	#[test]
+	fn replay_reports_first_divergent_tick() {
+		let mut recording = record(7, &inputs());
+		recording.ticks[120].inputs[0].jump = !recording.ticks[120].inputs[0].jump;
+		
+		match replay(&recording, &SpawnTable::default()) {
+			ReplayResult::Diverged { tick, .. } => assert_eq!(tick, 120),
+			_ => panic!("Replay should have diverged"),
+		}
+	}
+	
+	#[test]
+	fn replay_refuses_other_spawn_table() {
+		let recording = record(7, &inputs());
+		let table = SpawnTable::parse("critter = weight 2, cap 4").unwrap();
+		match replay(&recording, &table) {
+			ReplayResult::SpawnTableChanged { expected, actual } => {
+				assert_eq!(expected, recording.spawn_table_hash);
+				assert_eq!(actual, table.state_hash());
+			}
+			_ => panic!("Replay should have refused the spawn table"),
+		}
+		
+		// Any change to a rule counts
+		assert_ne!(table.state_hash(), SpawnTable::parse("critter = weight 2, cap 5").unwrap().state_hash());
+		assert_ne!(table.state_hash(), SpawnTable::parse("critter = weight 2, cap 4, wander 0").unwrap().state_hash());
+	}
+}
@@ -1,14 +1,8 @@
-use glium::{program::ProgramCreationInput, texture::{MipmapsOption, RawImage2d, SrgbFormat, SrgbTexture2d}, Display, Frame, Surface, Texture2d};
+use glium::{Frame, Surface, Texture2d};
 
 use crate::*;
 
 
-pub fn load_texture(display: &Display, path: &str) -> SrgbTexture2d {
-    let image = image::open(&format!("assets/textures/{path}.png")).unwrap();
-    use image::GenericImageView;
-    SrgbTexture2d::with_format(display, RawImage2d::from_raw_rgba(image.to_rgba8().into_raw(), image.dimensions()), SrgbFormat::U8U8U8U8, MipmapsOption::NoMipmap).unwrap()
-}
-
 pub fn blit_texture(target: &mut Frame, texture: &Texture2d) {
     texture.as_surface().blit_whole_color_to(
         target,
@@ -22,19 +16,6 @@ pub fn blit_texture(target: &mut Frame, texture: &Texture2d) {
     );
 }
 
-pub fn load_shader_program(display: &Display, vert_shader: &str, frag_shader: &str) -> glium::Program {
-    glium::Program::new(display, ProgramCreationInput::SourceCode {
-        vertex_shader: &std::fs::read_to_string(format!("src/shaders/{vert_shader}.vert")).unwrap(),
-        tessellation_control_shader: None,
-        tessellation_evaluation_shader: None,
-        geometry_shader: None,
-        fragment_shader: &std::fs::read_to_string(format!("src/shaders/{frag_shader}.frag")).unwrap(),
-        transform_feedback_varyings: None,
-        outputs_srgb: false,
-        uses_point_size: false,
-    }).unwrap()
-}
-
 #[derive(Clone, Copy)]
 pub struct ModelVertex {
     pub position: Vec3<f32>,
@@ -0,0 +1,104 @@
+use std::{collections::HashMap, path::{Path, PathBuf}, time::{Duration, Instant, SystemTime}};
+
+
+pub const WATCH_INTERVAL: Duration = Duration::from_millis(500);
+
+
+// Notices files being added or changed under a set of directories by comparing modification times.
+// Polling a few dozen files is cheap enough to do from the main loop, and works the same everywhere.
+pub struct FileWatcher {
+	roots: Vec<PathBuf>,
+	modified: HashMap<PathBuf, SystemTime>,
+	interval: Duration,
+	last_scan: Instant,
+}
+
+impl FileWatcher {
+	// `roots` can be files as well as directories, which are watched recursively
+	pub fn new(roots: &[impl AsRef<Path>], interval: Duration) -> Self {
+		let roots = roots.iter().map(|root| root.as_ref().to_path_buf()).collect::<Vec<_>>();
+		let modified = scan(&roots);
+		Self { roots, modified, interval, last_scan: Instant::now() }
+	}
+	
+	// Like `changes`, but at most once every `interval` and empty in between
+	pub fn poll(&mut self) -> Vec<PathBuf> {
+		if self.last_scan.elapsed() < self.interval { return Vec::new() }
+		self.last_scan = Instant::now();
+		self.changes()
+	}
+	
+	// Files that were added or changed since the last scan, in order. Removed files are forgotten quietly.
+	pub fn changes(&mut self) -> Vec<PathBuf> {
+		let modified = scan(&self.roots);
+		let mut changes = modified.iter()
+			.filter(|(path, time)| self.modified.get(*path) != Some(*time))
+			.map(|(path, _)| path.clone())
+			.collect::<Vec<_>>();
+		changes.sort();
+		self.modified = modified;
+		changes
+	}
+}
+
+fn scan(roots: &[PathBuf]) -> HashMap<PathBuf, SystemTime> {
+	let mut modified = HashMap::new();
+	for root in roots {
+		scan_path(root, &mut modified);
+	}
+	modified
+}
+
+// Files that can't be read are skipped, and picked up again once they can
+fn scan_path(path: &Path, modified: &mut HashMap<PathBuf, SystemTime>) {
+	let Ok(metadata) = std::fs::metadata(path) else { return };
+	if metadata.is_dir() {
+		let Ok(entries) = std::fs::read_dir(path) else { return };
+		for entry in entries.flatten() {
+			scan_path(&entry.path(), modified);
+		}
+	} else if let Ok(time) = metadata.modified() {
+		modified.insert(path.to_path_buf(), time);
+	}
+}
+
+
+
+#[cfg(test)]
+mod tests {
+	use std::fs::File;
+	
+	use super::*;
+	
+	fn set_modified(path: &Path, seconds: u64) {
+		File::options().write(true).open(path).unwrap().set_modified(SystemTime::UNIX_EPOCH + Duration::from_secs(seconds)).unwrap();
+	}
+	
+	#[test]
+	fn notices_changed_and_added_files() {
+		let root = std::env::temp_dir().join(format!("engine2d_watch_{}", std::process::id()));
+		let _ = std::fs::remove_dir_all(&root);
+		std::fs::create_dir_all(root.join("nested")).unwrap();
+		let (a, b, c) = (root.join("a.frag"), root.join("nested/b.png"), root.join("nested/c.png"));
+		std::fs::write(&a, "a").unwrap();
+		std::fs::write(&b, "b").unwrap();
+		set_modified(&a, 1000);
+		set_modified(&b, 1000);
+		
+		let mut watcher = FileWatcher::new(&[&root], WATCH_INTERVAL);
+		assert!(watcher.changes().is_empty());
+		
+		set_modified(&b, 2000);
+		std::fs::write(&c, "c").unwrap();
+		assert_eq!(watcher.changes(), [b, c.clone()]);
+		assert!(watcher.changes().is_empty());
+		
+		// Polling waits for the interval before looking again
+		std::fs::remove_file(&c).unwrap();
+		set_modified(&a, 3000);
+		assert!(watcher.poll().is_empty());
+		assert_eq!(watcher.changes(), [a]);
+		
+		std::fs::remove_dir_all(&root).unwrap();
+	}
+}
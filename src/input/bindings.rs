@@ -0,0 +1,444 @@
+use std::{collections::HashMap, fmt::Display};
+
+use glium::glutin::event::{MouseButton, VirtualKeyCode};
+
+use crate::*;
+
+
+pub const DEFAULT_CONTROLS: &str = include_str!("../../assets/config/controls.cfg");
+
+
+macro_rules! named_enum {
+	($(#[$meta:meta])* pub enum $name:ident { $($variant:ident),* $(,)? }) => {
+		$(#[$meta])*
+		pub enum $name { $($variant),* }
+		
+		impl $name {
+			pub const ALL: &'static [Self] = &[$(Self::$variant),*];
+			
+			pub fn name(self) -> &'static str {
+				match self { $(Self::$variant => stringify!($variant)),* }
+			}
+			
+			pub fn from_name(name: &str) -> Option<Self> {
+				match name { $(stringify!($variant) => Some(Self::$variant),)* _ => None }
+			}
+		}
+	};
+}
+
+named_enum! {
+	#[derive(Copy, Clone, Debug, PartialEq, Eq, Hash)]
+	pub enum Action {
+		MoveForward,
+		MoveBack,
+		MoveLeft,
+		MoveRight,
+		Jump,
+		LookUp,
+		LookDown,
+		LookLeft,
+		LookRight,
+		PushForward,
+		PushBack,
+		PushLeft,
+		PushRight,
+		Interact,
+		ToggleCamera,
+		ZoomIn,
+		ZoomOut,
+		ReloadShaders,
+		ResetPlayer,
+		NewWorld,
+		SwitchEntity,
+		SwitchPlayer,
+		PrintDebug,
+		Quit,
+	}
+}
+
+named_enum! {
+	#[derive(Copy, Clone, Debug, PartialEq, Eq, Hash)]
+	pub enum InputAxis {
+		MoveX,
+		MoveY,
+		LookX,
+		LookY,
+		Zoom,
+	}
+}
+
+named_enum! {
+	#[derive(Copy, Clone, Debug, PartialEq, Eq, Hash)]
+	pub enum AxisSource {
+		MouseX,
+		MouseY,
+		Wheel,
+		LeftStickX,
+		LeftStickY,
+		RightStickX,
+		RightStickY,
+	}
+}
+
+impl AxisSource {
+	// Sticks report a position rather than motion, so they're read every tick instead of accumulated
+	pub fn is_stick(self) -> bool {
+		matches!(self, Self::LeftStickX | Self::LeftStickY | Self::RightStickX | Self::RightStickY)
+	}
+}
+
+named_enum! {
+	#[derive(Copy, Clone, Debug, PartialEq, Eq, Hash)]
+	pub enum GamepadButton {
+		South,
+		East,
+		North,
+		West,
+		LeftBumper,
+		RightBumper,
+		LeftTrigger,
+		RightTrigger,
+		Select,
+		Start,
+		LeftStick,
+		RightStick,
+		DPadUp,
+		DPadDown,
+		DPadLeft,
+		DPadRight,
+	}
+}
+
+
+#[derive(Copy, Clone, Debug, PartialEq, Eq, Hash)]
+pub enum Button {
+	Key(VirtualKeyCode),
+	Mouse(MouseButton),
+	Gamepad(GamepadButton),
+}
+
+#[derive(Copy, Clone, Debug, Default, PartialEq, Eq, Hash)]
+pub struct Modifiers {
+	pub shift: bool,
+	pub ctrl: bool,
+	pub alt: bool,
+}
+
+impl Modifiers {
+	pub const NONE: Self = Self { shift: false, ctrl: false, alt: false };
+	
+	// True if every modifier required by self is held in `held`
+	pub fn satisfied_by(self, held: Modifiers) -> bool {
+		(!self.shift || held.shift) && (!self.ctrl || held.ctrl) && (!self.alt || held.alt)
+	}
+	
+	pub fn count(self) -> usize {
+		self.shift as usize + self.ctrl as usize + self.alt as usize
+	}
+}
+
+#[derive(Copy, Clone, Debug, PartialEq, Eq, Hash)]
+pub struct Binding {
+	pub button: Button,
+	pub modifiers: Modifiers,
+}
+
+impl Binding {
+	pub const fn key(key: VirtualKeyCode) -> Self {
+		Self { button: Button::Key(key), modifiers: Modifiers::NONE }
+	}
+	
+	pub const fn mouse(button: MouseButton) -> Self {
+		Self { button: Button::Mouse(button), modifiers: Modifiers::NONE }
+	}
+	
+	pub const fn gamepad(button: GamepadButton) -> Self {
+		Self { button: Button::Gamepad(button), modifiers: Modifiers::NONE }
+	}
+	
+	pub const fn with_shift(mut self) -> Self { self.modifiers.shift = true; self }
+	pub const fn with_ctrl(mut self) -> Self { self.modifiers.ctrl = true; self }
+	pub const fn with_alt(mut self) -> Self { self.modifiers.alt = true; self }
+	
+	pub fn parse(text: &str) -> Option<Self> {
+		let mut modifiers = Modifiers::NONE;
+		let mut parts = text.split('+').map(str::trim).collect::<Vec<_>>();
+		let button = parse_button(parts.pop()?)?;
+		for part in parts {
+			match part {
+				"Shift" => modifiers.shift = true,
+				"Ctrl" => modifiers.ctrl = true,
+				"Alt" => modifiers.alt = true,
+				_ => return None,
+			}
+		}
+		Some(Self { button, modifiers })
+	}
+}
+
+#[derive(Copy, Clone, Debug, PartialEq)]
+pub struct AxisBinding {
+	pub source: AxisSource,
+	pub scale: f32,
+}
+
+impl AxisBinding {
+	pub fn parse(text: &str) -> Option<Self> {
+		let (source, scale) = match text.split_once('*') {
+			Some((source, scale)) => (source.trim(), scale.trim().parse().ok()?),
+			None => (text.trim(), 1.0),
+		};
+		Some(Self { source: AxisSource::from_name(source)?, scale })
+	}
+}
+
+#[derive(Copy, Clone, Debug, PartialEq)]
+pub struct StickSettings {
+	pub dead_zone: f32, // Fraction of the stick's range that reads as centered
+	pub curve: f32, // Exponent applied to the deflection past the dead zone, > 1 gives finer control near the center
+}
+
+impl Default for StickSettings {
+	fn default() -> Self {
+		Self { dead_zone: 0.15, curve: 2.0 }
+	}
+}
+
+impl StickSettings {
+	// Radial dead zone, so diagonals aren't cut off, then rescaled so output still starts at zero
+	pub fn apply(self, stick: Vec2<f32>) -> Vec2<f32> {
+		let length = stick.length().min(1.0);
+		if length <= self.dead_zone { return Vec2(0.0, 0.0) }
+		
+		let deflection = (length - self.dead_zone) / (1.0 - self.dead_zone);
+		stick.normalize() * deflection.powf(self.curve)
+	}
+}
+
+
+
+// MARK: Action Map
+
+#[derive(Clone, Debug, Default, PartialEq)]
+pub struct ActionMap {
+	actions: HashMap<Action, Vec<Binding>>,
+	axes: HashMap<InputAxis, Vec<AxisBinding>>,
+	pub sticks: StickSettings,
+}
+
+#[derive(Clone, Debug, PartialEq)]
+pub struct BindingError {
+	pub line: usize,
+	pub message: String,
+}
+
+impl Display for BindingError {
+	fn fmt(&self, f: &mut std::fmt::Formatter<'_>) -> std::fmt::Result {
+		write!(f, "line {}: {}", self.line, self.message)
+	}
+}
+
+impl ActionMap {
+	pub fn default_controls() -> Self {
+		Self::parse(DEFAULT_CONTROLS).expect("Default controls failed to parse")
+	}
+	
+	// Falls back to the default controls if the file is missing or broken
+	pub fn load_or_default(assets: &Assets, path: &str) -> Self {
+		match assets.read_to_string(path) {
+			Ok(text) => Self::parse(&text).unwrap_or_else(|e| {
+				eprintln!("Error in {path}, using default controls: {e}");
+				Self::default_controls()
+			}),
+			Err(_) => Self::default_controls(),
+		}
+	}
+	
+	pub fn parse(text: &str) -> Result<Self, BindingError> {
+		let mut map = Self::default();
+		
+		for (i, line) in text.lines().enumerate() {
+			let error = |message: String| BindingError { line: i + 1, message };
+			
+			let line = line.split('#').next().unwrap().trim();
+			if line.is_empty() { continue }
+			
+			let (name, value) = line.split_once('=').ok_or_else(|| error("expected <name> = <bindings>".into()))?;
+			let name = name.trim();
+			let bindings = value.split(',').map(str::trim).filter(|b| !b.is_empty());
+			
+			if name == "StickDeadZone" || name == "StickCurve" {
+				let value = value.trim().parse().map_err(|_| error(format!("expected a number for {name}")))?;
+				match name {
+					"StickDeadZone" => map.sticks.dead_zone = value,
+					_ => map.sticks.curve = value,
+				}
+			} else if let Some(action) = Action::from_name(name) {
+				for text in bindings {
+					map.bind(action, Binding::parse(text).ok_or_else(|| error(format!("unknown binding {text:?}")))?);
+				}
+			} else if let Some(axis) = InputAxis::from_name(name) {
+				for text in bindings {
+					map.bind_axis(axis, AxisBinding::parse(text).ok_or_else(|| error(format!("unknown axis binding {text:?}")))?);
+				}
+			} else {
+				return Err(error(format!("unknown action {name:?}")))
+			}
+		}
+		
+		Ok(map)
+	}
+	
+	pub fn bind(&mut self, action: Action, binding: Binding) {
+		let bindings = self.actions.entry(action).or_default();
+		if !bindings.contains(&binding) {
+			bindings.push(binding);
+		}
+	}
+	
+	pub fn bind_axis(&mut self, axis: InputAxis, binding: AxisBinding) {
+		self.axes.entry(axis).or_default().push(binding);
+	}
+	
+	pub fn unbind_all(&mut self, action: Action) {
+		self.actions.remove(&action);
+	}
+	
+	pub fn bindings(&self, action: Action) -> &[Binding] {
+		self.actions.get(&action).map(Vec::as_slice).unwrap_or(&[])
+	}
+	
+	pub fn axis_bindings(&self, axis: InputAxis) -> &[AxisBinding] {
+		self.axes.get(&axis).map(Vec::as_slice).unwrap_or(&[])
+	}
+	
+	// Writes the map back out in the same format `parse` reads
+	pub fn to_config(&self) -> String {
+		let mut text = String::new();
+		for action in Action::ALL {
+			let bindings = self.bindings(*action);
+			if bindings.is_empty() { continue }
+			text += &format!("{} = {}\n", action.name(), bindings.iter().map(|b| binding_name(*b)).collect::<Vec<_>>().join(", "));
+		}
+		for axis in InputAxis::ALL {
+			let bindings = self.axis_bindings(*axis);
+			if bindings.is_empty() { continue }
+			text += &format!("{} = {}\n", axis.name(), bindings.iter().map(|b| format!("{} * {}", b.source.name(), b.scale)).collect::<Vec<_>>().join(", "));
+		}
+		text += &format!("StickDeadZone = {}\nStickCurve = {}\n", self.sticks.dead_zone, self.sticks.curve);
+		text
+	}
+}
+
+
+fn binding_name(binding: Binding) -> String {
+	let mut name = String::new();
+	if binding.modifiers.shift { name += "Shift+"; }
+	if binding.modifiers.ctrl { name += "Ctrl+"; }
+	if binding.modifiers.alt { name += "Alt+"; }
+	name += &match binding.button {
+		Button::Key(key) => key_name(key).into(),
+		Button::Mouse(MouseButton::Left) => "MouseLeft".into(),
+		Button::Mouse(MouseButton::Right) => "MouseRight".into(),
+		Button::Mouse(MouseButton::Middle) => "MouseMiddle".into(),
+		Button::Mouse(MouseButton::Other(n)) => format!("Mouse{n}"),
+		Button::Gamepad(button) => format!("Pad{}", button.name()),
+	};
+	name
+}
+
+fn parse_button(name: &str) -> Option<Button> {
+	match name {
+		"MouseLeft" => Some(Button::Mouse(MouseButton::Left)),
+		"MouseRight" => Some(Button::Mouse(MouseButton::Right)),
+		"MouseMiddle" => Some(Button::Mouse(MouseButton::Middle)),
+		_ => match name.strip_prefix("Mouse").and_then(|n| n.parse().ok()) {
+			Some(n) => Some(Button::Mouse(MouseButton::Other(n))),
+			None => match name.strip_prefix("Pad").and_then(GamepadButton::from_name) {
+				Some(button) => Some(Button::Gamepad(button)),
+				None => key_from_name(name).map(Button::Key),
+			}
+		}
+	}
+}
+
+
+macro_rules! key_names {
+	($($key:ident),* $(,)?) => {
+		fn key_from_name(name: &str) -> Option<VirtualKeyCode> {
+			match name { $(stringify!($key) => Some(VirtualKeyCode::$key),)* _ => None }
+		}
+		
+		// Every key has to be listed, so that any binding can be written out and read back
+		fn key_name(key: VirtualKeyCode) -> &'static str {
+			match key { $(VirtualKeyCode::$key => stringify!($key)),* }
+		}
+	};
+}
+
+key_names!(
+	Key1, Key2, Key3, Key4, Key5, Key6, Key7, Key8, Key9, Key0,
+	A, B, C, D, E, F, G, H, I, J, K, L, M, N, O, P, Q, R, S, T, U, V, W, X, Y, Z,
+	Escape, F1, F2, F3, F4, F5, F6, F7, F8, F9, F10, F11, F12,
+	F13, F14, F15, F16, F17, F18, F19, F20, F21, F22, F23, F24,
+	Snapshot, Scroll, Pause, Insert, Home, Delete, End, PageDown, PageUp,
+	Left, Up, Right, Down,
+	Back, Return, Space, Tab, Compose, Caret, Numlock, Capital,
+	Numpad0, Numpad1, Numpad2, Numpad3, Numpad4, Numpad5, Numpad6, Numpad7, Numpad8, Numpad9,
+	NumpadAdd, NumpadSubtract, NumpadMultiply, NumpadDivide, NumpadDecimal, NumpadComma, NumpadEnter, NumpadEquals,
+	Apostrophe, Asterisk, At, Backslash, Colon, Comma, Equals, Grave, LBracket, RBracket, Minus, Period, Plus, Semicolon, Slash, Underline,
+	LAlt, LControl, LShift, LWin, RAlt, RControl, RShift, RWin,
+	AbntC1, AbntC2, Apps, Ax, Convert, Kana, Kanji, NoConvert, OEM102, Yen, Sysrq, Unlabeled,
+	Calculator, Mail, MediaSelect, MediaStop, Mute, MyComputer, NavigateForward, NavigateBackward, NextTrack, PlayPause, PrevTrack,
+	Power, Sleep, Stop, VolumeDown, VolumeUp, Wake, WebBack, WebFavorites, WebForward, WebHome, WebRefresh, WebSearch, WebStop,
+	Copy, Paste, Cut,
+);
+
+
+
+#[cfg(test)]
+mod tests {
+	use super::*;
+	
+	#[test]
+	fn default_controls_parse() {
+		let map = ActionMap::default_controls();
+		assert_eq!(map.bindings(Action::MoveForward), &[Binding::key(VirtualKeyCode::W)]);
+		assert_eq!(map.bindings(Action::Jump), &[Binding::key(VirtualKeyCode::Space), Binding::gamepad(GamepadButton::South)]);
+		assert_eq!(map.bindings(Action::Interact)[..2], [Binding::key(VirtualKeyCode::R), Binding::mouse(MouseButton::Left)]);
+		assert_eq!(map.bindings(Action::NewWorld), &[Binding::key(VirtualKeyCode::Grave).with_shift()]);
+		assert_eq!(map.axis_bindings(InputAxis::LookX)[0], AxisBinding { source: AxisSource::MouseX, scale: -0.0008 });
+		
+		for action in Action::ALL {
+			assert!(!map.bindings(*action).is_empty(), "{action:?} has no default binding");
+		}
+	}
+	
+	#[test]
+	fn parse_errors_point_at_line() {
+		assert_eq!(ActionMap::parse("Jump = Space\n\nJump = Spcae").unwrap_err().line, 3);
+		assert_eq!(ActionMap::parse("Fly = Space").unwrap_err().line, 1);
+		assert_eq!(ActionMap::parse("Jump Space").unwrap_err().line, 1);
+		assert_eq!(ActionMap::parse("Jump = Hyper+Space").unwrap_err().line, 1);
+		assert_eq!(ActionMap::parse("LookX = MouseZ").unwrap_err().line, 1);
+		assert_eq!(ActionMap::parse("Jump = PadSouthEast").unwrap_err().line, 1);
+		assert_eq!(ActionMap::parse("StickDeadZone = lots").unwrap_err().line, 1);
+	}
+	
+	#[test]
+	fn config_round_trips() {
+		let map = ActionMap::parse("Jump = Space, Ctrl+Alt+J, Mouse4, PadSouth # comment\nZoom = Wheel * -2.5\nMoveX = LeftStickX\nStickCurve = 1.5\nQuit = Escape").unwrap();
+		assert_eq!(map.bindings(Action::Jump)[2], Binding::mouse(MouseButton::Other(4)));
+		assert_eq!(map.bindings(Action::Jump)[3], Binding::gamepad(GamepadButton::South));
+		assert_eq!(map.sticks, StickSettings { dead_zone: 0.15, curve: 1.5 });
+		assert_eq!(ActionMap::parse(&map.to_config()).unwrap(), map);
+		assert_eq!(ActionMap::parse(&ActionMap::default_controls().to_config()).unwrap(), ActionMap::default_controls());
+		
+		let mut map = ActionMap::default();
+		for key in [VirtualKeyCode::F13, VirtualKeyCode::Yen, VirtualKeyCode::MediaStop, VirtualKeyCode::NumpadEquals] {
+			map.bind(Action::Interact, Binding::key(key));
+		}
+		assert_eq!(ActionMap::parse(&map.to_config()).unwrap(), map);
+	}
+}
@@ -0,0 +1,73 @@
+use gilrs::{Axis, EventType, Gilrs};
+
+use crate::*;
+
+
+// Polls connected gamepads through gilrs. Each pad has its own input state, so it can drive its own player.
+pub struct Gamepads {
+	gilrs: Gilrs,
+}
+
+impl Gamepads {
+	pub fn new() -> Option<Self> {
+		match Gilrs::new() {
+			Ok(gilrs) => Some(Self { gilrs }),
+			Err(e) => {
+				eprintln!("Gamepad support unavailable: {e}");
+				None
+			}
+		}
+	}
+	
+	pub fn poll(&mut self, inputs: &mut LocalInputs) {
+		while let Some(event) = self.gilrs.next_event() {
+			if let Some(translated) = translate_event(event.event) {
+				inputs.gamepad_event(event.id.into(), translated);
+			}
+		}
+	}
+}
+
+
+fn translate_event(event: EventType) -> Option<GamepadEvent> {
+	match event {
+		EventType::ButtonPressed(button, _) => Some(GamepadEvent::Button(translate_button(button)?, true)),
+		EventType::ButtonReleased(button, _) => Some(GamepadEvent::Button(translate_button(button)?, false)),
+		EventType::AxisChanged(axis, value, _) => Some(GamepadEvent::Axis(translate_axis(axis)?, value)),
+		EventType::Disconnected => Some(GamepadEvent::Disconnected),
+		_ => None,
+	}
+}
+
+fn translate_button(button: gilrs::Button) -> Option<GamepadButton> {
+	use gilrs::Button::*;
+	Some(match button {
+		South => GamepadButton::South,
+		East => GamepadButton::East,
+		North => GamepadButton::North,
+		West => GamepadButton::West,
+		LeftTrigger => GamepadButton::LeftBumper,
+		RightTrigger => GamepadButton::RightBumper,
+		LeftTrigger2 => GamepadButton::LeftTrigger,
+		RightTrigger2 => GamepadButton::RightTrigger,
+		Select => GamepadButton::Select,
+		Start => GamepadButton::Start,
+		LeftThumb => GamepadButton::LeftStick,
+		RightThumb => GamepadButton::RightStick,
+		DPadUp => GamepadButton::DPadUp,
+		DPadDown => GamepadButton::DPadDown,
+		DPadLeft => GamepadButton::DPadLeft,
+		DPadRight => GamepadButton::DPadRight,
+		_ => return None,
+	})
+}
+
+fn translate_axis(axis: Axis) -> Option<AxisSource> {
+	match axis {
+		Axis::LeftStickX => Some(AxisSource::LeftStickX),
+		Axis::LeftStickY => Some(AxisSource::LeftStickY),
+		Axis::RightStickX => Some(AxisSource::RightStickX),
+		Axis::RightStickY => Some(AxisSource::RightStickY),
+		_ => None,
+	}
+}
@@ -0,0 +1,393 @@
+use std::collections::{BTreeMap, HashMap, HashSet};
+
+use crate::*;
+
+mod bindings;
+#[cfg(feature = "gamepad")] mod gamepad;
+mod recording;
+
+pub use bindings::*;
+#[cfg(feature = "gamepad")] pub use gamepad::*;
+pub use recording::*;
+
+
+// Everything the simulation needs to know about a local player's input for one tick. The simulation only
+// ever reads input through this, so feeding back recorded ticks reproduces a run exactly.
+#[derive(Copy, Clone, Debug, Default, PartialEq)]
+pub struct TickInput {
+	pub movement: Vec2<f32>, // (right, forward) relative to the view yaw
+	pub look: Vec2<f32>, // Change in (yaw, pitch)
+	pub impulse: Vec2<f32>, // (right, forward) relative to the view yaw
+	pub jump: bool,
+	pub reset: bool,
+	pub new_world: bool,
+	pub switch_entity: bool, // Take control of the next entity
+}
+
+impl TickInput {
+	// Two sources driving the same player at once
+	pub fn combine(self, other: Self) -> Self {
+		Self {
+			movement: self.movement + other.movement,
+			look: self.look + other.look,
+			impulse: self.impulse + other.impulse,
+			jump: self.jump || other.jump,
+			reset: self.reset || other.reset,
+			new_world: self.new_world || other.new_world,
+			switch_entity: self.switch_entity || other.switch_entity,
+		}
+	}
+}
+
+
+// Input from a gamepad, translated from the backend's events so it can also be produced synthetically
+#[derive(Copy, Clone, Debug, PartialEq)]
+pub enum GamepadEvent {
+	Button(GamepadButton, bool),
+	Axis(AxisSource, f32),
+	Disconnected,
+}
+
+
+// Live input state built up from window events. Game code asks about actions rather than keys;
+// simulation actions are sampled once per tick, the rest are taken by the frame loop.
+#[derive(Default)]
+pub struct InputState {
+	pub map: ActionMap,
+	held: HashSet<Button>,
+	modifiers: Modifiers,
+	pressed: HashSet<Action>,
+	axes: HashMap<InputAxis, f32>,
+	sticks: HashMap<AxisSource, f32>,
+}
+
+impl InputState {
+	pub fn new(map: ActionMap) -> Self {
+		Self { map, ..Default::default() }
+	}
+	
+	pub fn set_modifiers(&mut self, modifiers: Modifiers) {
+		self.modifiers = modifiers;
+	}
+	
+	pub fn button(&mut self, button: Button, is_pressed: bool) {
+		if !is_pressed {
+			self.held.remove(&button);
+			return
+		}
+		
+		// Key repeats don't count as new presses
+		if !self.held.insert(button) { return }
+		
+		// Only the most specific bindings fire, so Shift+Grave doesn't also trigger Grave
+		let matching = Action::ALL.iter().flat_map(|&action| self.map.bindings(action).iter().map(move |b| (action, *b)))
+			.filter(|(_, b)| b.button == button && b.modifiers.satisfied_by(self.modifiers))
+			.collect::<Vec<_>>();
+		let specificity = matching.iter().map(|(_, b)| b.modifiers.count()).max();
+		for (action, binding) in matching {
+			if Some(binding.modifiers.count()) == specificity {
+				self.pressed.insert(action);
+			}
+		}
+	}
+	
+	pub fn axis_motion(&mut self, source: AxisSource, value: f32) {
+		if source.is_stick() {
+			self.sticks.insert(source, value);
+			return
+		}
+		
+		for axis in InputAxis::ALL {
+			for binding in self.map.axis_bindings(*axis) {
+				if binding.source == source {
+					*self.axes.entry(*axis).or_default() += value * binding.scale;
+				}
+			}
+		}
+	}
+	
+	pub fn gamepad_event(&mut self, event: GamepadEvent) {
+		match event {
+			GamepadEvent::Button(button, is_pressed) => self.button(Button::Gamepad(button), is_pressed),
+			GamepadEvent::Axis(source, value) => self.axis_motion(source, value),
+			GamepadEvent::Disconnected => {
+				self.held.retain(|button| !matches!(button, Button::Gamepad(_)));
+				self.sticks.clear();
+			}
+		}
+	}
+	
+	// Forget held buttons, e.g. when the window loses focus and release events won't arrive
+	pub fn release_all(&mut self) {
+		self.held.clear();
+		self.modifiers = Modifiers::NONE;
+		self.sticks.clear();
+	}
+	
+	pub fn is_held(&self, action: Action) -> bool {
+		self.map.bindings(action).iter().any(|b| self.held.contains(&b.button) && b.modifiers.satisfied_by(self.modifiers))
+	}
+	
+	// True once per press of any of the action's bindings
+	pub fn take_pressed(&mut self, action: Action) -> bool {
+		self.pressed.remove(&action)
+	}
+	
+	pub fn take_axis(&mut self, axis: InputAxis) -> f32 {
+		self.axes.remove(&axis).unwrap_or(0.0)
+	}
+	
+	// Stick position after the dead zone and curve, which apply to both of a stick's axes together
+	pub fn stick(&self, source: AxisSource) -> f32 {
+		let raw = |source| self.sticks.get(&source).copied().unwrap_or(0.0);
+		let shaped = |x, y| self.map.sticks.apply(Vec2(raw(x), raw(y)));
+		match source {
+			AxisSource::LeftStickX => shaped(AxisSource::LeftStickX, AxisSource::LeftStickY).x(),
+			AxisSource::LeftStickY => shaped(AxisSource::LeftStickX, AxisSource::LeftStickY).y(),
+			AxisSource::RightStickX => shaped(AxisSource::RightStickX, AxisSource::RightStickY).x(),
+			AxisSource::RightStickY => shaped(AxisSource::RightStickX, AxisSource::RightStickY).y(),
+			_ => 0.0,
+		}
+	}
+	
+	// Current value of an axis from the sticks bound to it
+	pub fn held_axis(&self, axis: InputAxis) -> f32 {
+		self.map.axis_bindings(axis).iter().filter(|b| b.source.is_stick()).map(|b| self.stick(b.source) * b.scale).sum()
+	}
+	
+	pub fn sample(&mut self, dt: f64) -> TickInput {
+		use Action::*;
+		
+		let turn_speed = 0.5 * std::f32::consts::PI * dt as f32;
+		let held = |negative, positive| self.is_held(positive) as i8 as f32 - self.is_held(negative) as i8 as f32;
+		
+		let movement = Vec2(held(MoveLeft, MoveRight), held(MoveBack, MoveForward)) + Vec2(self.held_axis(InputAxis::MoveX), self.held_axis(InputAxis::MoveY));
+		let look_keys = Vec2(held(LookRight, LookLeft), held(LookUp, LookDown));
+		let look_sticks = Vec2(self.held_axis(InputAxis::LookX), self.held_axis(InputAxis::LookY));
+		let jump = self.is_held(Jump);
+		
+		let mut pressed = |negative, positive| self.take_pressed(positive) as i8 as f32 - self.take_pressed(negative) as i8 as f32;
+		let impulse = Vec2(pressed(PushLeft, PushRight), pressed(PushBack, PushForward));
+		
+		let new_world = self.take_pressed(NewWorld);
+		let reset = self.take_pressed(ResetPlayer) || new_world;
+		let switch_entity = self.take_pressed(SwitchEntity);
+		
+		TickInput {
+			movement,
+			look: Vec2(self.take_axis(InputAxis::LookX), self.take_axis(InputAxis::LookY)) + look_sticks * dt as f32 + look_keys * turn_speed,
+			impulse,
+			jump,
+			reset,
+			new_world,
+			switch_entity,
+		}
+	}
+}
+
+
+// Where input comes from. The keyboard and mouse together are one source, and each gamepad is another.
+#[derive(Copy, Clone, Debug, PartialEq, Eq, Hash)]
+pub enum InputSource {
+	KeyboardMouse,
+	Gamepad(usize),
+}
+
+// The input of every source, each driving its own local player so several people can play at once.
+// Sources are handed to the players in the order they're first used, wrapping around when there are
+// more sources than players, so a single player is driven by all of them.
+pub struct LocalInputs {
+	pub keyboard_mouse: InputState,
+	gamepads: BTreeMap<usize, InputState>,
+	order: Vec<InputSource>,
+}
+
+impl LocalInputs {
+	pub fn new(map: ActionMap) -> Self {
+		Self { keyboard_mouse: InputState::new(map), gamepads: BTreeMap::new(), order: vec![] }
+	}
+	
+	pub fn gamepad_event(&mut self, gamepad: usize, event: GamepadEvent) {
+		let map = &self.keyboard_mouse.map;
+		self.gamepads.entry(gamepad).or_insert_with(|| InputState::new(map.clone())).gamepad_event(event);
+	}
+	
+	// None until the source has been used
+	pub fn player(&self, source: InputSource, players: usize) -> Option<usize> {
+		self.order.iter().position(|s| *s == source).map(|i| i % players)
+	}
+	
+	fn sources(&mut self) -> impl Iterator<Item = (InputSource, &mut InputState)> {
+		std::iter::once((InputSource::KeyboardMouse, &mut self.keyboard_mouse))
+			.chain(self.gamepads.iter_mut().map(|(gamepad, state)| (InputSource::Gamepad(*gamepad), state)))
+	}
+	
+	pub fn sample(&mut self, dt: f64, players: usize) -> Vec<TickInput> {
+		let sampled = self.sources().map(|(source, state)| (source, state.sample(dt))).collect::<Vec<_>>();
+		let mut inputs = vec![TickInput::default(); players];
+		for (source, input) in sampled {
+			if input == TickInput::default() { continue }
+			if !self.order.contains(&source) {
+				self.order.push(source);
+			}
+			let player = self.player(source, players).unwrap();
+			inputs[player] = inputs[player].combine(input);
+		}
+		inputs
+	}
+	
+	// Actions outside the simulation, like zooming the camera, come from any source
+	pub fn take_pressed(&mut self, action: Action) -> bool {
+		self.sources().fold(false, |pressed, (_, state)| state.take_pressed(action) || pressed)
+	}
+	
+	pub fn take_axis(&mut self, axis: InputAxis) -> f32 {
+		self.sources().map(|(_, state)| state.take_axis(axis)).sum()
+	}
+	
+	pub fn release_all(&mut self) {
+		self.sources().for_each(|(_, state)| state.release_all());
+	}
+}
+
+
+
+#[cfg(test)]
+mod tests {
+	use glium::glutin::event::VirtualKeyCode;
+	
+	use super::*;
+	
+	fn key(key: VirtualKeyCode) -> Button {
+		Button::Key(key)
+	}
+	
+	#[test]
+	fn sampling_clears_pressed_actions() {
+		let mut state = InputState::new(ActionMap::default_controls());
+		state.button(key(VirtualKeyCode::W), true);
+		state.button(key(VirtualKeyCode::A), true);
+		state.button(key(VirtualKeyCode::Right), true);
+		state.button(key(VirtualKeyCode::Grave), true);
+		
+		let first = state.sample(0.01);
+		assert_eq!(first.movement, Vec2(-1.0, 1.0));
+		assert_eq!(first.impulse, Vec2(1.0, 0.0));
+		assert!(first.reset && !first.new_world);
+		
+		// Key repeat while held
+		state.button(key(VirtualKeyCode::Right), true);
+		
+		let second = state.sample(0.01);
+		assert_eq!(second.movement, Vec2(-1.0, 1.0));
+		assert_eq!(second.impulse, Vec2(0.0, 0.0));
+		assert!(!second.reset);
+		
+		state.button(key(VirtualKeyCode::W), false);
+		assert_eq!(state.sample(0.01).movement, Vec2(-1.0, 0.0));
+	}
+	
+	#[test]
+	fn modifier_combos_take_priority() {
+		let mut state = InputState::new(ActionMap::default_controls());
+		state.set_modifiers(Modifiers { shift: true, ..Modifiers::NONE });
+		state.button(key(VirtualKeyCode::Grave), true);
+		assert!(!state.take_pressed(Action::ResetPlayer));
+		assert!(state.take_pressed(Action::NewWorld));
+		
+		// Extra modifiers don't block plain bindings
+		state.button(key(VirtualKeyCode::W), true);
+		assert!(state.is_held(Action::MoveForward));
+	}
+	
+	#[test]
+	fn multiple_bindings_and_axes() {
+		let mut map = ActionMap::parse("Jump = Space, MouseRight\nLookX = MouseX * 2, Wheel * -1").unwrap();
+		map.bind(Action::Interact, Binding::key(VirtualKeyCode::E).with_ctrl());
+		let mut state = InputState::new(map);
+		
+		state.button(Button::Mouse(glium::glutin::event::MouseButton::Right), true);
+		assert!(state.sample(0.01).jump);
+		
+		state.button(key(VirtualKeyCode::E), true);
+		assert!(!state.take_pressed(Action::Interact));
+		state.button(key(VirtualKeyCode::E), false);
+		state.set_modifiers(Modifiers { ctrl: true, ..Modifiers::NONE });
+		state.button(key(VirtualKeyCode::E), true);
+		assert!(state.take_pressed(Action::Interact));
+		
+		state.axis_motion(AxisSource::MouseX, 3.0);
+		state.axis_motion(AxisSource::Wheel, 1.0);
+		state.axis_motion(AxisSource::MouseY, 5.0);
+		assert_eq!(state.sample(0.01).look, Vec2(5.0, 0.0));
+		assert_eq!(state.take_axis(InputAxis::LookX), 0.0);
+	}
+	
+	#[test]
+	fn gamepad_sticks_and_buttons() {
+		let mut state = InputState::new(ActionMap::default_controls());
+		state.map.sticks = StickSettings { dead_zone: 0.2, curve: 1.0 };
+		
+		// Resting drift stays inside the dead zone
+		state.gamepad_event(GamepadEvent::Axis(AxisSource::LeftStickX, 0.1));
+		state.gamepad_event(GamepadEvent::Axis(AxisSource::LeftStickY, -0.1));
+		assert_eq!(state.sample(0.01).movement, Vec2(0.0, 0.0));
+		
+		// Sticks are held, not consumed by sampling
+		state.gamepad_event(GamepadEvent::Axis(AxisSource::LeftStickX, 0.0));
+		state.gamepad_event(GamepadEvent::Axis(AxisSource::LeftStickY, 0.6));
+		assert!((state.sample(0.01).movement.y() - 0.5).abs() < 1e-6);
+		assert!((state.sample(0.01).movement.y() - 0.5).abs() < 1e-6);
+		
+		state.gamepad_event(GamepadEvent::Axis(AxisSource::RightStickX, 1.0));
+		assert!((state.sample(0.5).look.x() - -1.5).abs() < 1e-6);
+		
+		state.gamepad_event(GamepadEvent::Button(GamepadButton::South, true));
+		state.gamepad_event(GamepadEvent::Button(GamepadButton::Select, true));
+		let input = state.sample(0.01);
+		assert!(input.jump && input.reset);
+		
+		state.gamepad_event(GamepadEvent::Disconnected);
+		let input = state.sample(0.01);
+		assert!(!input.jump);
+		assert_eq!(input.movement, Vec2(0.0, 0.0));
+		assert_eq!(input.look, Vec2(0.0, 0.0));
+	}
+	
+	#[test]
+	fn stick_dead_zone_and_curve() {
+		let settings = StickSettings { dead_zone: 0.2, curve: 2.0 };
+		assert_eq!(settings.apply(Vec2(0.15, 0.1)), Vec2(0.0, 0.0));
+		assert!((settings.apply(Vec2(0.0, -0.6)).y() - -0.25).abs() < 1e-6);
+		assert!((settings.apply(Vec2(1.0, 1.0)).length() - 1.0).abs() < 1e-6);
+		
+		// Radial, so a diagonal just past the dead zone keeps both components
+		let diagonal = settings.apply(Vec2(0.2, 0.2));
+		assert!(diagonal.x() > 0.0 && diagonal.x() == diagonal.y());
+	}
+	
+	#[test]
+	fn sources_drive_their_own_players() {
+		let mut inputs = LocalInputs::new(ActionMap::default_controls());
+		inputs.gamepad_event(3, GamepadEvent::Button(GamepadButton::South, true));
+		inputs.sample(0.01, 2);
+		inputs.keyboard_mouse.button(key(VirtualKeyCode::W), true);
+		
+		// The pad was used first, so it has the first player
+		let sampled = inputs.sample(0.01, 2);
+		assert_eq!(inputs.player(InputSource::Gamepad(3), 2), Some(0));
+		assert_eq!(inputs.player(InputSource::KeyboardMouse, 2), Some(1));
+		assert!(sampled[0].jump && sampled[0].movement == Vec2(0.0, 0.0));
+		assert!(!sampled[1].jump && sampled[1].movement == Vec2(0.0, 1.0));
+		
+		// With one player they drive it together, and a pad that was never used drives nobody
+		let sampled = inputs.sample(0.01, 1);
+		assert!(sampled[0].jump && sampled[0].movement == Vec2(0.0, 1.0));
+		assert_eq!(inputs.player(InputSource::Gamepad(4), 1), None);
+		
+		// Frame actions come from any of them
+		inputs.gamepad_event(3, GamepadEvent::Button(GamepadButton::North, true));
+		assert!(inputs.take_pressed(Action::ToggleCamera));
+		assert!(!inputs.take_pressed(Action::ToggleCamera));
+	}
+}
@@ -0,0 +1,233 @@
+use std::{fs::File, io::{BufRead, BufReader, BufWriter, Write}, path::Path};
+
+use crate::*;
+
+
+// Recordings are plain text so they can be attached to bug reports and diffed. The first lines are the
+// header, then there is one line per tick with the input of each local player, separated by `|`,
+// and the state hash after the tick ran:
+//
+// engine2d-input 5
+// seed 1234
+// spawns <spawn table hash>
+// <movement x> <movement y> <look x> <look y> <impulse x> <impulse y> <flags> | <second player...> <state hash>
+//
+// Floats are written with Rust's shortest round-trip formatting so they read back bit for bit.
+// The version goes up whenever the format or what goes into the state hash changes. Older recordings
+// are refused, since every tick would be reported as diverged.
+
+const RECORDING_MAGIC: &str = "engine2d-input";
+const RECORDING_VERSION: u32 = 5;
+
+const FLAG_JUMP: u8 = 1;
+const FLAG_RESET: u8 = 2;
+const FLAG_NEW_WORLD: u8 = 4;
+const FLAG_SWITCH_ENTITY: u8 = 8;
+
+
+#[derive(Clone, Debug, PartialEq)]
+pub struct RecordedTick {
+	pub inputs: Vec<TickInput>, // One for each local player
+	pub state_hash: Option<u64>,
+}
+
+#[derive(Clone, Debug, Default, PartialEq)]
+pub struct InputRecording {
+	pub seed: u64,
+	pub spawn_table_hash: u64,
+	pub ticks: Vec<RecordedTick>,
+}
+
+impl InputRecording {
+	pub fn load(path: impl AsRef<Path>) -> std::io::Result<Self> {
+		Self::read(BufReader::new(File::open(path)?))
+	}
+	
+	pub fn save(&self, path: impl AsRef<Path>) -> std::io::Result<()> {
+		let mut writer = BufWriter::new(File::create(path)?);
+		write_header(&mut writer, self.seed, self.spawn_table_hash)?;
+		for tick in &self.ticks {
+			write_tick(&mut writer, tick)?;
+		}
+		writer.flush()
+	}
+	
+	pub fn read(reader: impl BufRead) -> std::io::Result<Self> {
+		let mut lines = reader.lines().enumerate();
+		let mut next_line = || -> std::io::Result<Option<(usize, String)>> {
+			lines.next().map(|(i, line)| line.map(|line| (i + 1, line))).transpose()
+		};
+		
+		let (_, header) = next_line()?.ok_or_else(|| invalid_data(1, "empty recording"))?;
+		match header.split_once(' ') {
+			Some((RECORDING_MAGIC, version)) if version.parse() == Ok(RECORDING_VERSION) => (),
+			_ => return Err(invalid_data(1, &format!("expected \"{RECORDING_MAGIC} {RECORDING_VERSION}\""))),
+		}
+		
+		let (n, seed_line) = next_line()?.ok_or_else(|| invalid_data(2, "missing seed"))?;
+		let seed = seed_line.strip_prefix("seed ").and_then(|v| v.trim().parse().ok()).ok_or_else(|| invalid_data(n, "expected \"seed <number>\""))?;
+		
+		let (n, spawns_line) = next_line()?.ok_or_else(|| invalid_data(3, "missing spawn table hash"))?;
+		let spawn_table_hash = spawns_line.strip_prefix("spawns ").and_then(|v| u64::from_str_radix(v.trim(), 16).ok()).ok_or_else(|| invalid_data(n, "expected \"spawns <hash>\""))?;
+		
+		let mut ticks = vec![];
+		while let Some((n, line)) = next_line()? {
+			if line.trim().is_empty() { continue }
+			ticks.push(parse_tick(&line).ok_or_else(|| invalid_data(n, "malformed tick"))?);
+		}
+		
+		Ok(Self { seed, spawn_table_hash, ticks })
+	}
+}
+
+
+// Writes ticks to disk as they happen, so a recording survives the game crashing
+pub struct InputRecorder {
+	writer: BufWriter<File>,
+}
+
+impl InputRecorder {
+	pub fn create(path: impl AsRef<Path>, seed: u64, spawn_table_hash: u64) -> std::io::Result<Self> {
+		let mut writer = BufWriter::new(File::create(path)?);
+		write_header(&mut writer, seed, spawn_table_hash)?;
+		writer.flush()?;
+		Ok(Self { writer })
+	}
+	
+	pub fn record(&mut self, inputs: &[TickInput], state_hash: u64) -> std::io::Result<()> {
+		write_tick(&mut self.writer, &RecordedTick { inputs: inputs.to_vec(), state_hash: Some(state_hash) })?;
+		self.writer.flush()
+	}
+}
+
+
+fn invalid_data(line: usize, message: &str) -> std::io::Error {
+	std::io::Error::new(std::io::ErrorKind::InvalidData, format!("line {line}: {message}"))
+}
+
+fn write_header(writer: &mut impl Write, seed: u64, spawn_table_hash: u64) -> std::io::Result<()> {
+	writeln!(writer, "{RECORDING_MAGIC} {RECORDING_VERSION}")?;
+	writeln!(writer, "seed {seed}")?;
+	writeln!(writer, "spawns {spawn_table_hash:016x}")
+}
+
+fn write_tick(writer: &mut impl Write, tick: &RecordedTick) -> std::io::Result<()> {
+	for (i, input) in tick.inputs.iter().enumerate() {
+		if i > 0 { write!(writer, " | ")? }
+		let flags = (input.jump as u8 * FLAG_JUMP) | (input.reset as u8 * FLAG_RESET) | (input.new_world as u8 * FLAG_NEW_WORLD) | (input.switch_entity as u8 * FLAG_SWITCH_ENTITY);
+		write!(writer, "{} {} {} {} {} {} {}",
+			input.movement.x(), input.movement.y(),
+			input.look.x(), input.look.y(),
+			input.impulse.x(), input.impulse.y(),
+			flags,
+		)?;
+	}
+	match tick.state_hash {
+		Some(hash) => writeln!(writer, " {hash:016x}"),
+		None => writeln!(writer),
+	}
+}
+
+fn parse_tick(line: &str) -> Option<RecordedTick> {
+	let players = line.split('|').collect::<Vec<_>>();
+	let mut inputs = vec![];
+	let mut state_hash = None;
+	
+	for (i, player) in players.iter().enumerate() {
+		let mut fields = player.split_whitespace();
+		inputs.push(parse_input(&mut fields)?);
+		
+		// Only the last player is followed by the state hash
+		if let Some(hash) = fields.next() {
+			if i + 1 < players.len() { return None }
+			state_hash = Some(u64::from_str_radix(hash, 16).ok()?);
+		}
+		if fields.next().is_some() { return None }
+	}
+	
+	Some(RecordedTick { inputs, state_hash })
+}
+
+fn parse_input<'a>(fields: &mut impl Iterator<Item = &'a str>) -> Option<TickInput> {
+	let mut float = || fields.next()?.parse::<f32>().ok();
+	let movement = Vec2(float()?, float()?);
+	let look = Vec2(float()?, float()?);
+	let impulse = Vec2(float()?, float()?);
+	
+	let flags = fields.next()?.parse::<u8>().ok()?;
+	
+	Some(TickInput {
+		movement,
+		look,
+		impulse,
+		jump: flags & FLAG_JUMP != 0,
+		reset: flags & FLAG_RESET != 0,
+		new_world: flags & FLAG_NEW_WORLD != 0,
+		switch_entity: flags & FLAG_SWITCH_ENTITY != 0,
+	})
+}
+
+
+
+#[cfg(test)]
+mod tests {
+	use super::*;
+	
+	#[test]
+	fn recording_round_trips_exactly() {
+		let recording = InputRecording {
+			seed: 42,
+			spawn_table_hash: 0x0123456789abcdef,
+			ticks: vec![
+				RecordedTick { inputs: vec![TickInput::default()], state_hash: Some(0) },
+				RecordedTick {
+					inputs: vec![TickInput {
+						movement: Vec2(-1.0, 1.0),
+						look: Vec2(0.1 + 0.2, -1e-7),
+						impulse: Vec2(0.0, -0.0),
+						jump: true,
+						reset: false,
+						new_world: true,
+						switch_entity: true,
+					}],
+					state_hash: Some(u64::MAX),
+				},
+				RecordedTick { inputs: vec![TickInput { reset: true, ..Default::default() }, TickInput { jump: true, ..Default::default() }], state_hash: None },
+				RecordedTick { inputs: vec![TickInput::default(), TickInput { movement: Vec2(0.5, 0.0), ..Default::default() }], state_hash: Some(7) },
+			],
+		};
+		
+		let mut bytes = vec![];
+		write_header(&mut bytes, recording.seed, recording.spawn_table_hash).unwrap();
+		for tick in &recording.ticks {
+			write_tick(&mut bytes, tick).unwrap();
+		}
+		
+		let read = InputRecording::read(bytes.as_slice()).unwrap();
+		assert_eq!(read, recording);
+		assert_eq!(read.ticks[1].inputs[0].look.x().to_bits(), (0.1f32 + 0.2).to_bits());
+	}
+	
+	#[test]
+	fn malformed_recordings_report_line() {
+		let error = InputRecording::read("engine2d-input 5\nseed 3\nspawns ab\n0 0 0 0 0 0 0\n0 0 zero 0 0 0 0\n".as_bytes()).unwrap_err();
+		assert_eq!(error.kind(), std::io::ErrorKind::InvalidData);
+		assert!(error.to_string().starts_with("line 5"), "{error}");
+		
+		assert!(InputRecording::read("engine2d-input 6\nseed 3\nspawns ab\n".as_bytes()).is_err());
+		
+		let error = InputRecording::read("engine2d-input 5\nseed 3\n0 0 0 0 0 0 0\n".as_bytes()).unwrap_err();
+		assert!(error.to_string().starts_with("line 3"), "{error}");
+		
+		// Only the last player has the state hash after it
+		assert!(InputRecording::read("engine2d-input 5\nseed 3\nspawns ab\n0 0 0 0 0 0 0 ff | 0 0 0 0 0 0 0\n".as_bytes()).is_err());
+		
+		// Older recordings hash a different state
+		assert!(InputRecording::read("engine2d-input 1\nseed 3\n0 1 0 0 0 0 1 ff\n".as_bytes()).is_err());
+		assert!(InputRecording::read("engine2d-input 2\nseed 3\n0 1 0 0 0 0 1 ff\n".as_bytes()).is_err());
+		assert!(InputRecording::read("engine2d-input 3\nseed 3\n0 1 0 0 0 0 1 ff\n".as_bytes()).is_err());
+		
+		// Nor do ones without the spawn table
+		assert!(InputRecording::read("engine2d-input 4\nseed 3\n0 1 0 0 0 0 1 ff\n".as_bytes()).is_err());
+	}
+}
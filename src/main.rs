@@ -1,20 +1,40 @@
-use std::{collections::HashMap, f32::consts::PI};
+use std::{collections::HashMap, path::Path};
 
-use glium::{draw_parameters::DepthClamp, framebuffer::MultiOutputFrameBuffer, glutin::{dpi::{PhysicalPosition, PhysicalSize}, event::{ElementState, Event, KeyboardInput, MouseButton, VirtualKeyCode, WindowEvent}, event_loop::{ControlFlow, EventLoop}, window::{CursorGrabMode, Icon, WindowBuilder}, ContextBuilder}, index::PrimitiveType, texture::{DepthTexture2d, Texture2d}, uniforms::{MagnifySamplerFilter, MinifySamplerFilter, Sampler, SamplerBehavior, SamplerWrapFunction, UniformsStorage}, BackfaceCullingMode, Blend, BlendingFunction, Depth, DepthTest, Display, DrawParameters, IndexBuffer, LinearBlendingFactor, Surface, VertexBuffer};
+use glium::{draw_parameters::DepthClamp, glutin::{dpi::{PhysicalPosition, PhysicalSize}, event::{ElementState, Event, KeyboardInput, MouseScrollDelta, WindowEvent}, event_loop::{ControlFlow, EventLoop}, window::{CursorGrabMode, Icon, WindowBuilder}, ContextBuilder}, index::PrimitiveType, uniforms::{MagnifySamplerFilter, MinifySamplerFilter, Sampler, SamplerBehavior, SamplerWrapFunction, UniformsStorage}, BackfaceCullingMode, Blend, BlendingFunction, Depth, DepthTest, Display, DrawParameters, IndexBuffer, LinearBlendingFactor, Surface, VertexBuffer};
 
 #[allow(dead_code)] mod math;
 #[allow(dead_code)] mod entity;
+#[allow(dead_code)] mod animation;
 #[allow(dead_code)] mod physics;
 #[allow(dead_code)] mod graphics;
+#[allow(dead_code)] mod assets;
+#[allow(dead_code)] mod atlas;
+#[allow(dead_code)] mod shader;
 #[allow(dead_code)] mod tiles;
 #[allow(dead_code)] mod world;
+#[allow(dead_code)] mod input;
+#[allow(dead_code)] mod game;
+#[allow(dead_code)] mod camera;
+#[allow(dead_code)] mod culling;
+#[allow(dead_code)] mod post_process;
+#[allow(dead_code)] mod hot_reload;
 
 use math::*;
 use entity::*;
+use animation::*;
 use physics::*;
 use graphics::*;
+use assets::*;
+use atlas::*;
+use shader::*;
 use tiles::*;
 use world::*;
+use input::*;
+use game::*;
+use camera::*;
+use culling::*;
+use post_process::*;
+use hot_reload::*;
 
 
 
@@ -31,17 +51,110 @@ impl IsPressed for ElementState {
 }
 
 
+const MAX_TICKS_PER_FRAME: usize = 10;
+const WINDOW_TITLE: &str = "balls";
+
+struct Options {
+	seed: Option<u64>,
+	record: Option<String>,
+	replay: Option<String>,
+	view_distance: Option<f64>,
+	players: usize,
+}
+
+impl Options {
+	fn from_args() -> Self {
+		let mut options = Self { seed: None, record: None, replay: None, view_distance: None, players: 1 };
+		let mut args = std::env::args().skip(1);
+		while let Some(arg) = args.next() {
+			match arg.as_str() {
+				"--seed" => options.seed = Some(args.next().and_then(|v| v.parse().ok()).expect("--seed expects an unsigned integer")),
+				"--record" => options.record = Some(args.next().expect("--record expects a file path")),
+				"--replay" => options.replay = Some(args.next().expect("--replay expects a file path")),
+				"--players" => options.players = args.next().and_then(|v| v.parse().ok()).filter(|n| *n > 0).expect("--players expects a positive number"),
+				"--view-distance" => options.view_distance = Some(args.next().and_then(|v| v.parse().ok()).expect("--view-distance expects a number of tiles")),
+				_ => panic!("Unknown argument {arg}"),
+			}
+		}
+		options
+	}
+}
 
 
+// Hides and confines the cursor so mouse movement can turn the view
+fn set_mouse_look(display: &Display, enabled: bool) {
+	let window = display.gl_window();
+	let window = window.window();
+	window.set_cursor_grab(match enabled { true => CursorGrabMode::Confined, false => CursorGrabMode::None }).unwrap();
+	window.set_cursor_visible(!enabled);
+	
+	if enabled {
+		let size = window.inner_size();
+		window.set_cursor_position(PhysicalPosition::new(size.width / 2, size.height / 2)).unwrap();
+	}
+}
+
+// Errors go to the log, and the first one is shown in the title bar until the next reload
+fn show_reload_errors(display: &Display, errors: &[String]) {
+	for error in errors {
+		eprintln!("{error}");
+	}
+	
+	let title = match errors.first().and_then(|e| e.lines().next()) {
+		Some(error) => format!("{WINDOW_TITLE} - {error}"),
+		None => WINDOW_TITLE.to_string(),
+	};
+	display.gl_window().window().set_title(&title);
+}
+
+// Every local player's entity gets the player sprites, wherever control has moved to
+fn set_player_sprites(game: &mut Game, sprites: &SpriteSet) {
+	for player in 0..game.players.len() {
+		if let Some(sprite) = game.entity(player).and_then(|id| game.world.entities.sprites.get_mut(id)) {
+			sprite.sprites = sprites.clone();
+		}
+	}
+}
+
+fn run_replay(path: &str) -> ! {
+	let recording = match InputRecording::load(path) {
+		Ok(recording) => recording,
+		Err(e) => {
+			eprintln!("Could not read recording {path}: {e}");
+			std::process::exit(2);
+		}
+	};
+	
+	match replay(&recording, &SpawnTable::load_or_default(&Assets::locate(), SPAWNS_PATH)) {
+		ReplayResult::Finished { ticks, state_hash } => {
+			println!("Replayed {ticks} ticks with seed {}, final state hash {state_hash:016x}", recording.seed);
+			std::process::exit(0);
+		}
+		ReplayResult::Diverged { tick, expected, actual } => {
+			eprintln!("Replay diverged on tick {tick}: expected state hash {expected:016x}, got {actual:016x}");
+			std::process::exit(1);
+		}
+		ReplayResult::SpawnTableChanged { expected, actual } => {
+			eprintln!("Recording was made with a different spawn table than {SPAWNS_PATH}: expected hash {expected:016x}, got {actual:016x}");
+			std::process::exit(2);
+		}
+	}
+}
+
 
 
 fn main() {
 	
+	let options = Options::from_args();
+	if let Some(path) = &options.replay {
+		run_replay(path);
+	}
+	
 	// MARK: Setup
 	
 	let event_loop = EventLoop::new();
 	let wb = WindowBuilder::new()
-		.with_title("balls")
+		.with_title(WINDOW_TITLE)
 		// .with_fullscreen(Some(glium::winit::window::Fullscreen::Borderless(None)))
 		.with_maximized(true)
 		// .with_inner_size(Some(glium::winit::dpi::Size::Logical(LogicalSize { width: 300.0, height: 300.0 })))
@@ -55,13 +168,15 @@ fn main() {
 	let display = Display::new(wb, cb, &event_loop).unwrap();
 	
 	
-	let mut world_program = load_shader_program(&display, "tilemap", "tilemap");
-	let mut world_debug_program = load_shader_program(&display, "tilemap_debug", "tilemap_debug");
-	let _screen_texture_program = load_shader_program(&display, "screen_rectangle", "screen_rectangle");
-	let mut post_program = load_shader_program(&display, "default", "post_process");
+	let mut assets = Assets::locate();
+	let mut file_watcher = assets.directory().map(|root| FileWatcher::new(&[root], WATCH_INTERVAL));
 	
-	let rect_vertex_buffer = VertexBuffer::new(&display, &[Vec2(0.0f32, 0.0), Vec2(1.0, 0.0), Vec2(1.0, 1.0), Vec2(0.0, 1.0)]).unwrap();
-	let rect_index_buffer = IndexBuffer::new(&display, PrimitiveType::TrianglesList, &[0, 1, 2, 0, 2, 3u8]).unwrap();
+	let mut world_program = ShaderProgram::new(&display, &assets, "tilemap", "tilemap", &[]).unwrap_or_else(|e| panic!("{e}"));
+	let mut world_debug_program = ShaderProgram::new(&display, &assets, "tilemap", "tilemap", &["VERTEX_COLOR"]).unwrap_or_else(|e| panic!("{e}"));
+	let mut sprite_program = ShaderProgram::new(&display, &assets, "tilemap", "tilemap", &["SPRITE"]).unwrap_or_else(|e| panic!("{e}"));
+	let _screen_texture_program = ShaderProgram::new(&display, &assets, "screen_rectangle", "screen_rectangle", &[]).unwrap_or_else(|e| panic!("{e}"));
+	let post_settings = PostSettings::load_or_default(&assets, POST_PROCESS_PATH);
+	let mut post_chain = PostChain::new(&display, &mut assets, &post_settings).unwrap_or_else(|e| panic!("{e}"));
 	
 	
 	let oct_vertex_buffer = VertexBuffer::new(&display, &[NZ, PZ, NY, PY, NX, PX].map(|d| ModelDebugVertex {
@@ -73,66 +188,45 @@ fn main() {
 	
 	
 	let PhysicalSize { width: window_width, height: window_height } = display.gl_window().window().inner_size();
-	let mut aspect_ratio = window_width as f32 / window_height as f32;
-	
-	let mut tile_size = 1.0/30.0; //1.0/10.0;
-	let tile_depth = 1.0/128.0;
-	
-	let mut screen_texture = Texture2d::empty(&display, window_width, window_height).unwrap();
-	let mut data_texture = Texture2d::empty(&display, window_width, window_height).unwrap();
-	let mut depth_texture = DepthTexture2d::empty(&display, window_width, window_height).unwrap();
+	let mut camera = Camera::new(CameraMode::FirstPerson, window_width as f32 / window_height as f32);
+	if let Some(view_distance) = options.view_distance {
+		camera.view_distance = view_distance;
+	}
+	let mut cull_stats = CullStats::default();
 	
-	let tilemap_texture = load_texture(&display, "tilemap");
+	let mut render_targets = RenderTargets::new(&display, (window_width, window_height));
 	
+	let mut tile_atlas = TileAtlas::load(&display, &assets).unwrap_or_else(|e| panic!("{e}"));
 	
-	let mut key_w = false;
-	let mut key_a = false;
-	let mut key_s = false;
-	let mut key_d = false;
 	
-	let mut key_i = false;
-	let mut key_j = false;
-	let mut key_k = false;
-	let mut key_l = false;
+	let mut input = LocalInputs::new(ActionMap::load_or_default(&assets, "config/controls.cfg"));
+	#[cfg(feature = "gamepad")]
+	let mut gamepads = Gamepads::new();
 	
-	let mut key_shift = false;
-	let mut _key_ctrl = false;
-	let mut key_space = false;
 	
-	let mut u = 0.0f32;
-	let mut v = 0.0f32;//PI / 3.0;
 	
+	let mut mouse_look = camera.mode.is_perspective();
+	set_mouse_look(&display, mouse_look);
 	
 	
-	let mut first_person = true;
 	
-	display.gl_window().window().set_cursor_grab(match first_person { true => CursorGrabMode::Confined, false => CursorGrabMode::None }).unwrap();
-	display.gl_window().window().set_cursor_visible(!first_person);
+	let seed = options.seed.unwrap_or_else(|| (std::time::SystemTime::now().duration_since(std::time::UNIX_EPOCH).unwrap().as_millis() & (u64::MAX as u128)) as u64);
+	println!("World seed: {seed}");
 	
-	if first_person {
-		let size = display.gl_window().window().inner_size();
-		let center_x = size.width / 2;
-		let center_y = size.height / 2;
-		display.gl_window().window().set_cursor_position(PhysicalPosition::new(center_x, center_y)).unwrap();
+	let mut game = Game::new(seed);
+	game.world.set_tile_uvs(tile_atlas.uvs.clone());
+	game.world.spawn_table = SpawnTable::load_or_default(&assets, SPAWNS_PATH);
+	while game.players.len() < options.players {
+		game.add_player();
 	}
+	let player_sprites = assets.sprites(&display, "player").unwrap_or_else(|e| panic!("{e}"));
+	set_player_sprites(&mut game, &player_sprites);
+	let mut sprite_sets = HashMap::from([("player".to_string(), player_sprites.clone())]); // By name, for entities that come without sprites
 	
+	// Every input source drives its own local player, and the camera follows one of them
+	let mut active_player = 0;
 	
-	
-	let mut world = World::new();
-	
-	let player_pos = world.place_player(Vec3(0.5, 0.5, CELL_HEIGHT as f64));
-	world.entities.push(Entity::new(
-		player_pos,
-		Vec3(0.70, 0.70, 1.75),
-		SpriteSet::load(&display, "player")
-	));
-	world.entities[0].show = false;
-	
-	for entity in &mut world.entities {
-		if entity.mesh_buffers.is_none() {
-			entity.load_mesh_buffers(&display);
-		}
-	}
+	let mut recorder = options.record.as_ref().map(|path| InputRecorder::create(path, seed, game.world.spawn_table.state_hash()).unwrap_or_else(|e| panic!("Could not create recording {path}: {e}")));
 	
 	
 	
@@ -141,7 +235,7 @@ fn main() {
 	
 	
 	// let _generator_thread = std::thread::spawn(move || {
-		
+	
 	// });
 	
 	let mut edit_position = None;
@@ -149,6 +243,7 @@ fn main() {
 	
 	
 	let mut previous_frame_time = std::time::Instant::now();
+	let mut tick_accumulator = 0.0;
 	
 	event_loop.run(move |event, _window_target, control_flow| {
 		match event {
@@ -161,107 +256,42 @@ fn main() {
 				}
 				
 				WindowEvent::Resized(physical_size) => {
-					aspect_ratio = physical_size.width as f32 / physical_size.height as f32;
-					screen_texture = Texture2d::empty(&display, physical_size.width, physical_size.height).unwrap();
-					data_texture = Texture2d::empty(&display, physical_size.width, physical_size.height).unwrap();
-					depth_texture = DepthTexture2d::empty(&display, physical_size.width, physical_size.height).unwrap();
+					camera.aspect_ratio = physical_size.width as f32 / physical_size.height as f32;
+					render_targets.resize(&display, (physical_size.width, physical_size.height));
+				}
+				
+				WindowEvent::Focused(false) => {
+					input.release_all();
 				}
 				
 				WindowEvent::CursorMoved { position, device_id: _, .. } => {
-					if first_person {
+					if mouse_look {
 						let size = display.gl_window().window().inner_size();
 						let center_x = size.width / 2;
 						let center_y = size.height / 2;
-						u -= (position.x as f32 - center_x as f32) * 0.0008;
-						v += (position.y as f32 - center_y as f32) * 0.0008;
-						if v < -0.5*PI { v = -0.5*PI }
-						if v > 0.5*PI { v = 0.5*PI }
+						input.keyboard_mouse.axis_motion(AxisSource::MouseX, position.x as f32 - center_x as f32);
+						input.keyboard_mouse.axis_motion(AxisSource::MouseY, position.y as f32 - center_y as f32);
 						display.gl_window().window().set_cursor_position(PhysicalPosition::new(center_x, center_y)).unwrap();
 					}
 				}
 				
-				WindowEvent::MouseInput { state, button, device_id: _, .. } => match button {
-					MouseButton::Left => if state == ElementState::Pressed {
-						// println!("click");
-					},
-					_ => ()
+				WindowEvent::MouseInput { state, button, device_id: _, .. } => {
+					input.keyboard_mouse.button(Button::Mouse(button), state.is_pressed());
 				}
 				
-				WindowEvent::MouseWheel { device_id: _, delta: _, phase: _, .. } => {
-					
+				WindowEvent::MouseWheel { delta, device_id: _, phase: _, .. } => {
+					input.keyboard_mouse.axis_motion(AxisSource::Wheel, match delta {
+						MouseScrollDelta::LineDelta(_, y) => y,
+						MouseScrollDelta::PixelDelta(position) => position.y as f32 / 20.0,
+					});
+				}
+				
+				WindowEvent::ModifiersChanged(modifiers) => {
+					input.keyboard_mouse.set_modifiers(Modifiers { shift: modifiers.shift(), ctrl: modifiers.ctrl(), alt: modifiers.alt() });
 				}
 				
 				WindowEvent::KeyboardInput { input: KeyboardInput { virtual_keycode: Some(keycode), state, scancode: _, .. }, device_id: _, is_synthetic: _} => {
-					match keycode {
-						VirtualKeyCode::W => key_w = state.is_pressed(),
-						VirtualKeyCode::S => key_s = state.is_pressed(),
-						VirtualKeyCode::A => key_a = state.is_pressed(),
-						VirtualKeyCode::D => key_d = state.is_pressed(),
-						VirtualKeyCode::LShift | VirtualKeyCode::RShift => key_shift = state.is_pressed(),
-						VirtualKeyCode::LControl | VirtualKeyCode::RControl => _key_ctrl = state.is_pressed(),
-						VirtualKeyCode::Space => key_space = state.is_pressed(),
-						
-						VirtualKeyCode::Up    => if state.is_pressed() { world.entities[0].velocity += Vec3(-u.sin(), -u.cos(), 0.0).as_type::<f64>() * 30.0; }
-						VirtualKeyCode::Down  => if state.is_pressed() { world.entities[0].velocity += Vec3( u.sin(),  u.cos(), 0.0).as_type::<f64>() * 30.0; }
-						VirtualKeyCode::Left  => if state.is_pressed() { world.entities[0].velocity += Vec3(-u.cos(),  u.sin(), 0.0).as_type::<f64>() * 30.0; }
-						VirtualKeyCode::Right => if state.is_pressed() { world.entities[0].velocity += Vec3( u.cos(), -u.sin(), 0.0).as_type::<f64>() * 30.0; }
-						
-						VirtualKeyCode::Minus => if state.is_pressed() {
-							tile_size /= 1.1;
-						}
-						VirtualKeyCode::Equals => if state.is_pressed() {
-							tile_size *= 1.1;
-						}
-						
-						VirtualKeyCode::F => if state.is_pressed() {
-							first_person = !first_person;
-							display.gl_window().window().set_cursor_grab(match first_person { true => CursorGrabMode::Confined, false => CursorGrabMode::None }).unwrap();
-							display.gl_window().window().set_cursor_visible(!first_person);
-						}
-						
-						VirtualKeyCode::R => if state.is_pressed() {
-							//world.entities[0].velocity += Vec3(-u.sin()*v.cos(), -u.cos()*v.cos(), -v.sin()).as_type::<f64>() * 30.0;
-							edit_position = crate::raycast::cast_ray(&world.cells, world.entities[0].position + world.entities[0].size.component(Z) * 0.8, Vec3(
-								-u.sin() * v.cos(),
-								-u.cos() * v.cos(),
-								-v.sin(),
-							).as_type::<f64>() * 12.0).map(|(tile_pos, _incidence)| tile_pos);
-						}
-						
-						VirtualKeyCode::I => key_i = state.is_pressed(),
-						VirtualKeyCode::K => key_k = state.is_pressed(),
-						VirtualKeyCode::J => key_j = state.is_pressed(),
-						VirtualKeyCode::L => key_l = state.is_pressed(),
-						
-						VirtualKeyCode::F1 => if state.is_pressed() {
-							world_program = load_shader_program(&display, "tilemap", "tilemap");
-							world_debug_program = load_shader_program(&display, "tilemap_debug", "tilemap_debug");
-							post_program = load_shader_program(&display, "default", "post_process");
-						}
-						
-						VirtualKeyCode::Grave => if state.is_pressed() {
-							if key_shift {
-								let mut w = World::new();
-								w.entities.append(&mut world.entities.drain(0..=0).collect());
-								world = w;
-							}
-							
-							world.entities[0].position = world.place_player(Vec3(0.5, 0.5, CELL_HEIGHT as f64));
-							world.entities[0].velocity = Vec3(0.0, 0.0, 0.0);
-							
-							u = 0.0f32;
-							v = PI / 3.0;
-						}
-						
-						VirtualKeyCode::P => if state.is_pressed() {
-							println!("{:?}", world.get_block(world.entities[0].position.floor_to()));
-						}
-						
-						VirtualKeyCode::Escape => if state.is_pressed() {
-							*control_flow = ControlFlow::Exit;
-						}
-						_ => ()
-					}
+					input.keyboard_mouse.button(Button::Key(keycode), state.is_pressed());
 				}
 				_ => ()
 			}
@@ -269,66 +299,172 @@ fn main() {
 			Event::RedrawRequested(_) => { // MARK: Timestep
 				
 				let now = std::time::Instant::now();
-				let dt = now.duration_since(previous_frame_time).as_secs_f64();
+				let frame_dt = now.duration_since(previous_frame_time).as_secs_f64();
+				tick_accumulator += frame_dt;
 				previous_frame_time = now;
 				
+				// Run as many fixed ticks as real time has passed, but don't try to catch up after long stalls
+				if tick_accumulator > MAX_TICKS_PER_FRAME as f64 * TICK_DT {
+					tick_accumulator = MAX_TICKS_PER_FRAME as f64 * TICK_DT;
+				}
+				
+				#[cfg(feature = "gamepad")]
+				if let Some(gamepads) = &mut gamepads {
+					gamepads.poll(&mut input);
+				}
+				
+				while tick_accumulator >= TICK_DT {
+					tick_accumulator -= TICK_DT;
+					
+					let inputs = input.sample(TICK_DT, game.players.len());
+					game.tick(&inputs);
+					
+					if let Some(recorder) = &mut recorder {
+						if let Err(e) = recorder.record(&inputs, game.world.state_hash()) {
+							eprintln!("Stopped recording input: {e}");
+							*control_flow = ControlFlow::Exit;
+						}
+					}
+				}
+				
+				// MARK: Hot reload
+				
+				// Paths within the assets, which only change when they're read from a directory
+				let changed = match (&mut file_watcher, assets.directory()) {
+					(Some(watcher), Some(root)) => watcher.poll().iter()
+						.filter_map(|path| Some(path.strip_prefix(root).ok()?.to_string_lossy().replace('\\', "/")))
+						.collect(),
+					_ => Vec::new(),
+				};
+				for path in &changed {
+					assets.changed(path);
+				}
+				let reload_requested = input.take_pressed(Action::ReloadShaders);
+				let mut reload_errors = Vec::new();
+				
+				let shaders_changed = reload_requested || changed.iter().any(|path| Path::new(path).starts_with(SHADER_DIRECTORY));
+				if shaders_changed {
+					for program in [&mut world_program, &mut world_debug_program, &mut sprite_program] {
+						if let Err(e) = program.reload(&display, &assets) {
+							reload_errors.push(e.to_string());
+						}
+					}
+				}
 				
+				// The list of passes only changes with the settings, otherwise the passes are just recompiled
+				if reload_requested || changed.iter().any(|path| path == POST_PROCESS_PATH) {
+					let post_settings = PostSettings::load_or_default(&assets, POST_PROCESS_PATH);
+					match PostChain::new(&display, &mut assets, &post_settings) {
+						Ok(chain) => post_chain = chain,
+						Err(e) => reload_errors.push(e.to_string()),
+					}
+				} else if shaders_changed {
+					reload_errors.extend(post_chain.reload_shaders(&display, &assets).iter().map(ShaderError::to_string));
+				}
 				
-				let load_distance = 4.5;
-				let unload_distance = 5.5;
+				// Any change to the tiles repacks the whole atlas, which moves every material's texture coordinates
+				let tile_directory = format!("textures/{TILE_TEXTURE_DIRECTORY}/");
+				if changed.iter().any(|path| path.starts_with(&tile_directory)) {
+					match TileAtlas::load(&display, &assets) {
+						Ok(atlas) => {
+							game.world.set_tile_uvs(atlas.uvs.clone());
+							tile_atlas = atlas;
+						}
+						Err(e) => reload_errors.push(format!("could not rebuild tile atlas: {e}")),
+					}
+				}
 				
-				let cell_position = world.entities[0].position.scale_divide(CELL_SIZE.as_type::<f64>()) - Vec3(0.5, 0.5, 0.5);
+				// Sprites are reloaded whole, whether their images or their animations changed
+				let sprites_changed = |path: &String| path.starts_with("textures/player");
+				if changed.iter().any(sprites_changed) {
+					match assets.sprites(&display, "player") {
+						Ok(sprites) => {
+							set_player_sprites(&mut game, &sprites);
+							sprite_sets.insert("player".to_string(), sprites);
+						}
+						Err(e) => reload_errors.push(format!("could not reload player sprites: {e}")),
+					}
+				}
 				
-				for (pos, cell) in &mut world.cells {
-					if (pos.x() as f64 - cell_position.x()).abs() > 0.5 + unload_distance
-					|| (pos.y() as f64 - cell_position.y()).abs() > 0.5 + unload_distance {
-						cell.unload = true;
+				for name in changed.iter().filter(|path| !path.starts_with(&tile_directory) && !sprites_changed(path)).filter_map(|path| path.strip_prefix("textures/")?.strip_suffix(".png")) {
+					if let Err(e) = post_chain.reload_texture(&display, &mut assets, name) {
+						reload_errors.push(format!("could not reload texture {name}: {e}"));
 					}
 				}
 				
-				world.unload_flagged();
+				if reload_requested || !changed.is_empty() {
+					show_reload_errors(&display, &reload_errors);
+				}
+				
+				// MARK: Actions
+				
+				if input.take_pressed(Action::Quit) {
+					*control_flow = ControlFlow::Exit;
+				}
 				
-				for pos in Vec3Range::<isize, ZYX>::inclusive(
-					(cell_position + Vec3(0.5 - load_distance, 0.5 - load_distance, 0.0)).floor_to().with_z(0),
-					(cell_position + Vec3(0.5 + load_distance, 0.5 + load_distance, 0.0)).floor_to().with_z(0)
-				) {
-					world.get_or_load_cell(pos);
+				if input.take_pressed(Action::ToggleCamera) {
+					camera.set_mode(camera.mode.next());
+					mouse_look = camera.mode.is_perspective();
+					set_mouse_look(&display, mouse_look);
 				}
 				
+				if input.take_pressed(Action::ZoomIn) {
+					camera.zoom(1.1);
+				}
+				if input.take_pressed(Action::ZoomOut) {
+					camera.zoom(1.0 / 1.1);
+				}
+				camera.zoom(1.1f32.powf(input.take_axis(InputAxis::Zoom)));
 				
 				
+				if input.take_pressed(Action::SwitchPlayer) {
+					active_player = (active_player + 1) % game.players.len();
+				}
 				
-				if key_i { v -= 0.5*PI * dt as f32; if v < -0.5*PI { v = -0.5*PI } }
-				if key_k { v += 0.5*PI * dt as f32; if v > 0.5*PI { v = 0.5*PI } }
-				if key_j { u += 0.5*PI * dt as f32; }
-				if key_l { u -= 0.5*PI * dt as f32; }
+				let viewed = game.entity(active_player);
 				
-				let view_matrix = Vec3(
-					Vec3(1.0, 0.0, 0.0),
-					Vec3(0.0, v.sin(), -v.cos()),
-					Vec3(0.0, v.cos(), v.sin()),
-				).matmul(Vec3(
-					Vec3(u.cos(), -u.sin(), 0.0),
-					Vec3(u.sin(), u.cos(), 0.0),
-					Vec3(0.0, 0.0, 1.0),
-				));
+				if input.take_pressed(Action::Interact) {
+					if let Some((transform, body)) = viewed.and_then(|id| Some((game.world.entities.transforms.get(id)?, game.world.entities.bodies.get(id)?))) {
+						edit_position = crate::raycast::cast_ray(&game.world.cells, Ray::new(transform.position + body.size.component(Z) * 0.8, game.look_direction(active_player) * 12.0)).map(|(tile_pos, _incidence)| tile_pos);
+					}
+				}
 				
+				if input.take_pressed(Action::PrintDebug) {
+					if let Some(transform) = viewed.and_then(|id| game.world.entities.transforms.get(id)) {
+						let position = transform.position.floor_to();
+						println!("{:?}", game.world.get_block(position));
+					}
+					println!("tick {} state hash {:016x}", game.world.tick, game.world.state_hash());
+					println!("cells drawn {} culled {}, per level of detail {:?}", cull_stats.drawn, cull_stats.culled, cull_stats.lods);
+				}
 				
-				let mut dp = Vec3(0.0, 0.0, 0.0f64);
-				if key_w { dp += Vec3(-u.sin(), -u.cos(), 0.0).as_type::<f64>(); }
-				if key_s { dp += Vec3( u.sin(),  u.cos(), 0.0).as_type::<f64>(); }
-				if key_a { dp += Vec3(-u.cos(),  u.sin(), 0.0).as_type::<f64>(); }
-				if key_d { dp += Vec3( u.cos(), -u.sin(), 0.0).as_type::<f64>(); }
-				world.entities[0].movement_input = dp.normalize_or_zero();
 				
-				world.entities[0].jump_input = key_space;
+				let LocalPlayer { u, v } = game.players[active_player];
+				let world = &mut game.world;
 				
-				for entity in &mut world.entities {
-					physics_step(entity, &world.cells, 0.01);
-					entity.update_sprite_status();
+				if let Some((transform, body)) = viewed.and_then(|id| Some((world.entities.transforms.get(id)?, world.entities.bodies.get(id)?))) {
+					camera.update(&world.cells, transform.position, body.size.z(), u, v, frame_dt);
 				}
 				
-				// if world.entities[0].position.x() > 0.65 { panic!() }
+				// Only the entity being looked out of is hidden, and entities can appear at any time, so this is kept up every frame
+				let entities = &mut world.entities;
+				// Spawned entities, and any put back after their cell unloaded, come without sprites. They get their spawn
+				// rule's, or the players' if they weren't spawned.
+				for (id, sprite) in entities.sprites.iter_mut().filter(|(_, sprite)| matches!(sprite.sprites, SpriteSet::None)) {
+					let rule = entities.spawned.get(id).and_then(|spawned| world.spawn_table.rule(&spawned.rule));
+					let name = rule.map_or("player", |rule| &rule.sprites);
+					sprite.sprites = sprite_sets.entry(name.to_string()).or_insert_with(|| assets.sprites(&display, name).unwrap_or_else(|e| {
+						eprintln!("Could not load sprites {name}: {e}");
+						SpriteSet::None
+					})).clone();
+				}
+				for (id, sprite) in entities.sprites.iter_mut() {
+					sprite.show = camera.mode != CameraMode::FirstPerson || Some(id) != viewed;
+					if sprite.mesh_buffers.is_none() {
+						sprite.load_mesh_buffers(&display, entities.bodies.get(id).map_or(Vec3(1.0, 1.0, 1.0), |body| body.size));
+					}
+				}
+				let view_projection = camera.view_projection();
 				
 				
 				
@@ -353,26 +489,20 @@ fn main() {
 				};
 				
 				
-				let mut target = MultiOutputFrameBuffer::with_depth_buffer(&display, [
-					("color", &screen_texture),
-					("data", &data_texture),
-				], &depth_texture).unwrap();
+				let mut target = render_targets.scene_framebuffer(&display);
 				target.clear_color_and_depth((0.0, 0.0, 0.0, 0.0), 0.0);
 				
 				// MARK: Draw Tilemap
-				for (location, cell) in &world.cells {
-					if let Some((vertex_buffer, index_buffer)) = &cell.mesh_buffers {
+				let (visible_cells, stats) = visible_cells(&world.cells, &camera);
+				cull_stats = stats;
+				for VisibleCell { location, cell, lod } in visible_cells {
+					if let Some((vertex_buffer, index_buffer)) = cell.mesh_buffers(lod) {
 						target.draw(vertex_buffer, index_buffer, &world_program, &UniformsStorage::
-							 new("tile_size", Vec3(tile_size, tile_size * aspect_ratio, tile_depth))
-							.add("render_position", (*location << CELL_SIZE_BITS).as_type::<f32>() - (world.entities[0].position.as_type::<f32>() + match first_person {
-								false => Vec3::ZERO,
-								true => world.entities[0].size.component(Z).as_type::<f32>() * 0.8,
-							}))
-							.add("view_transform", view_matrix)
-							.add("first_person", match first_person { false => 0, true => 1 })
-							.add("tex", Sampler(&tilemap_texture, SamplerBehavior {
-								wrap_function: (SamplerWrapFunction::Repeat, SamplerWrapFunction::Repeat, SamplerWrapFunction::Repeat),
-								minify_filter: MinifySamplerFilter::Nearest,
+							 new("render_position", camera.relative((location << CELL_SIZE_BITS).as_type()))
+							.add("view_projection", view_projection)
+							.add("tex", Sampler(&tile_atlas.texture, SamplerBehavior {
+								wrap_function: (SamplerWrapFunction::Clamp, SamplerWrapFunction::Clamp, SamplerWrapFunction::Clamp),
+								minify_filter: MinifySamplerFilter::NearestMipmapLinear,
 								magnify_filter: MagnifySamplerFilter::Nearest,
 								depth_texture_comparison: None,
 								max_anisotropy: 1,
@@ -383,20 +513,25 @@ fn main() {
 				
 				
 				// MARK: Draw Entities
-				world.entities.iter().rev().for_each(|entity| {
+				// Standing up facing the camera when looking from the side, otherwise lying flat like on a map
+				let billboard = camera.mode.is_perspective();
+				let orientation = match billboard {
+					true => camera.billboard_orientation(),
+					false => Mat3::IDENTITY,
+				};
+				world.entities.sprites.iter().rev().for_each(|(id, entity)| {
+					let Some(transform) = world.entities.transforms.get(id) else { return };
 					if !entity.show { return }
-					if let Some((vertex_buffer, index_buffer)) = &entity.mesh_buffers {
-						target.draw(vertex_buffer, index_buffer, &world_program, &UniformsStorage::
-							 new("tile_size", Vec3(tile_size, tile_size * aspect_ratio, tile_depth))
-							.add("render_position", entity.position.as_type::<f32>() - (world.entities[0].position.as_type::<f32>() + match first_person {
-								false => Vec3::ZERO,
-								true => world.entities[0].size.component(Z).as_type::<f32>() * 0.8,
-							}))
-							.add("view_transform", view_matrix)
-							.add("first_person", match first_person { false => 0, true => 1 })
-							.add("tex", Sampler(entity.current_sprite(), SamplerBehavior {
-								wrap_function: (SamplerWrapFunction::Repeat, SamplerWrapFunction::Repeat, SamplerWrapFunction::Repeat),
-								minify_filter: MinifySamplerFilter::Linear,
+					let Some((sprite, frame)) = entity.current(transform.direction, camera.yaw) else { return };
+					if let Some((vertex_buffer, index_buffer)) = if billboard { &entity.billboard_buffers } else { &entity.mesh_buffers } {
+						target.draw(vertex_buffer, index_buffer, &sprite_program, &UniformsStorage::
+							 new("render_position", camera.relative(transform.position))
+							.add("view_projection", view_projection)
+							.add("orientation", orientation)
+							.add("frame", [frame.min.x(), frame.min.y(), frame.max.x(), frame.max.y()])
+							.add("tex", Sampler(sprite, SamplerBehavior {
+								wrap_function: (SamplerWrapFunction::Clamp, SamplerWrapFunction::Clamp, SamplerWrapFunction::Clamp),
+								minify_filter: MinifySamplerFilter::Nearest,
 								magnify_filter: MagnifySamplerFilter::Nearest,
 								depth_texture_comparison: None,
 								max_anisotropy: 1,
@@ -408,13 +543,8 @@ fn main() {
 				
 				if let Some(edit_position) = edit_position {
 					target.draw(&oct_vertex_buffer, &oct_index_buffer, &world_debug_program, &UniformsStorage::
-						 new("tile_size", Vec3(tile_size, tile_size * aspect_ratio, tile_depth))
-						.add("render_position", edit_position.as_type::<f32>() - (world.entities[0].position.as_type::<f32>() + match first_person {
-							false => Vec3::ZERO,
-							true => world.entities[0].size.component(Z).as_type::<f32>() * 0.8,
-						}))
-						.add("view_transform", view_matrix)
-						.add("first_person", match first_person { false => 0, true => 1 }),
+						 new("render_position", camera.relative(edit_position.as_type()))
+						.add("view_projection", view_projection),
 					&DrawParameters {
 						backface_culling: BackfaceCullingMode::CullClockwise,
 						blend: Blend {
@@ -452,31 +582,7 @@ fn main() {
 				
 				// MARK: Post-processing
 				let mut display_target = display.draw();
-				display_target.draw(&rect_vertex_buffer, &rect_index_buffer, &post_program, &UniformsStorage::
-					 new("aspect_ratio", aspect_ratio)
-					.add("tile_depth_inverse", 1.0 / tile_depth)
-					.add("screen_texture", Sampler(&screen_texture, SamplerBehavior {
-						wrap_function: (SamplerWrapFunction::Clamp, SamplerWrapFunction::Clamp, SamplerWrapFunction::Clamp),
-						minify_filter: MinifySamplerFilter::Nearest,
-						magnify_filter: MagnifySamplerFilter::Nearest,
-						depth_texture_comparison: None,
-						max_anisotropy: 1
-					}))
-					.add("data_texture", Sampler(&data_texture, SamplerBehavior {
-						wrap_function: (SamplerWrapFunction::Clamp, SamplerWrapFunction::Clamp, SamplerWrapFunction::Clamp),
-						minify_filter: MinifySamplerFilter::Nearest,
-						magnify_filter: MagnifySamplerFilter::Nearest,
-						depth_texture_comparison: None,
-						max_anisotropy: 1
-					}))
-					.add("depth_texture", Sampler(&depth_texture, SamplerBehavior {
-						wrap_function: (SamplerWrapFunction::Clamp, SamplerWrapFunction::Clamp, SamplerWrapFunction::Clamp),
-						minify_filter: MinifySamplerFilter::Nearest,
-						magnify_filter: MagnifySamplerFilter::Nearest,
-						depth_texture_comparison: None,
-						max_anisotropy: 1
-					})),
-				&DrawParameters::default()).unwrap();
+				post_chain.draw(&mut display_target, &render_targets, &camera);
 				
 				display_target.finish().unwrap();
 				
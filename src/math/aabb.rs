@@ -0,0 +1,101 @@
+use std::ops::{Add, Sub};
+
+use num_traits::Float;
+
+use super::{Axis::*, Vec3, Vec3Range, ZYX};
+
+// Axis aligned box from `min` to `max`, both inclusive
+#[derive(Copy, Clone, Debug, Default, PartialEq, Eq, Hash)]
+#[cfg_attr(feature = "serde", derive(serde::Serialize, serde::Deserialize))]
+pub struct Aabb<T> {
+	pub min: Vec3<T>,
+	pub max: Vec3<T>,
+}
+
+fn partial_min<T: PartialOrd>(a: T, b: T) -> T { if b < a { b } else { a } }
+fn partial_max<T: PartialOrd>(a: T, b: T) -> T { if b > a { b } else { a } }
+
+impl<T> Aabb<T> {
+	pub const fn new(min: Vec3<T>, max: Vec3<T>) -> Self { Self { min, max } }
+}
+
+impl<T> Aabb<T> where T: Copy + PartialOrd {
+	// Box with the two points as opposite corners, in any order
+	pub fn from_corners(a: Vec3<T>, b: Vec3<T>) -> Self {
+		Self::new(Vec3::by_axis(|ax| partial_min(a[ax], b[ax])), Vec3::by_axis(|ax| partial_max(a[ax], b[ax])))
+	}
+	
+	pub fn is_empty(self) -> bool {
+		[X, Y, Z].into_iter().any(|a| self.max[a] < self.min[a])
+	}
+	
+	// Picks `max` on the axes where `high` is set and `min` on the others
+	pub fn corner(self, high: Vec3<bool>) -> Vec3<T> {
+		Vec3::by_axis(|a| if high[a] { self.max[a] } else { self.min[a] })
+	}
+	
+	pub fn contains(self, point: Vec3<T>) -> bool {
+		[X, Y, Z].into_iter().all(|a| point[a] >= self.min[a] && point[a] <= self.max[a])
+	}
+	
+	pub fn contains_aabb(self, other: Self) -> bool {
+		self.contains(other.min) && self.contains(other.max)
+	}
+	
+	// Whether the insides overlap, so boxes that only touch don't intersect
+	pub fn intersects(self, other: Self) -> bool {
+		[X, Y, Z].into_iter().all(|a| self.min[a] < other.max[a] && other.min[a] < self.max[a])
+	}
+	
+	// The shared region, which is flat if the boxes only touch
+	pub fn intersection(self, other: Self) -> Option<Self> {
+		let overlap = Self::new(
+			Vec3::by_axis(|a| partial_max(self.min[a], other.min[a])),
+			Vec3::by_axis(|a| partial_min(self.max[a], other.max[a])),
+		);
+		(!overlap.is_empty()).then_some(overlap)
+	}
+	
+	// The point in the box nearest to `point`, which is `point` itself if it's inside
+	pub fn closest_point(self, point: Vec3<T>) -> Vec3<T> {
+		Vec3::by_axis(|a| partial_min(partial_max(point[a], self.min[a]), self.max[a]))
+	}
+	
+	pub fn union(self, other: Self) -> Self {
+		Self::new(
+			Vec3::by_axis(|a| partial_min(self.min[a], other.min[a])),
+			Vec3::by_axis(|a| partial_max(self.max[a], other.max[a])),
+		)
+	}
+}
+
+impl<T> Aabb<T> where T: Copy + Add<Output = T> + Sub<Output = T> {
+	pub fn size(self) -> Vec3<T> { self.max - self.min }
+	
+	pub fn translate(self, offset: Vec3<T>) -> Self { Self::new(self.min + offset, self.max + offset) }
+	
+	// Grows every face outwards by `margin`, or shrinks the box for a negative margin
+	pub fn expand(self, margin: Vec3<T>) -> Self { Self::new(self.min - margin, self.max + margin) }
+	
+	// The volume covered while moving by `motion`
+	pub fn sweep(self, motion: Vec3<T>) -> Self where T: PartialOrd {
+		self.union(self.translate(motion))
+	}
+}
+
+impl<T> Aabb<T> where T: Float {
+	pub fn center(self) -> Vec3<T> { (self.min + self.max) / (T::one() + T::one()) }
+}
+
+impl Aabb<f64> {
+	// The unit box of the tile at `tile_pos`
+	pub fn tile(tile_pos: Vec3<isize>) -> Self {
+		let min = tile_pos.as_type::<f64>();
+		Self::new(min, min + Vec3(1.0, 1.0, 1.0))
+	}
+	
+	// Every tile the box touches, including the ones it's only flush against
+	pub fn tiles(self) -> Vec3Range<isize, ZYX> {
+		Vec3Range::inclusive(self.min.floor_to(), self.max.floor_to())
+	}
+}
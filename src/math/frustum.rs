@@ -0,0 +1,34 @@
+use num_traits::Float;
+
+use super::{Aabb, Mat4, Plane, Vec3};
+
+// The volume a projection maps into clip space, as six planes facing inwards
+#[derive(Copy, Clone, Debug, PartialEq)]
+pub struct Frustum<T> {
+	pub planes: [Plane<T>; 6],
+}
+
+impl<T> Frustum<T> where T: Float {
+	// Each clip space bound, like -w <= x <= w, is a plane in the space the matrix maps from. Works for
+	// orthographic and perspective projections, including the infinite reversed one.
+	pub fn from_matrix(m: Mat4<T>) -> Self {
+		let w = m.row(3);
+		let plane = |row: [T; 4], sign: T| Plane::new(
+			Vec3(w[0] + row[0] * sign, w[1] + row[1] * sign, w[2] + row[2] * sign),
+			-(w[3] + row[3] * sign),
+		);
+		let (x, y, z) = (m.row(0), m.row(1), m.row(2));
+		let one = T::one();
+		Self { planes: [plane(x, one), plane(x, -one), plane(y, one), plane(y, -one), plane(z, one), plane(z, -one)] }
+	}
+	
+	pub fn contains(self, point: Vec3<T>) -> bool {
+		self.planes.iter().all(|plane| plane.evaluate(point) >= T::zero())
+	}
+	
+	// Tests the corner of the box furthest along each plane's normal. This is conservative, so boxes just
+	// outside a corner of the frustum can still pass.
+	pub fn intersects_aabb(self, aabb: Aabb<T>) -> bool {
+		self.planes.iter().all(|plane| plane.evaluate(aabb.corner(plane.normal.map(|n| n >= T::zero()))) >= T::zero())
+	}
+}
@@ -0,0 +1,164 @@
+use std::{fmt::Debug, ops::{Add, AddAssign, Index, IndexMut, Mul, MulAssign, Neg, Sub, SubAssign}};
+
+use glium::uniforms::{AsUniformValue, UniformValue};
+use num_traits::{ConstOne, ConstZero, Float, Zero};
+
+use super::{Quat, Vec3};
+
+// Column major, so `self.0[c][r]` is column c, row r, matching how GLSL lays out matrices
+#[derive(Copy, Clone, Default, PartialEq, Eq, Hash)]
+#[cfg_attr(feature = "serde", derive(serde::Serialize, serde::Deserialize))]
+pub struct Mat3<T>(pub [[T; 3]; 3]);
+
+impl<T: Debug> Debug for Mat3<T> {
+	fn fmt(&self, f: &mut std::fmt::Formatter<'_>) -> std::fmt::Result {
+		let [c0, c1, c2] = &self.0;
+		f.write_fmt(format_args!("Mat3( {:?}, {:?}, {:?} | {:?}, {:?}, {:?} | {:?}, {:?}, {:?} )", c0[0], c1[0], c2[0], c0[1], c1[1], c2[1], c0[2], c1[2], c2[2]))
+	}
+}
+
+impl<T> Mat3<T> where T: Copy + ConstZero + ConstOne {
+	pub const ZERO: Self = Self([[T::ZERO; 3]; 3]);
+	pub const IDENTITY: Self = Self([
+		[T::ONE, T::ZERO, T::ZERO],
+		[T::ZERO, T::ONE, T::ZERO],
+		[T::ZERO, T::ZERO, T::ONE],
+	]);
+}
+
+impl<T> Mat3<T> where T: Copy {
+	pub const fn from_cols(c0: Vec3<T>, c1: Vec3<T>, c2: Vec3<T>) -> Self {
+		Self([[c0.0, c0.1, c0.2], [c1.0, c1.1, c1.2], [c2.0, c2.1, c2.2]])
+	}
+	
+	pub const fn from_rows(r0: Vec3<T>, r1: Vec3<T>, r2: Vec3<T>) -> Self {
+		Self([[r0.0, r1.0, r2.0], [r0.1, r1.1, r2.1], [r0.2, r1.2, r2.2]])
+	}
+	
+	pub const fn col(self, c: usize) -> Vec3<T> { Vec3(self.0[c][0], self.0[c][1], self.0[c][2]) }
+	pub const fn row(self, r: usize) -> Vec3<T> { Vec3(self.0[0][r], self.0[1][r], self.0[2][r]) }
+	
+	pub fn from_diagonal(d: Vec3<T>) -> Self
+	where
+		T: ConstZero
+	{ Self::from_cols(Vec3(d.0, T::ZERO, T::ZERO), Vec3(T::ZERO, d.1, T::ZERO), Vec3(T::ZERO, T::ZERO, d.2)) }
+	
+	pub const fn transpose(self) -> Self {
+		Self::from_rows(self.col(0), self.col(1), self.col(2))
+	}
+	
+	pub fn map<U, F>(self, mut f: F) -> Mat3<U> where F: FnMut(T) -> U {
+		Mat3(self.0.map(|c| c.map(&mut f)))
+	}
+	
+	pub fn determinant(self) -> T
+	where
+		T: Mul<Output = T> + Add<Output = T> + Sub<Output = T>
+	{ self.col(0).dot(self.col(1).cross(self.col(2))) }
+}
+
+impl<T> Mat3<T> where T: Float {
+	// Returns None if the matrix is singular
+	pub fn inverse(self) -> Option<Self> {
+		let det = self.determinant();
+		if det == T::zero() { return None }
+		
+		let (c0, c1, c2) = (self.col(0), self.col(1), self.col(2));
+		Some(Self::from_rows(c1.cross(c2), c2.cross(c0), c0.cross(c1)) * (T::one() / det))
+	}
+	
+	// Rotation of `angle` radians around `axis`, turning X towards Y for a positive angle around Z
+	pub fn from_axis_angle(axis: Vec3<T>, angle: T) -> Self {
+		Self::from(Quat::from_axis_angle(axis, angle))
+	}
+}
+
+impl<T> Zero for Mat3<T> where
+	T: Copy + Zero
+{
+	fn zero() -> Self { Self([[T::zero(); 3]; 3]) }
+	fn is_zero(&self) -> bool { self.0.iter().flatten().all(T::is_zero) }
+}
+
+impl<T: Copy> From<Vec3<Vec3<T>>> for Mat3<T> {
+	fn from(m: Vec3<Vec3<T>>) -> Self { Self::from_cols(m.0, m.1, m.2) }
+}
+
+impl<T: Float> From<Quat<T>> for Mat3<T> {
+	fn from(q: Quat<T>) -> Self {
+		let (x, y, z, w) = (q.0, q.1, q.2, q.3);
+		let two = T::one() + T::one();
+		Self::from_rows(
+			Vec3(T::one() - two * (y*y + z*z), two * (x*y - w*z), two * (x*z + w*y)),
+			Vec3(two * (x*y + w*z), T::one() - two * (x*x + z*z), two * (y*z - w*x)),
+			Vec3(two * (x*z - w*y), two * (y*z + w*x), T::one() - two * (x*x + y*y)),
+		)
+	}
+}
+
+impl AsUniformValue for Mat3<f32> { fn as_uniform_value(&self) -> UniformValue<'_> { UniformValue::Mat3(self.0) } }
+impl AsUniformValue for Mat3<f64> { fn as_uniform_value(&self) -> UniformValue<'_> { UniformValue::DoubleMat3(self.0) } }
+
+
+
+// Operator overloading
+
+impl<T> Index<usize> for Mat3<T> {
+	type Output = [T; 3];
+	fn index(&self, c: usize) -> &Self::Output { &self.0[c] }
+}
+
+impl<T> IndexMut<usize> for Mat3<T> {
+	fn index_mut(&mut self, c: usize) -> &mut Self::Output { &mut self.0[c] }
+}
+
+impl<T> Add for Mat3<T> where
+	T: Copy + Add<Output = T>
+{
+	type Output = Self;
+	fn add(self, rhs: Self) -> Self::Output { Self(std::array::from_fn(|c| std::array::from_fn(|r| self.0[c][r] + rhs.0[c][r]))) }
+}
+impl<T> AddAssign for Mat3<T> where
+	Mat3<T>: Copy + Add<Output = Mat3<T>>
+{ fn add_assign(&mut self, rhs: Self) { *self = *self + rhs; } }
+
+impl<T> Sub for Mat3<T> where
+	T: Copy + Sub<Output = T>
+{
+	type Output = Self;
+	fn sub(self, rhs: Self) -> Self::Output { Self(std::array::from_fn(|c| std::array::from_fn(|r| self.0[c][r] - rhs.0[c][r]))) }
+}
+impl<T> SubAssign for Mat3<T> where
+	Mat3<T>: Copy + Sub<Output = Mat3<T>>
+{ fn sub_assign(&mut self, rhs: Self) { *self = *self - rhs; } }
+
+impl<T> Neg for Mat3<T> where
+	T: Copy + Neg<Output = T>
+{
+	type Output = Self;
+	fn neg(self) -> Self::Output { self.map(|v| -v) }
+}
+
+impl<T> Mul for Mat3<T> where
+	T: Copy + Mul<Output = T> + Add<Output = T>
+{
+	type Output = Self;
+	fn mul(self, rhs: Self) -> Self::Output { Self::from_cols(self * rhs.col(0), self * rhs.col(1), self * rhs.col(2)) }
+}
+impl<T> MulAssign for Mat3<T> where
+	Mat3<T>: Copy + Mul<Output = Mat3<T>>
+{ fn mul_assign(&mut self, rhs: Self) { *self = *self * rhs; } }
+
+impl<T> Mul<Vec3<T>> for Mat3<T> where
+	T: Copy + Mul<Output = T> + Add<Output = T>
+{
+	type Output = Vec3<T>;
+	fn mul(self, v: Vec3<T>) -> Self::Output { self.col(0) * v.0 + self.col(1) * v.1 + self.col(2) * v.2 }
+}
+
+impl<T> Mul<T> for Mat3<T> where
+	T: Copy + Mul<Output = T>
+{
+	type Output = Self;
+	fn mul(self, rhs: T) -> Self::Output { self.map(|v| v * rhs) }
+}
@@ -0,0 +1,279 @@
+use std::{fmt::Debug, ops::{Add, AddAssign, Index, IndexMut, Mul, MulAssign, Neg, Sub, SubAssign}};
+
+use glium::uniforms::{AsUniformValue, UniformValue};
+use num_traits::{ConstOne, ConstZero, Float, Zero};
+
+use super::{Mat3, Quat, Vec3};
+
+// Column major, so `self.0[c][r]` is column c, row r, matching how GLSL lays out matrices.
+// Vectors are columns, so `a * b` applies b first.
+#[derive(Copy, Clone, Default, PartialEq, Eq, Hash)]
+#[cfg_attr(feature = "serde", derive(serde::Serialize, serde::Deserialize))]
+pub struct Mat4<T>(pub [[T; 4]; 4]);
+
+impl<T: Debug> Debug for Mat4<T> {
+	fn fmt(&self, f: &mut std::fmt::Formatter<'_>) -> std::fmt::Result {
+		let [c0, c1, c2, c3] = &self.0;
+		f.write_str("Mat4(")?;
+		for r in 0..4 {
+			f.write_fmt(format_args!(" {:?}, {:?}, {:?}, {:?} {}", c0[r], c1[r], c2[r], c3[r], if r < 3 { "|" } else { ")" }))?;
+		}
+		Ok(())
+	}
+}
+
+impl<T> Mat4<T> where T: Copy + ConstZero + ConstOne {
+	pub const ZERO: Self = Self([[T::ZERO; 4]; 4]);
+	pub const IDENTITY: Self = Self([
+		[T::ONE, T::ZERO, T::ZERO, T::ZERO],
+		[T::ZERO, T::ONE, T::ZERO, T::ZERO],
+		[T::ZERO, T::ZERO, T::ONE, T::ZERO],
+		[T::ZERO, T::ZERO, T::ZERO, T::ONE],
+	]);
+}
+
+impl<T> Mat4<T> where T: Copy {
+	pub const fn col(self, c: usize) -> [T; 4] { self.0[c] }
+	pub const fn row(self, r: usize) -> [T; 4] { [self.0[0][r], self.0[1][r], self.0[2][r], self.0[3][r]] }
+	
+	pub fn transpose(self) -> Self {
+		Self(std::array::from_fn(|c| self.row(c)))
+	}
+	
+	pub fn map<U, F>(self, mut f: F) -> Mat4<U> where F: FnMut(T) -> U {
+		Mat4(self.0.map(|c| c.map(&mut f)))
+	}
+	
+	// The upper left 3x3 part, which holds rotation and scale
+	pub fn truncate(self) -> Mat3<T> {
+		Mat3(std::array::from_fn(|c| std::array::from_fn(|r| self.0[c][r])))
+	}
+	
+	pub fn translation_part(self) -> Vec3<T> {
+		Vec3(self.0[3][0], self.0[3][1], self.0[3][2])
+	}
+	
+	pub fn from_mat3(m: Mat3<T>) -> Self
+	where
+		T: ConstZero + ConstOne
+	{
+		let mut result = Self::IDENTITY;
+		for c in 0..3 {
+			result.0[c][..3].copy_from_slice(&m.0[c]);
+		}
+		result
+	}
+	
+	pub fn from_translation(v: Vec3<T>) -> Self
+	where
+		T: ConstZero + ConstOne
+	{
+		let mut result = Self::IDENTITY;
+		result.0[3] = [v.0, v.1, v.2, T::ONE];
+		result
+	}
+	
+	pub fn from_scale(v: Vec3<T>) -> Self
+	where
+		T: ConstZero + ConstOne
+	{ Self::from_mat3(Mat3::from_diagonal(v)) }
+	
+	// Multiplies a homogeneous column vector
+	pub fn transform(self, v: [T; 4]) -> [T; 4]
+	where
+		T: Mul<Output = T> + Add<Output = T>
+	{ std::array::from_fn(|r| self.0[0][r] * v[0] + self.0[1][r] * v[1] + self.0[2][r] * v[2] + self.0[3][r] * v[3]) }
+	
+	// Transforms a point, including translation and the perspective divide
+	pub fn transform_point(self, p: Vec3<T>) -> Vec3<T>
+	where
+		T: ConstOne + Mul<Output = T> + Add<Output = T> + std::ops::Div<Output = T>
+	{
+		let [x, y, z, w] = self.transform([p.0, p.1, p.2, T::ONE]);
+		Vec3(x / w, y / w, z / w)
+	}
+	
+	// Transforms a direction, ignoring translation
+	pub fn transform_vector(self, v: Vec3<T>) -> Vec3<T>
+	where
+		T: ConstZero + Mul<Output = T> + Add<Output = T>
+	{
+		let [x, y, z, _] = self.transform([v.0, v.1, v.2, T::ZERO]);
+		Vec3(x, y, z)
+	}
+}
+
+impl<T> Mat4<T> where T: Float + ConstZero + ConstOne {
+	pub fn from_quat(q: Quat<T>) -> Self {
+		Self::from_mat3(Mat3::from(q))
+	}
+	
+	pub fn from_axis_angle(axis: Vec3<T>, angle: T) -> Self {
+		Self::from_mat3(Mat3::from_axis_angle(axis, angle))
+	}
+	
+	// View matrix for a camera at `eye` looking at `target`, with -Z forward and +Y up in view space
+	pub fn look_at(eye: Vec3<T>, target: Vec3<T>, up: Vec3<T>) -> Self {
+		Self::look_to(eye, target - eye, up)
+	}
+	
+	pub fn look_to(eye: Vec3<T>, forward: Vec3<T>, up: Vec3<T>) -> Self {
+		let f = forward.normalize();
+		let r = f.cross(up).normalize();
+		let u = r.cross(f);
+		
+		let mut result = Self::from_mat3(Mat3::from_rows(r, u, -f));
+		result.0[3] = [-r.dot(eye), -u.dot(eye), f.dot(eye), T::ONE];
+		result
+	}
+	
+	// OpenGL style projection, mapping `near` to -1 and `far` to 1 in normalized depth
+	pub fn perspective(fov_y: T, aspect_ratio: T, near: T, far: T) -> Self {
+		let two = T::ONE + T::ONE;
+		let f = T::ONE / (fov_y / two).tan();
+		let mut result = Self::ZERO;
+		result.0[0][0] = f / aspect_ratio;
+		result.0[1][1] = f;
+		result.0[2][2] = (far + near) / (near - far);
+		result.0[2][3] = -T::ONE;
+		result.0[3][2] = two * far * near / (near - far);
+		result
+	}
+	
+	// Perspective with no far plane and depth reversed, so `near` maps to 1 and infinity approaches 0
+	pub fn perspective_infinite_reversed(fov_y: T, aspect_ratio: T, near: T) -> Self {
+		let two = T::ONE + T::ONE;
+		let f = T::ONE / (fov_y / two).tan();
+		let mut result = Self::ZERO;
+		result.0[0][0] = f / aspect_ratio;
+		result.0[1][1] = f;
+		result.0[2][3] = -T::ONE;
+		result.0[3][2] = near;
+		result
+	}
+	
+	// OpenGL style orthographic projection of the given view space box
+	pub fn orthographic(left: T, right: T, bottom: T, top: T, near: T, far: T) -> Self {
+		let two = T::ONE + T::ONE;
+		let mut result = Self::IDENTITY;
+		result.0[0][0] = two / (right - left);
+		result.0[1][1] = two / (top - bottom);
+		result.0[2][2] = -two / (far - near);
+		result.0[3] = [-(right + left) / (right - left), -(top + bottom) / (top - bottom), -(far + near) / (far - near), T::ONE];
+		result
+	}
+	
+	pub fn determinant(self) -> T {
+		let (s, c) = self.cofactor_parts();
+		s[0] * c[5] - s[1] * c[4] + s[2] * c[3] + s[3] * c[2] - s[4] * c[1] + s[5] * c[0]
+	}
+	
+	// Returns None if the matrix is singular
+	pub fn inverse(self) -> Option<Self> {
+		let (s, c) = self.cofactor_parts();
+		let det = s[0] * c[5] - s[1] * c[4] + s[2] * c[3] + s[3] * c[2] - s[4] * c[1] + s[5] * c[0];
+		if det == T::ZERO { return None }
+		
+		let m = |r: usize, col: usize| self.0[col][r];
+		let inv = T::ONE / det;
+		
+		// Rows of the adjugate, from the 2x2 minors of the top and bottom halves
+		let rows = [
+			[ m(1,1)*c[5] - m(1,2)*c[4] + m(1,3)*c[3], -m(0,1)*c[5] + m(0,2)*c[4] - m(0,3)*c[3],  m(3,1)*s[5] - m(3,2)*s[4] + m(3,3)*s[3], -m(2,1)*s[5] + m(2,2)*s[4] - m(2,3)*s[3]],
+			[-m(1,0)*c[5] + m(1,2)*c[2] - m(1,3)*c[1],  m(0,0)*c[5] - m(0,2)*c[2] + m(0,3)*c[1], -m(3,0)*s[5] + m(3,2)*s[2] - m(3,3)*s[1],  m(2,0)*s[5] - m(2,2)*s[2] + m(2,3)*s[1]],
+			[ m(1,0)*c[4] - m(1,1)*c[2] + m(1,3)*c[0], -m(0,0)*c[4] + m(0,1)*c[2] - m(0,3)*c[0],  m(3,0)*s[4] - m(3,1)*s[2] + m(3,3)*s[0], -m(2,0)*s[4] + m(2,1)*s[2] - m(2,3)*s[0]],
+			[-m(1,0)*c[3] + m(1,1)*c[1] - m(1,2)*c[0],  m(0,0)*c[3] - m(0,1)*c[1] + m(0,2)*c[0], -m(3,0)*s[3] + m(3,1)*s[1] - m(3,2)*s[0],  m(2,0)*s[3] - m(2,1)*s[1] + m(2,2)*s[0]],
+		];
+		
+		Some(Self(std::array::from_fn(|col| std::array::from_fn(|r| rows[r][col] * inv))))
+	}
+	
+	// 2x2 determinants of rows 0-1 and rows 2-3, used by the Laplace expansion in `inverse` and `determinant`
+	fn cofactor_parts(self) -> ([T; 6], [T; 6]) {
+		let m = |r: usize, c: usize| self.0[c][r];
+		let s = [
+			m(0,0)*m(1,1) - m(1,0)*m(0,1),
+			m(0,0)*m(1,2) - m(1,0)*m(0,2),
+			m(0,0)*m(1,3) - m(1,0)*m(0,3),
+			m(0,1)*m(1,2) - m(1,1)*m(0,2),
+			m(0,1)*m(1,3) - m(1,1)*m(0,3),
+			m(0,2)*m(1,3) - m(1,2)*m(0,3),
+		];
+		let c = [
+			m(2,0)*m(3,1) - m(3,0)*m(2,1),
+			m(2,0)*m(3,2) - m(3,0)*m(2,2),
+			m(2,0)*m(3,3) - m(3,0)*m(2,3),
+			m(2,1)*m(3,2) - m(3,1)*m(2,2),
+			m(2,1)*m(3,3) - m(3,1)*m(2,3),
+			m(2,2)*m(3,3) - m(3,2)*m(2,3),
+		];
+		(s, c)
+	}
+}
+
+impl<T> Zero for Mat4<T> where
+	T: Copy + Zero
+{
+	fn zero() -> Self { Self([[T::zero(); 4]; 4]) }
+	fn is_zero(&self) -> bool { self.0.iter().flatten().all(T::is_zero) }
+}
+
+impl AsUniformValue for Mat4<f32> { fn as_uniform_value(&self) -> UniformValue<'_> { UniformValue::Mat4(self.0) } }
+impl AsUniformValue for Mat4<f64> { fn as_uniform_value(&self) -> UniformValue<'_> { UniformValue::DoubleMat4(self.0) } }
+
+
+
+// Operator overloading
+
+impl<T> Index<usize> for Mat4<T> {
+	type Output = [T; 4];
+	fn index(&self, c: usize) -> &Self::Output { &self.0[c] }
+}
+
+impl<T> IndexMut<usize> for Mat4<T> {
+	fn index_mut(&mut self, c: usize) -> &mut Self::Output { &mut self.0[c] }
+}
+
+impl<T> Add for Mat4<T> where
+	T: Copy + Add<Output = T>
+{
+	type Output = Self;
+	fn add(self, rhs: Self) -> Self::Output { Self(std::array::from_fn(|c| std::array::from_fn(|r| self.0[c][r] + rhs.0[c][r]))) }
+}
+impl<T> AddAssign for Mat4<T> where
+	Mat4<T>: Copy + Add<Output = Mat4<T>>
+{ fn add_assign(&mut self, rhs: Self) { *self = *self + rhs; } }
+
+impl<T> Sub for Mat4<T> where
+	T: Copy + Sub<Output = T>
+{
+	type Output = Self;
+	fn sub(self, rhs: Self) -> Self::Output { Self(std::array::from_fn(|c| std::array::from_fn(|r| self.0[c][r] - rhs.0[c][r]))) }
+}
+impl<T> SubAssign for Mat4<T> where
+	Mat4<T>: Copy + Sub<Output = Mat4<T>>
+{ fn sub_assign(&mut self, rhs: Self) { *self = *self - rhs; } }
+
+impl<T> Neg for Mat4<T> where
+	T: Copy + Neg<Output = T>
+{
+	type Output = Self;
+	fn neg(self) -> Self::Output { self.map(|v| -v) }
+}
+
+impl<T> Mul for Mat4<T> where
+	T: Copy + Mul<Output = T> + Add<Output = T>
+{
+	type Output = Self;
+	fn mul(self, rhs: Self) -> Self::Output { Self(rhs.0.map(|c| self.transform(c))) }
+}
+impl<T> MulAssign for Mat4<T> where
+	Mat4<T>: Copy + Mul<Output = Mat4<T>>
+{ fn mul_assign(&mut self, rhs: Self) { *self = *self * rhs; } }
+
+impl<T> Mul<T> for Mat4<T> where
+	T: Copy + Mul<Output = T>
+{
+	type Output = Self;
+	fn mul(self, rhs: T) -> Self::Output { self.map(|v| v * rhs) }
+}
@@ -3,8 +3,24 @@ use num_traits::{ConstOne, PrimInt};
 
 mod vec2;
 mod vec3;
+mod mat3;
+mod mat4;
+mod quat;
+mod aabb;
+mod ray;
+mod plane;
+mod frustum;
 pub use vec2::*;
 pub use vec3::*;
+pub use mat3::*;
+pub use mat4::*;
+pub use quat::*;
+pub use aabb::*;
+pub use ray::*;
+pub use plane::*;
+pub use frustum::*;
+
+#[cfg(test)] mod tests;
 
 pub trait Modulo<T> { fn modulo(self, rhs: T) -> Self; }
 impl<T> Modulo<T> for f32 where
@@ -0,0 +1,41 @@
+use std::ops::{Add, Mul, Sub};
+
+use num_traits::Float;
+
+use super::Vec3;
+
+// The points p where `p.dot(normal) == offset`, with the side the normal points to in front. The normal
+// doesn't need to be unit length, which lets tile slopes stay exact with integer normals.
+#[derive(Copy, Clone, Debug, Default, PartialEq, Eq, Hash)]
+#[cfg_attr(feature = "serde", derive(serde::Serialize, serde::Deserialize))]
+pub struct Plane<T> {
+	pub normal: Vec3<T>,
+	pub offset: T,
+}
+
+impl<T> Plane<T> {
+	pub const fn new(normal: Vec3<T>, offset: T) -> Self { Self { normal, offset } }
+}
+
+impl<T> Plane<T> where T: Copy + Add<Output = T> + Sub<Output = T> + Mul<Output = T> {
+	pub fn from_point(normal: Vec3<T>, point: Vec3<T>) -> Self { Self::new(normal, point.dot(normal)) }
+	
+	// Positive in front of the plane, scaled by the length of the normal
+	pub fn evaluate(self, point: Vec3<T>) -> T { point.dot(self.normal) - self.offset }
+}
+
+impl<T> Plane<T> where T: Float {
+	pub fn unit_normal(self) -> Vec3<T> { self.normal.normalize() }
+	
+	pub fn normalize(self) -> Self {
+		let length = self.normal.length();
+		Self::new(self.normal / length, self.offset / length)
+	}
+	
+	pub fn signed_distance(self, point: Vec3<T>) -> T { self.evaluate(point) / self.normal.length() }
+	
+	// Closest point on the plane
+	pub fn project(self, point: Vec3<T>) -> Vec3<T> {
+		point - self.normal * (self.evaluate(point) / self.normal.length_squared())
+	}
+}
@@ -0,0 +1,167 @@
+use std::{fmt::Debug, ops::{Add, Mul, MulAssign, Neg, Sub}};
+
+use num_traits::{ConstOne, ConstZero, Float};
+
+use super::Vec3;
+
+// Rotation quaternion (x, y, z, w), with w the scalar part
+#[derive(Copy, Clone, Default, PartialEq, Eq, Hash)]
+#[cfg_attr(feature = "serde", derive(serde::Serialize, serde::Deserialize))]
+pub struct Quat<T>(pub T, pub T, pub T, pub T);
+
+impl<T: Debug> Debug for Quat<T> {
+	fn fmt(&self, f: &mut std::fmt::Formatter<'_>) -> std::fmt::Result {
+		f.write_fmt(format_args!("Quat( {:?}, {:?}, {:?}, {:?} )", &self.0, &self.1, &self.2, &self.3))
+	}
+}
+
+impl<T> Quat<T> where T: ConstZero + ConstOne {
+	pub const IDENTITY: Self = Self(T::ZERO, T::ZERO, T::ZERO, T::ONE);
+}
+
+impl<T> Quat<T> {
+	pub const fn x(self) -> T where T: Copy { self.0 }
+	pub const fn y(self) -> T where T: Copy { self.1 }
+	pub const fn z(self) -> T where T: Copy { self.2 }
+	pub const fn w(self) -> T where T: Copy { self.3 }
+	pub const fn vector(self) -> Vec3<T> where T: Copy { Vec3(self.0, self.1, self.2) }
+	
+	pub const fn from_parts(vector: Vec3<T>, w: T) -> Self where T: Copy { Self(vector.0, vector.1, vector.2, w) }
+	
+	pub fn dot(self, q: Self) -> T
+	where
+		T: Mul<Output = T> + Add<Output = T>
+	{ self.0 * q.0 + self.1 * q.1 + self.2 * q.2 + self.3 * q.3 }
+	
+	pub fn conjugate(self) -> Self
+	where
+		T: Neg<Output = T>
+	{ Self(-self.0, -self.1, -self.2, self.3) }
+}
+
+impl<T> Quat<T> where T: Float {
+	// Rotation of `angle` radians around `axis`, turning X towards Y for a positive angle around Z
+	pub fn from_axis_angle(axis: Vec3<T>, angle: T) -> Self {
+		let half = angle / (T::one() + T::one());
+		Self::from_parts(axis.normalize() * half.sin(), half.cos())
+	}
+	
+	// Inverse of `from_axis_angle`, with the angle in 0..=PI. The axis is arbitrary for the identity.
+	pub fn to_axis_angle(self) -> (Vec3<T>, T) {
+		let q = if self.3 < T::zero() { -self } else { self };
+		let sin_half = q.vector().length();
+		if sin_half <= T::epsilon() {
+			return (Vec3(T::zero(), T::zero(), T::one()), T::zero())
+		}
+		(q.vector() / sin_half, (T::one() + T::one()) * sin_half.atan2(q.3))
+	}
+	
+	// Shortest rotation taking direction `from` to direction `to`
+	pub fn from_rotation_arc(from: Vec3<T>, to: Vec3<T>) -> Self {
+		let (from, to) = (from.normalize(), to.normalize());
+		let d = from.dot(to);
+		if d < -T::one() + T::epsilon() {
+			// Opposite directions, so any perpendicular axis works
+			let axis = match from.cross(Vec3(T::one(), T::zero(), T::zero())) {
+				a if a.length() > T::epsilon() => a,
+				_ => from.cross(Vec3(T::zero(), T::one(), T::zero())),
+			};
+			return Self::from_axis_angle(axis, T::from(std::f64::consts::PI).unwrap())
+		}
+		Self::from_parts(from.cross(to), T::one() + d).normalize()
+	}
+	
+	pub fn length(self) -> T { self.dot(self).sqrt() }
+	
+	pub fn normalize(self) -> Self {
+		let l = self.length();
+		Self(self.0 / l, self.1 / l, self.2 / l, self.3 / l)
+	}
+	
+	pub fn inverse(self) -> Self {
+		let l2 = self.dot(self);
+		let c = self.conjugate();
+		Self(c.0 / l2, c.1 / l2, c.2 / l2, c.3 / l2)
+	}
+	
+	// Spherical interpolation along the shorter arc, constant angular speed in t
+	pub fn slerp(self, to: Self, t: T) -> Self {
+		let mut d = self.dot(to);
+		let to = if d < T::zero() { d = -d; -to } else { to };
+		
+		// Nearly parallel, where the sine below loses precision, so interpolate linearly instead
+		if d > T::one() - T::from(1e-6).unwrap() {
+			return (self * (T::one() - t) + to * t).normalize()
+		}
+		
+		let theta = d.min(T::one()).acos();
+		let sin_theta = theta.sin();
+		self * (((T::one() - t) * theta).sin() / sin_theta) + to * ((t * theta).sin() / sin_theta)
+	}
+	
+	pub fn rotate(self, v: Vec3<T>) -> Vec3<T> {
+		// v + 2w(q × v) + 2q × (q × v), for a unit quaternion
+		let q = self.vector();
+		let two = T::one() + T::one();
+		let t = q.cross(v) * two;
+		v + t * self.3 + q.cross(t)
+	}
+}
+
+
+
+// Operator overloading
+
+impl<T> Neg for Quat<T> where
+	T: Neg<Output = T>
+{
+	type Output = Self;
+	fn neg(self) -> Self::Output { Self(-self.0, -self.1, -self.2, -self.3) }
+}
+
+impl<T> Add for Quat<T> where
+	T: Add<Output = T>
+{
+	type Output = Self;
+	fn add(self, rhs: Self) -> Self::Output { Self(self.0 + rhs.0, self.1 + rhs.1, self.2 + rhs.2, self.3 + rhs.3) }
+}
+
+impl<T> Sub for Quat<T> where
+	T: Sub<Output = T>
+{
+	type Output = Self;
+	fn sub(self, rhs: Self) -> Self::Output { Self(self.0 - rhs.0, self.1 - rhs.1, self.2 - rhs.2, self.3 - rhs.3) }
+}
+
+// Composition, so `a * b` rotates by b and then by a
+impl<T> Mul for Quat<T> where
+	T: Copy + Mul<Output = T> + Add<Output = T> + Sub<Output = T>
+{
+	type Output = Self;
+	fn mul(self, rhs: Self) -> Self::Output {
+		let (a, b) = (self, rhs);
+		Self(
+			a.3 * b.0 + a.0 * b.3 + a.1 * b.2 - a.2 * b.1,
+			a.3 * b.1 - a.0 * b.2 + a.1 * b.3 + a.2 * b.0,
+			a.3 * b.2 + a.0 * b.1 - a.1 * b.0 + a.2 * b.3,
+			a.3 * b.3 - a.0 * b.0 - a.1 * b.1 - a.2 * b.2,
+		)
+	}
+}
+impl<T> MulAssign for Quat<T> where
+	Quat<T>: Copy + Mul<Output = Quat<T>>
+{ fn mul_assign(&mut self, rhs: Self) { *self = *self * rhs; } }
+
+impl<T> Mul<Vec3<T>> for Quat<T> where
+	T: Float
+{
+	type Output = Vec3<T>;
+	fn mul(self, v: Vec3<T>) -> Self::Output { self.rotate(v) }
+}
+
+impl<T> Mul<T> for Quat<T> where
+	T: Copy + Mul<Output = T>
+{
+	type Output = Self;
+	fn mul(self, rhs: T) -> Self::Output { Self(self.0 * rhs, self.1 * rhs, self.2 * rhs, self.3 * rhs) }
+}
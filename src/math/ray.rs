@@ -0,0 +1,62 @@
+use std::ops::{Add, Mul};
+
+use num_traits::Float;
+
+use super::{Aabb, Axis::*, Plane, Vec3};
+
+// Segment from `origin` to `origin + direction`. The direction isn't normalized, so t in 0..=1 covers the
+// segment and other values extend it.
+#[derive(Copy, Clone, Debug, Default, PartialEq, Eq, Hash)]
+#[cfg_attr(feature = "serde", derive(serde::Serialize, serde::Deserialize))]
+pub struct Ray<T> {
+	pub origin: Vec3<T>,
+	pub direction: Vec3<T>,
+}
+
+impl<T> Ray<T> {
+	pub const fn new(origin: Vec3<T>, direction: Vec3<T>) -> Self { Self { origin, direction } }
+}
+
+impl<T> Ray<T> where T: Copy + Add<Output = T> + Mul<Output = T> {
+	pub fn at(self, t: T) -> Vec3<T> { self.origin + self.direction * t }
+	pub fn end(self) -> Vec3<T> { self.origin + self.direction }
+}
+
+impl<T> Ray<T> where T: Float {
+	pub fn between(from: Vec3<T>, to: Vec3<T>) -> Self { Self::new(from, to - from) }
+	
+	// Slab test, returning the t at which the line through the ray enters and leaves the box. Entry is
+	// negative if the origin is inside, so callers check the range against the part they care about.
+	pub fn intersect_aabb(self, aabb: Aabb<T>) -> Option<(T, T)> {
+		let mut t_enter = T::neg_infinity();
+		let mut t_exit = T::infinity();
+		
+		for a in [X, Y, Z] {
+			if self.direction[a] == T::zero() {
+				if self.origin[a] < aabb.min[a] || self.origin[a] > aabb.max[a] { return None }
+				continue
+			}
+			
+			let t_min = (aabb.min[a] - self.origin[a]) / self.direction[a];
+			let t_max = (aabb.max[a] - self.origin[a]) / self.direction[a];
+			t_enter = t_enter.max(t_min.min(t_max));
+			t_exit = t_exit.min(t_min.max(t_max));
+			
+			if t_enter > t_exit { return None }
+		}
+		
+		Some((t_enter, t_exit))
+	}
+	
+	// Where the ray crosses from in front of the plane to behind it. Rays that start behind the plane,
+	// run parallel to it or move away from it don't hit, and t isn't limited above.
+	pub fn intersect_plane(self, plane: Plane<T>) -> Option<T> {
+		let speed = self.direction.dot(plane.normal);
+		if speed >= T::zero() { return None }
+		
+		let current = self.origin.dot(plane.normal);
+		if current < plane.offset { return None }
+		
+		Some((plane.offset - current) / speed)
+	}
+}
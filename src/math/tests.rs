@@ -0,0 +1,272 @@
+use std::f64::consts::PI;
+
+use crate::*;
+
+
+const EPSILON: f64 = 1e-9;
+
+fn assert_vec_near(a: Vec3<f64>, b: Vec3<f64>) {
+	assert!((a - b).length() < EPSILON, "{a:?} != {b:?}");
+}
+
+fn assert_mat4_near(a: Mat4<f64>, b: Mat4<f64>) {
+	let error = (a - b).0.iter().flatten().fold(0.0, |e: f64, v| e.max(v.abs()));
+	assert!(error < EPSILON, "{a:?} != {b:?}");
+}
+
+fn assert_quat_near(a: Quat<f64>, b: Quat<f64>) {
+	// q and -q are the same rotation
+	assert!(a.dot(b).abs() > 1.0 - EPSILON, "{a:?} != {b:?}");
+}
+
+fn sample_matrix() -> Mat4<f64> {
+	Mat4::from_translation(Vec3(3.0, -2.0, 0.5))
+		* Mat4::from_axis_angle(Vec3(1.0, 2.0, -0.5), 0.7)
+		* Mat4::from_scale(Vec3(2.0, 0.5, 1.5))
+}
+
+
+
+// MARK: Mat3
+
+#[test]
+fn mat3_multiplies_like_matmul() {
+	let a = Vec3(Vec3(1.0, 2.0, 3.0), Vec3(0.0, 1.0, 4.0), Vec3(5.0, 6.0, 0.0));
+	let b = Vec3(Vec3(-2.0, 1.0, 0.5), Vec3(3.0, 0.0, 1.0), Vec3(1.0, 1.0, 1.0));
+	assert_eq!(Mat3::from(a) * Mat3::from(b), Mat3::from(a.matmul(b)));
+	assert_eq!(Mat3::from(a).col(2), Vec3(5.0, 6.0, 0.0));
+	assert_eq!(Mat3::from(a).transpose().row(2), Vec3(5.0, 6.0, 0.0));
+}
+
+#[test]
+fn mat3_inverse() {
+	let m = Mat3::from_rows(Vec3(1.0, 2.0, 3.0), Vec3(0.0, 1.0, 4.0), Vec3(5.0, 6.0, 0.0));
+	assert_eq!(m.determinant(), 1.0);
+	assert_eq!(m.inverse().unwrap() * m, Mat3::IDENTITY);
+	
+	let singular = Mat3::from_rows(Vec3(1.0, 2.0, 3.0), Vec3(2.0, 4.0, 6.0), Vec3(0.0, 1.0, 1.0));
+	assert_eq!(singular.inverse(), None);
+}
+
+#[test]
+fn axis_angle_rotation_direction() {
+	let m = Mat3::from_axis_angle(Vec3(0.0, 0.0, 2.0), 0.5 * PI);
+	assert_vec_near(m * Vec3(1.0, 0.0, 0.0), Vec3(0.0, 1.0, 0.0));
+	assert_vec_near(m * Vec3(0.0, 0.0, 1.0), Vec3(0.0, 0.0, 1.0));
+	
+	let m = Mat3::from_axis_angle(Vec3(1.0, 0.0, 0.0), 0.5 * PI);
+	assert_vec_near(m * Vec3(0.0, 1.0, 0.0), Vec3(0.0, 0.0, 1.0));
+	assert!((m.determinant() - 1.0).abs() < EPSILON);
+}
+
+
+
+// MARK: Mat4
+
+#[test]
+fn mat4_inverse() {
+	let m = sample_matrix();
+	let inverse = m.inverse().unwrap();
+	assert_mat4_near(m * inverse, Mat4::IDENTITY);
+	assert_mat4_near(inverse * m, Mat4::IDENTITY);
+	assert!((m.determinant() - 2.0 * 0.5 * 1.5).abs() < EPSILON);
+	
+	assert_eq!(Mat4::from_scale(Vec3(1.0, 0.0, 1.0)).inverse(), None);
+	
+	// A projection has no affine shortcut, so this exercises the full expansion
+	let p = Mat4::perspective(1.2, 1.5, 0.1, 100.0);
+	assert_mat4_near(p * p.inverse().unwrap(), Mat4::IDENTITY);
+}
+
+#[test]
+fn mat4_transforms() {
+	let m = Mat4::from_translation(Vec3(1.0, 2.0, 3.0)) * Mat4::from_scale(Vec3(2.0, 2.0, 2.0));
+	assert_eq!(m.transform_point(Vec3(1.0, 1.0, 1.0)), Vec3(3.0, 4.0, 5.0));
+	assert_eq!(m.transform_vector(Vec3(1.0, 1.0, 1.0)), Vec3(2.0, 2.0, 2.0));
+	assert_eq!(m.translation_part(), Vec3(1.0, 2.0, 3.0));
+	assert_eq!(m.truncate(), Mat3::from_diagonal(Vec3(2.0, 2.0, 2.0)));
+	assert_eq!(m.transpose().transpose(), m);
+	assert_eq!(m * 2.0, m + m);
+}
+
+#[test]
+fn look_at_points_down_negative_z() {
+	let eye = Vec3(4.0, -3.0, 2.0);
+	let target = Vec3(1.0, 5.0, -1.0);
+	let view = Mat4::look_at(eye, target, Vec3(0.0, 0.0, 1.0));
+	
+	assert_vec_near(view.transform_point(eye), Vec3(0.0, 0.0, 0.0));
+	let t = view.transform_point(target);
+	assert!(t.x().abs() < EPSILON && t.y().abs() < EPSILON && t.z() < 0.0);
+	assert!((t.z() + (target - eye).length()).abs() < EPSILON);
+	
+	// World up stays up on screen
+	assert!(view.transform_vector(Vec3(0.0, 0.0, 1.0)).y() > 0.0);
+}
+
+#[test]
+fn perspective_maps_near_and_far() {
+	let p = Mat4::perspective(0.5 * PI, 2.0, 0.5, 50.0);
+	assert!((p.transform_point(Vec3(0.0, 0.0, -0.5)).z() + 1.0).abs() < EPSILON);
+	assert!((p.transform_point(Vec3(0.0, 0.0, -50.0)).z() - 1.0).abs() < EPSILON);
+	
+	// 90 degree vertical fov puts y = -z on the top edge, and x is squeezed by the aspect ratio
+	let edge = p.transform_point(Vec3(2.0, 1.0, -1.0));
+	assert!((edge.x() - 1.0).abs() < EPSILON && (edge.y() - 1.0).abs() < EPSILON);
+	
+	let r = Mat4::perspective_infinite_reversed(0.5 * PI, 2.0, 0.5);
+	assert!((r.transform_point(Vec3(0.0, 0.0, -0.5)).z() - 1.0).abs() < EPSILON);
+	assert!(r.transform_point(Vec3(0.0, 0.0, -1e9)).z() < 1e-6);
+}
+
+#[test]
+fn orthographic_maps_box_to_cube() {
+	let o = Mat4::orthographic(-4.0, 2.0, -1.0, 3.0, 0.5, 10.0);
+	assert_vec_near(o.transform_point(Vec3(-4.0, -1.0, -0.5)), Vec3(-1.0, -1.0, -1.0));
+	assert_vec_near(o.transform_point(Vec3(2.0, 3.0, -10.0)), Vec3(1.0, 1.0, 1.0));
+}
+
+
+
+// MARK: Quat
+
+#[test]
+fn quat_matches_matrix_rotation() {
+	let axis = Vec3(1.0, -2.0, 0.5);
+	let q = Quat::from_axis_angle(axis, 1.1);
+	let m = Mat3::from_axis_angle(axis, 1.1);
+	let v = Vec3(0.3, 2.0, -1.0);
+	
+	assert_vec_near(q * v, m * v);
+	assert_vec_near((q * q.inverse()) * v, v);
+	assert_vec_near((q * q) * v, q * (q * v));
+	
+	let (axis_out, angle) = q.to_axis_angle();
+	assert_vec_near(axis_out, axis.normalize());
+	assert!((angle - 1.1).abs() < EPSILON);
+}
+
+#[test]
+fn quat_rotation_arc() {
+	let from = Vec3(1.0, 0.0, 0.0);
+	for to in [Vec3(0.0, 3.0, 0.0), Vec3(-1.0, 0.0, 0.0), Vec3(1.0, 1.0, 1.0)] {
+		assert_vec_near(Quat::from_rotation_arc(from, to) * from, to.normalize());
+	}
+}
+
+#[test]
+fn slerp_has_constant_angular_speed() {
+	let axis = Vec3(0.0, 0.0, 1.0);
+	let a = Quat::from_axis_angle(axis, 0.2);
+	let b = Quat::from_axis_angle(axis, 1.8);
+	
+	assert_quat_near(a.slerp(b, 0.0), a);
+	assert_quat_near(a.slerp(b, 1.0), b);
+	assert_quat_near(a.slerp(b, 0.25), Quat::from_axis_angle(axis, 0.6));
+	
+	// Takes the short way round even if the signs disagree
+	assert_quat_near(a.slerp(-b, 0.5), Quat::from_axis_angle(axis, 1.0));
+	
+	// Nearly identical rotations fall back to a normalized lerp
+	let c = Quat::from_axis_angle(axis, 0.2 + 1e-8);
+	assert!((a.slerp(c, 0.5).length() - 1.0).abs() < EPSILON);
+	
+	assert_eq!(Quat::<f64>::IDENTITY * Vec3(1.0, 2.0, 3.0), Vec3(1.0, 2.0, 3.0));
+}
+
+
+
+// MARK: Geometry
+
+#[test]
+fn aabb_overlap() {
+	let a = Aabb::from_corners(Vec3(2.0, 0.0, 1.0), Vec3(0.0, 2.0, 0.0));
+	assert_eq!(a, Aabb::new(Vec3(0.0, 0.0, 0.0), Vec3(2.0, 2.0, 1.0)));
+	
+	let b = Aabb::new(Vec3(1.0, 1.0, 0.5), Vec3(3.0, 3.0, 3.0));
+	assert!(a.intersects(b));
+	assert_eq!(a.intersection(b), Some(Aabb::new(Vec3(1.0, 1.0, 0.5), Vec3(2.0, 2.0, 1.0))));
+	assert_eq!(a.union(b), Aabb::new(Vec3(0.0, 0.0, 0.0), Vec3(3.0, 3.0, 3.0)));
+	
+	// Touching faces share a flat region but don't count as intersecting
+	let touching = a.translate(Vec3(2.0, 0.0, 0.0));
+	assert!(!a.intersects(touching));
+	assert_eq!(a.intersection(touching).map(|i| i.size()), Some(Vec3(0.0, 2.0, 1.0)));
+	assert_eq!(a.intersection(a.translate(Vec3(0.0, 0.0, 5.0))), None);
+	
+	assert!(a.contains(Vec3(2.0, 1.0, 0.0)) && !a.contains(Vec3(2.1, 1.0, 0.0)));
+	assert!(a.union(b).contains_aabb(b) && !a.contains_aabb(b));
+	assert_eq!(a.center(), Vec3(1.0, 1.0, 0.5));
+}
+
+#[test]
+fn aabb_expand_and_sweep() {
+	let a = Aabb::new(Vec3(0.0, 0.0, 0.0), Vec3(1.0, 1.0, 1.0));
+	assert_eq!(a.expand(Vec3::all(0.5)), Aabb::new(Vec3::all(-0.5), Vec3::all(1.5)));
+	assert_eq!(a.sweep(Vec3(2.0, -1.0, 0.0)), Aabb::new(Vec3(0.0, -1.0, 0.0), Vec3(3.0, 1.0, 1.0)));
+	assert_eq!(a.corner(Vec3(true, false, true)), Vec3(1.0, 0.0, 1.0));
+	
+	// Flush against the tile boundary still touches the next tile
+	let tiles = Aabb::new(Vec3(0.5, 0.5, 0.5), Vec3(2.0, 1.5, 0.9)).tiles().collect::<Vec<_>>();
+	assert_eq!(tiles.len(), 3 * 2);
+	assert!(tiles.contains(&Vec3(2, 1, 0)));
+	assert_eq!(Aabb::tile(Vec3(-1, 2, 0)), Aabb::new(Vec3(-1.0, 2.0, 0.0), Vec3(0.0, 3.0, 1.0)));
+}
+
+#[test]
+fn ray_slab_test() {
+	let b = Aabb::new(Vec3(2.0, -1.0, -1.0), Vec3(4.0, 1.0, 1.0));
+	
+	assert_eq!(Ray::new(Vec3(0.0, 0.0, 0.0), Vec3(1.0, 0.0, 0.0)).intersect_aabb(b), Some((2.0, 4.0)));
+	assert_eq!(Ray::new(Vec3(3.0, 0.0, 0.0), Vec3(-2.0, 0.0, 0.0)).intersect_aabb(b), Some((-0.5, 0.5)));
+	assert_eq!(Ray::new(Vec3(0.0, 2.0, 0.0), Vec3(1.0, 0.0, 0.0)).intersect_aabb(b), None);
+	assert_eq!(Ray::new(Vec3(0.0, 0.0, 0.0), Vec3(1.0, 1.0, 0.0)).intersect_aabb(b), None);
+	
+	let (enter, exit): (f64, f64) = Ray::between(Vec3(0.0, -2.0, 0.5), Vec3(6.0, 4.0, 0.5)).intersect_aabb(b).unwrap();
+	assert!((enter - 1.0 / 3.0).abs() < EPSILON && (exit - 1.0 / 2.0).abs() < EPSILON);
+}
+
+#[test]
+fn plane_side_and_crossing() {
+	// Integer slope normals aren't unit length
+	let plane = Plane::new(Vec3(-1.0, 0.0, 1.0), 1.0);
+	assert_eq!(plane.evaluate(Vec3(0.0, 5.0, 3.0)), 2.0);
+	assert!((plane.signed_distance(Vec3(0.0, 5.0, 3.0)) - 2.0f64.sqrt()).abs() < EPSILON);
+	assert!(plane.evaluate(plane.project(Vec3(3.0, 1.0, -2.0))).abs() < EPSILON);
+	assert_eq!(Plane::from_point(plane.normal, Vec3(1.0, 0.0, 2.0)), plane);
+	assert!((plane.normalize().signed_distance(Vec3(4.0, 0.0, 0.0)) - plane.signed_distance(Vec3(4.0, 0.0, 0.0))).abs() < EPSILON);
+	
+	let down = Ray::new(Vec3(0.0, 0.0, 3.0), Vec3(0.0, 0.0, -4.0));
+	assert_eq!(down.intersect_plane(plane), Some(0.5));
+	assert_eq!(down.at(0.5), Vec3(0.0, 0.0, 1.0));
+	
+	// From behind, moving away or parallel never hits
+	assert_eq!(Ray::new(Vec3(0.0, 0.0, 0.0), Vec3(0.0, 0.0, 1.0)).intersect_plane(plane), None);
+	assert_eq!(Ray::new(Vec3(0.0, 0.0, 3.0), Vec3(0.0, 0.0, 1.0)).intersect_plane(plane), None);
+	assert_eq!(Ray::new(Vec3(0.0, 0.0, 3.0), Vec3(1.0, 0.0, 1.0)).intersect_plane(plane), None);
+}
+
+#[test]
+fn frustum_from_projection() {
+	let view_projection = Mat4::perspective(0.5 * PI, 1.0, 1.0, 100.0) * Mat4::look_at(Vec3(0.0, 0.0, 0.0), Vec3(0.0, 1.0, 0.0), Vec3(0.0, 0.0, 1.0));
+	let frustum = Frustum::from_matrix(view_projection);
+	
+	assert!(frustum.contains(Vec3(0.0, 10.0, 0.0)));
+	assert!(frustum.contains(Vec3(9.0, 10.0, -9.0)));
+	assert!(!frustum.contains(Vec3(11.0, 10.0, 0.0)));
+	assert!(!frustum.contains(Vec3(0.0, -10.0, 0.0)));
+	assert!(!frustum.contains(Vec3(0.0, 0.5, 0.0)), "In front of the near plane");
+	assert!(!frustum.contains(Vec3(0.0, 101.0, 0.0)), "Past the far plane");
+	
+	// Boxes straddling a plane still count
+	assert!(frustum.intersects_aabb(Aabb::new(Vec3(9.0, 10.0, -1.0), Vec3(15.0, 12.0, 1.0))));
+	assert!(frustum.intersects_aabb(Aabb::new(Vec3(-50.0, -50.0, -50.0), Vec3(50.0, 50.0, 50.0))));
+	assert!(!frustum.intersects_aabb(Aabb::new(Vec3(-5.0, -20.0, -5.0), Vec3(5.0, -2.0, 5.0))));
+	assert!(!frustum.intersects_aabb(Aabb::new(Vec3(12.0, 1.0, -1.0), Vec3(20.0, 10.0, 1.0))));
+	
+	// With an infinite far plane only the near plane limits depth
+	let infinite = Frustum::from_matrix(Mat4::perspective_infinite_reversed(0.5 * PI, 1.0, 1.0));
+	assert!(infinite.contains(Vec3(0.0, 0.0, -1e6)) && !infinite.contains(Vec3(0.0, 0.0, -0.5)));
+	
+	assert_eq!(Aabb::new(Vec3(0.0, 0.0, 0.0), Vec3(1.0, 2.0, 3.0)).closest_point(Vec3(-1.0, 1.0, 5.0)), Vec3(0.0, 1.0, 3.0));
+}
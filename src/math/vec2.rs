@@ -6,6 +6,7 @@ use num_traits::{AsPrimitive, ConstOne, ConstZero, Float, Signed, Zero};
 use super::{Axis::{self, *}, Direction::{self, *}, Modulo, Vec3};
 
 #[derive(Copy, Clone, Default, PartialEq, Eq, Hash)]
+#[cfg_attr(feature = "serde", derive(serde::Serialize, serde::Deserialize))]
 pub struct Vec2<T>(pub T, pub T);
 
 impl<T: Debug> Debug for Vec2<T> {
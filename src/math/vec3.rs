@@ -6,6 +6,7 @@ use num_traits::{AsPrimitive, ConstOne, ConstZero, Float, Signed, Zero};
 use super::{Axis::{self, *}, Direction::{self, *}, Modulo, Vec2};
 
 #[derive(Copy, Clone, Default, PartialEq, Eq, Hash)]
+#[cfg_attr(feature = "serde", derive(serde::Serialize, serde::Deserialize))]
 pub struct Vec3<T>(pub T, pub T, pub T);
 
 impl<T: Debug> Debug for Vec3<T> {
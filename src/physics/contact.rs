@@ -12,22 +12,33 @@ pub struct Contact {
 // MARK: Detect Contacts
 
 // Decide what surfaces the hitbox is in contact with
-pub fn detect_contacts(cells: &HashMap<Vec3<isize>, Cell>, l: Vec3<f64>, h: Vec3<f64>) -> Vec<Contact> {
-	Vec3Range::<isize, ZYX>::inclusive(
-		(l - Vec3::all(SURFACE_MARGIN)).floor_to(),
-		(h + Vec3::all(SURFACE_MARGIN)).floor_to()
-	).map(|tile_pos| test_contact(l, h, cells, tile_pos)).flatten().collect::<Vec<_>>()
+pub fn detect_contacts(cells: &HashMap<Vec3<isize>, Cell>, hitbox: Aabb<f64>) -> Vec<Contact> {
+	hitbox.expand(Vec3::all(SURFACE_MARGIN)).tiles().map(|tile_pos| test_contact(hitbox, cells, tile_pos)).flatten().collect::<Vec<_>>()
 }
 
 
-fn test_contact(l: Vec3<f64>, h: Vec3<f64>, cells: &HashMap<Vec3<isize>, Cell>, tile_pos: Vec3<isize>) -> Vec<Contact> {
+// Being in water takes precedence over standing on something, and only surfaces that could be walked on count as ground
+pub fn contact_state(cells: &HashMap<Vec3<isize>, Cell>, hitbox: Aabb<f64>) -> ContactState {
+	let center = hitbox.center().floor_to::<isize>();
+	if cells.get(&(center >> CELL_SIZE_BITS)).is_some_and(|cell| cell.tiles[(center & CELL_MASK).as_type()].fluid == Water) {
+		return ContactState::Swimming
+	}
+	
+	match detect_contacts(cells, hitbox).iter().any(|contact| contact.normal.z() > 0.5) {
+		true => ContactState::Grounded,
+		false => ContactState::Airborne,
+	}
+}
+
+
+fn test_contact(hitbox: Aabb<f64>, cells: &HashMap<Vec3<isize>, Cell>, tile_pos: Vec3<isize>) -> Vec<Contact> {
 	let cell_pos = tile_pos >> CELL_SIZE_BITS;
 	if let Some(cell) = cells.get(&cell_pos) {
 		let tile = cell.tiles[(tile_pos & CELL_MASK).as_type()];
 		
 		match tile.state() {
 			TileState::Empty => vec![],
-			TileState::Full => match test_contact_full_block(l, h, tile_pos) {
+			TileState::Full => match test_contact_full_block(hitbox, tile_pos) {
 				Some((direction, displacement)) => vec![Contact {
 					normal: Vec3::<f64>::unit(direction),
 					material: tile.material,
@@ -35,7 +46,7 @@ fn test_contact(l: Vec3<f64>, h: Vec3<f64>, cells: &HashMap<Vec3<isize>, Cell>,
 				}],
 				None => vec![]
 			}
-			TileState::Partial => match test_contact_slope(l, h, tile_pos, tile.direction, tile.level) {
+			TileState::Partial => match test_contact_slope(hitbox, tile_pos, tile) {
 				Some((normal, displacement)) => vec![Contact {
 					normal,
 					material: tile.material,
@@ -47,9 +58,10 @@ fn test_contact(l: Vec3<f64>, h: Vec3<f64>, cells: &HashMap<Vec3<isize>, Cell>,
 	} else { vec![] }
 }
 
-fn test_contact_full_block(l: Vec3<f64>, h: Vec3<f64>, tile_pos: Vec3<isize>) -> Option<(Direction, f64)> {	
-	let h_inset = h - tile_pos.as_type::<f64>();
-	let l_inset = tile_pos.as_type::<f64>() + Vec3(1.0, 1.0, 1.0) - l;
+fn test_contact_full_block(hitbox: Aabb<f64>, tile_pos: Vec3<isize>) -> Option<(Direction, f64)> {
+	let tile_box = Aabb::tile(tile_pos);
+	let h_inset = hitbox.max - tile_box.min;
+	let l_inset = tile_box.max - hitbox.min;
 	
 	for a in [Z, Y, X] {
 		if h_inset[a.l()] > 0.0 && l_inset[a.l()] > 0.0
@@ -66,7 +78,8 @@ fn test_contact_full_block(l: Vec3<f64>, h: Vec3<f64>, tile_pos: Vec3<isize>) ->
 	None
 }
 
-fn test_contact_slope(l: Vec3<f64>, h: Vec3<f64>, tile_pos: Vec3<isize>, direction: Vec3<i8>, level: i8) -> Option<(Vec3<f64>, f64)> {
+fn test_contact_slope(hitbox: Aabb<f64>, tile_pos: Vec3<isize>, tile: Tile) -> Option<(Vec3<f64>, f64)> {
+	let (direction, level) = (tile.direction, tile.level);
 	
 	{ // Decide if we even need to run this at all
 		let mut positive_sum = 0;
@@ -74,29 +87,19 @@ fn test_contact_slope(l: Vec3<f64>, h: Vec3<f64>, tile_pos: Vec3<isize>, directi
 		direction.map(|v| if v >= 0 { positive_sum += v; } else { negative_sum += v; });
 		
 		if level <= negative_sum { return None }
-		if level >= positive_sum { return test_contact_full_block(l, h, tile_pos).map(|(d, displacement)| (Vec3::<f64>::unit(d), displacement)) }
+		if level >= positive_sum { return test_contact_full_block(hitbox, tile_pos).map(|(d, displacement)| (Vec3::<f64>::unit(d), displacement)) }
 	}
 	
 	
-	let near_corner = Vec3::by_axis(|a| if direction[a] >= 0 {l[a]} else {h[a]});
-	
-	let slope_normal = direction.as_type::<f64>();
-	let slope_s = (tile_pos.dot(direction.as_type::<isize>()) + level as isize) as f64;
+	let near_corner = hitbox.corner(direction.map(|d| d < 0));
 	
-	// let h_inset = h - tile_pos.as_type::<f64>();
-	// let l_inset = tile_pos.as_type::<f64>() + Vec3(1.0, 1.0, 1.0) - l;
+	let slope = tile.slope_plane(tile_pos);
+	let slope_normal = slope.normal;
 	
 	// Main slope face
-	for _ in std::iter::once(()) {
-		let current_s = near_corner.dot(slope_normal);
-		let s_inset = (slope_s - current_s) / slope_normal.length();
-		
-		if s_inset.abs() < SURFACE_MARGIN
-		&& near_corner.x() + 1e-10 >= tile_pos.x() as f64 && near_corner.x() <= tile_pos.x() as f64 + 1.0 + 1e-10
-		&& near_corner.y() + 1e-10 >= tile_pos.y() as f64 && near_corner.y() <= tile_pos.y() as f64 + 1.0 + 1e-10
-		&& near_corner.z() + 1e-10 >= tile_pos.z() as f64 && near_corner.z() <= tile_pos.z() as f64 + 1.0 + 1e-10 {
-			return Some((slope_normal.normalize(), s_inset))
-		}
+	let s_inset = -slope.signed_distance(near_corner);
+	if s_inset.abs() < SURFACE_MARGIN && Aabb::tile(tile_pos).expand(Vec3::all(1e-10)).contains(near_corner) {
+		return Some((slope.unit_normal(), s_inset))
 	}
 	
 	// Acute edges
@@ -118,7 +121,7 @@ fn test_contact_slope(l: Vec3<f64>, h: Vec3<f64>, tile_pos: Vec3<isize>, directi
 		if s_inset.abs() < SURFACE_MARGIN
 		&& near_edge.x() + 1e-10 >= tile_pos.x() as f64 && near_edge.x() <= tile_pos.x() as f64 + 1.0 + 1e-10
 		&& near_edge.y() + 1e-10 >= tile_pos.y() as f64 && near_edge.y() <= tile_pos.y() as f64 + 1.0 + 1e-10
-		&& plane_relative_position > l[a] && plane_relative_position < h[a] {
+		&& plane_relative_position > hitbox.min[a] && plane_relative_position < hitbox.max[a] {
 			return Some((edge_normal.normalize().vec3(a), s_inset))
 		}
 	}
@@ -137,19 +140,20 @@ fn test_contact_slope(l: Vec3<f64>, h: Vec3<f64>, tile_pos: Vec3<isize>, directi
 		);
 		
 		if s_inset.abs() < SURFACE_MARGIN
-		&& corner_relative_position.x() > l[a.l()] && corner_relative_position.x() < h[a.l()]
-		&& corner_relative_position.y() > l[a.r()] && corner_relative_position.y() < h[a.r()] {
+		&& corner_relative_position.x() > hitbox.min[a.l()] && corner_relative_position.x() < hitbox.max[a.l()]
+		&& corner_relative_position.y() > hitbox.min[a.r()] && corner_relative_position.y() < hitbox.max[a.r()] {
 			return Some((Vec3::unit(match direction[a] >= 0 { true => a.p(), false => a.n() }), s_inset))
 		}
 	}
 	
 	// Regular face contact
-	let (d, displacement) = test_contact_full_block(l, h, tile_pos)?;
+	let (d, displacement) = test_contact_full_block(hitbox, tile_pos)?;
 	let a = d.axis();
-	let contacting_corner = near_corner.with(a, if d.is_positive() {l[a]} else {h[a]});
-	let contacting_point = Vec3::by_axis(|a| contacting_corner[a].clamp(tile_pos[a] as f64, tile_pos[a] as f64 + 1.0));
+	let tile_box = Aabb::tile(tile_pos);
+	let contacting_corner = near_corner.with(a, if d.is_positive() {hitbox.min[a]} else {hitbox.max[a]});
+	let contacting_point = Vec3::by_axis(|a| contacting_corner[a].clamp(tile_box.min[a], tile_box.max[a]));
 	
-	if contacting_point.dot(slope_normal) + 1e-10 < slope_s {
+	if slope.evaluate(contacting_point) + 1e-10 < 0.0 {
 		Some((Vec3::unit(d), displacement))
 	} else {
 		None
@@ -3,14 +3,15 @@ mod contact;
 mod collision;
 mod movement;
 pub mod raycast;
+#[cfg(test)] mod tests;
 
 use constraints::ConstraintSet;
 
 use crate::*;
 
 
-fn get_force(entity: &Entity) -> Vec3<f64> { // MARK: get_force
-	movement::force_from_inputs(entity) + Vec3(0.0, 0.0, -9.8) * entity.mass
+fn get_force(body: &Body, movement: Option<&Movement>) -> Vec3<f64> { // MARK: get_force
+	movement.map_or(Vec3::ZERO, movement::force_from_inputs) + Vec3(0.0, 0.0, -9.8) * body.mass
 }
 
 
@@ -19,20 +20,19 @@ pub const SURFACE_MARGIN: f64 = 1e-4;
 pub const MIN_V_BOUNCE: f64 = 0.1;
 
 
-pub fn physics_step(entity: &mut Entity, cells: &HashMap<Vec3<isize>, Cell>, dt: f64) { // MARK: Physics Step
+pub fn physics_step(transform: &mut Transform, body: &mut Body, movement: Option<&Movement>, cells: &HashMap<Vec3<isize>, Cell>, dt: f64) { // MARK: Physics Step
 	
-	entity.velocity += get_force(entity) / entity.mass * dt;
+	body.velocity += get_force(body, movement) / body.mass * dt;
 	
 	
-	let mut l = entity.position + entity.size.scale(LOW_CORNER);
-	let mut h = entity.position + entity.size.scale(HIGH_CORNER);
+	let mut hitbox = body.hitbox(transform.position);
 	
-	let contacts = contact::detect_contacts(cells, l, h);
+	let contacts = contact::detect_contacts(cells, hitbox);
 	
 	// todo: jump direction evaluation
-	if entity.jump_input {
+	if movement.is_some_and(|movement| movement.jump) {
 		for contact in &contacts {
-			entity.velocity += contact.normal * 5.0;
+			body.velocity += contact.normal * 5.0;
 			break
 		}
 	}
@@ -46,31 +46,30 @@ pub fn physics_step(entity: &mut Entity, cells: &HashMap<Vec3<isize>, Cell>, dt:
 		let contacts = match contacts_iter.next() {
 			Some(contacts) => contacts,
 			None => {
-				l = entity.position + entity.size.scale(LOW_CORNER);
-				h = entity.position + entity.size.scale(HIGH_CORNER);
-				contact::detect_contacts(cells, l, h)
+				hitbox = body.hitbox(transform.position);
+				contact::detect_contacts(cells, hitbox)
 			}
 		};
 		
 		// todo: resolve displacements smarter
 		// for (normal, displacement) in &contacts {
-		// 	// entity.position += *normal * displacement;
+		// 	// transform.position += *normal * displacement;
 		// }
 		
 		
-		let (new_velocity, constraint_set) = constraints::find_constraints(entity.velocity, contacts);
-		entity.velocity = new_velocity;
+		let (new_velocity, constraint_set) = constraints::find_constraints(body.velocity, contacts);
+		body.velocity = new_velocity;
 		
 		
 		
 		
-		if let Some(collision) = collision::detect_next_collision(entity, cells, l, h, dt_remaining) {
-			entity.position += entity.velocity * collision.dt;
+		if let Some(collision) = collision::detect_next_collision(body.velocity, cells, hitbox, dt_remaining) {
+			transform.position += body.velocity * collision.dt;
 			
 			// todo: friction on collision and sub-step movement
 			
-			let v_projected = entity.velocity.dot(collision.normal);
-			entity.velocity -= collision.normal * v_projected * if v_projected < -MIN_V_BOUNCE {1.0 + collision.material.get_properties().bounciness} else {1.0};
+			let v_projected = body.velocity.dot(collision.normal);
+			body.velocity -= collision.normal * v_projected * if v_projected < -MIN_V_BOUNCE {1.0 + collision.material.get_properties().bounciness} else {1.0};
 			
 			dt_remaining -= collision.dt;
 			
@@ -80,7 +79,7 @@ pub fn physics_step(entity: &mut Entity, cells: &HashMap<Vec3<isize>, Cell>, dt:
 			// todo: unjank seriously
 			if let ConstraintSet::Single(constraint) = constraint_set {
 				
-				let speed = entity.velocity.length();
+				let speed = body.velocity.length();
 				let friction_delta_v = (
 					constraint.material_properties.friction_constant + 
 					constraint.material_properties.friction_linear * speed
@@ -88,18 +87,20 @@ pub fn physics_step(entity: &mut Entity, cells: &HashMap<Vec3<isize>, Cell>, dt:
 				let new_speed = speed - friction_delta_v;
 				
 				if new_speed > 0.0 {
-					entity.velocity = entity.velocity.normalize() * new_speed;
+					body.velocity = body.velocity.normalize() * new_speed;
 				} else {
-					entity.velocity = Vec3::ZERO;
+					body.velocity = Vec3::ZERO;
 				}
 			}
 			
-			entity.position += entity.velocity * dt_remaining;
+			transform.position += body.velocity * dt_remaining;
 			
 			break
 		}
 		
 	}
+	
+	body.contact = contact::contact_state(cells, body.hitbox(transform.position));
 }
 
 
@@ -0,0 +1,469 @@
+use crate::*;
+
+use super::contact::{detect_contacts, Contact};
+
+
+const DT: f64 = 0.01;
+const EPSILON: f64 = 1e-3;
+
+const PLAYER_SIZE: Vec3<f64> = Vec3(0.70, 0.70, 1.75);
+
+
+// MARK: Scene
+
+// Builds a small in-memory world out of ascii layers. Each layer is a horizontal slice at height z,
+// rows run along +y and columns along +x starting at the layer origin.
+struct Scene {
+	legend: HashMap<char, Tile>,
+	tiles: Vec<(Vec3<isize>, Tile)>,
+	extra_cells: Vec<Vec3<isize>>,
+}
+
+impl Scene {
+	fn new() -> Self {
+		Self {
+			legend: HashMap::from([
+				('.', Tile::empty(Air)),
+				(' ', Tile::empty(Air)),
+				('~', Tile::empty(Water)),
+				('#', Tile::full(Stone)),
+				('g', Tile::full(Grass)),
+				('d', Tile::full(Dirt)),
+				// 45 degree ramps, named by the direction they rise towards
+				('>', Tile { material: Stone, fluid: Air, level: 0, direction: Vec3(-1, 0, 1) }),
+				('<', Tile { material: Stone, fluid: Air, level: 1, direction: Vec3(1, 0, 1) }),
+				('v', Tile { material: Stone, fluid: Air, level: 0, direction: Vec3(0, -1, 1) }),
+				('^', Tile { material: Stone, fluid: Air, level: 1, direction: Vec3(0, 1, 1) }),
+			]),
+			tiles: vec![],
+			extra_cells: vec![],
+		}
+	}
+	
+	fn legend(mut self, c: char, tile: Tile) -> Self {
+		self.legend.insert(c, tile);
+		self
+	}
+	
+	fn layer(mut self, z: isize, origin: Vec2<isize>, rows: &[&str]) -> Self {
+		for (y, row) in rows.iter().enumerate() {
+			for (x, c) in row.chars().enumerate() {
+				let tile = *self.legend.get(&c).unwrap_or_else(|| panic!("No tile for {c:?} in scene legend"));
+				self.tiles.push((origin.with_z(z) + Vec3(x as isize, y as isize, 0), tile));
+			}
+		}
+		self
+	}
+	
+	// Inclusive box of a single tile type
+	fn fill(mut self, l: Vec3<isize>, h: Vec3<isize>, tile: Tile) -> Self {
+		for pos in Vec3Range::<isize, ZYX>::inclusive(l, h) {
+			self.tiles.push((pos, tile));
+		}
+		self
+	}
+	
+	// Make sure a cell exists even if no tiles were placed in it
+	fn cell(mut self, location: Vec3<isize>) -> Self {
+		self.extra_cells.push(location);
+		self
+	}
+	
+	fn build(self) -> HashMap<Vec3<isize>, Cell> {
+		let mut cells = HashMap::new();
+		for location in self.extra_cells {
+			cells.entry(location).or_insert_with(|| Cell::new(Cell::empty_tiles()));
+		}
+		for (pos, tile) in self.tiles {
+			let cell = cells.entry(pos >> CELL_SIZE_BITS).or_insert_with(|| Cell::new(Cell::empty_tiles()));
+			cell.tiles[(pos & CELL_MASK).as_type()] = tile;
+		}
+		cells
+	}
+}
+
+
+// MARK: Simulation
+
+// The components physics works on
+struct TestEntity {
+	transform: Transform,
+	body: Body,
+	movement: Movement,
+}
+
+impl TestEntity {
+	fn hitbox(&self) -> Aabb<f64> {
+		self.body.hitbox(self.transform.position)
+	}
+}
+
+fn entity_at(position: Vec3<f64>) -> TestEntity {
+	TestEntity { transform: Transform::new(position), body: Body::new(PLAYER_SIZE), movement: Movement::default() }
+}
+
+fn run(entity: &mut TestEntity, cells: &HashMap<Vec3<isize>, Cell>, ticks: usize) {
+	run_scripted(entity, cells, ticks, |_, _| ());
+}
+
+fn run_scripted<F: FnMut(usize, &mut TestEntity)>(entity: &mut TestEntity, cells: &HashMap<Vec3<isize>, Cell>, ticks: usize, mut script: F) {
+	for tick in 0..ticks {
+		script(tick, entity);
+		physics_step(&mut entity.transform, &mut entity.body, Some(&entity.movement), cells, DT);
+		assert!(entity.transform.position.x().is_finite() && entity.transform.position.y().is_finite() && entity.transform.position.z().is_finite(), "Entity position became {:?} on tick {tick}", entity.transform.position);
+	}
+}
+
+fn contacts(entity: &TestEntity, cells: &HashMap<Vec3<isize>, Cell>) -> Vec<Contact> {
+	detect_contacts(cells, entity.hitbox())
+}
+
+fn has_contact(contacts: &[Contact], normal: Vec3<f64>) -> bool {
+	contacts.iter().any(|c| (c.normal - normal).length() < 1e-6)
+}
+
+fn assert_near(actual: f64, expected: f64, what: &str) {
+	assert!((actual - expected).abs() < EPSILON, "{what}: expected {expected}, got {actual}");
+}
+
+fn flat_floor() -> Scene {
+	Scene::new().fill(Vec3(0, 0, 0), Vec3(15, 15, 0), Tile::full(Stone))
+}
+
+
+// MARK: Resting and falling
+
+#[test]
+fn rests_on_flat_ground() {
+	let cells = flat_floor().build();
+	let mut entity = entity_at(Vec3(8.0, 8.0, 3.0));
+	run(&mut entity, &cells, 200);
+	
+	assert_near(entity.transform.position.z(), 1.0, "z");
+	assert_near(entity.body.velocity.z(), 0.0, "vz");
+	assert_near(entity.transform.position.x(), 8.0, "x");
+	assert_near(entity.transform.position.y(), 8.0, "y");
+	assert!(has_contact(&contacts(&entity, &cells), Vec3::Z));
+}
+
+#[test]
+fn fast_fall_does_not_tunnel() {
+	let cells = flat_floor().build();
+	let mut entity = entity_at(Vec3(8.0, 8.0, 20.0));
+	entity.body.velocity = Vec3(0.0, 0.0, -500.0);
+	run(&mut entity, &cells, 50);
+	
+	assert_near(entity.transform.position.z(), 1.0, "z");
+}
+
+#[test]
+fn falls_freely_over_unloaded_cells() {
+	let cells = flat_floor().build();
+	let mut entity = entity_at(Vec3(-8.0, 8.0, 3.0));
+	run(&mut entity, &cells, 100);
+	
+	assert!(entity.transform.position.z() < -1.0, "Entity should not collide with unloaded cells, z = {}", entity.transform.position.z());
+}
+
+
+#[test]
+fn jumps_and_lands_again() {
+	let cells = flat_floor().build();
+	let mut entity = entity_at(Vec3(8.0, 8.0, 1.0));
+	let mut peak = 0.0f64;
+	let mut airborne = false;
+	run_scripted(&mut entity, &cells, 200, |tick, e| {
+		e.movement.jump = tick == 10;
+		peak = peak.max(e.transform.position.z());
+		airborne |= e.body.contact == ContactState::Airborne && tick > 10;
+	});
+	
+	assert!(peak > 2.0, "Jump only reached z = {peak}");
+	assert!(airborne);
+	assert_near(entity.transform.position.z(), 1.0, "z");
+	assert_near(entity.body.velocity.z(), 0.0, "vz");
+	assert_eq!(entity.body.contact, ContactState::Grounded);
+}
+
+#[test]
+fn water_counts_as_swimming() {
+	let cells = flat_floor().fill(Vec3(0, 0, 1), Vec3(15, 15, 2), Tile::empty(Water)).build();
+	let mut entity = entity_at(Vec3(8.0, 8.0, 3.0));
+	run(&mut entity, &cells, 200);
+	assert_eq!(entity.body.contact, ContactState::Swimming);
+}
+
+// MARK: Walls and corners
+
+#[test]
+fn walking_into_wall_stops_at_face() {
+	let cells = flat_floor()
+		.fill(Vec3(10, 0, 1), Vec3(10, 15, 2), Tile::full(Stone))
+		.build();
+	let mut entity = entity_at(Vec3(8.0, 8.0, 1.0));
+	run_scripted(&mut entity, &cells, 300, |_, e| e.movement.input = Vec3::X);
+	
+	let h = entity.hitbox().max;
+	assert!(h.x() <= 10.0 + SURFACE_MARGIN, "Entity clipped into wall, h.x = {}", h.x());
+	assert_near(h.x(), 10.0, "h.x");
+	assert_near(entity.body.velocity.x(), 0.0, "vx");
+	assert_near(entity.transform.position.z(), 1.0, "z");
+	assert!(has_contact(&contacts(&entity, &cells), -Vec3::<f64>::X));
+}
+
+#[test]
+fn slides_along_wall() {
+	let cells = flat_floor()
+		.fill(Vec3(10, 0, 1), Vec3(10, 15, 2), Tile::full(Stone))
+		.build();
+	let mut entity = entity_at(Vec3(9.0, 4.0, 1.0));
+	run_scripted(&mut entity, &cells, 100, |_, e| e.movement.input = Vec3(1.0, 1.0, 0.0).normalize());
+	
+	let h = entity.hitbox().max;
+	assert!(h.x() <= 10.0 + SURFACE_MARGIN, "Entity clipped into wall, h.x = {}", h.x());
+	assert!(entity.transform.position.y() > 5.0, "Entity got stuck on wall at y = {}", entity.transform.position.y());
+	assert!(entity.body.velocity.y() > 0.0);
+}
+
+#[test]
+fn inner_corner_stops_both_axes() {
+	let cells = flat_floor()
+		.layer(1, Vec2(6, 6), &[
+			"....#",
+			"....#",
+			"....#",
+			"....#",
+			"#####",
+		])
+		.build();
+	let mut entity = entity_at(Vec3(7.0, 7.0, 1.0));
+	run_scripted(&mut entity, &cells, 200, |_, e| e.movement.input = Vec3(1.0, 1.0, 0.0).normalize());
+	
+	let h = entity.hitbox().max;
+	assert_near(h.x(), 10.0, "h.x");
+	assert_near(h.y(), 10.0, "h.y");
+	assert_near(entity.transform.position.z(), 1.0, "z");
+	
+	let contacts = contacts(&entity, &cells);
+	assert!(has_contact(&contacts, -Vec3::<f64>::X));
+	assert!(has_contact(&contacts, -Vec3::<f64>::Y));
+}
+
+#[test]
+fn diagonal_into_outer_corner_does_not_clip() {
+	let cells = flat_floor()
+		.fill(Vec3(10, 10, 1), Vec3(15, 15, 2), Tile::full(Stone))
+		.build();
+	
+	// Aim exactly at the corner of the block
+	let mut entity = entity_at(Vec3(10.0 - 0.35 - 2.0, 10.0 - 0.35 - 2.0, 1.0));
+	run_scripted(&mut entity, &cells, 60, |_, e| {
+		let h = e.hitbox().max;
+		assert!(h.x() <= 10.0 + SURFACE_MARGIN || h.y() <= 10.0 + SURFACE_MARGIN, "Entity clipped into corner at {:?}", e.transform.position);
+		e.movement.input = Vec3(1.0, 1.0, 0.0).normalize();
+	});
+	
+	assert_near(entity.transform.position.z(), 1.0, "z");
+}
+
+#[test]
+fn grazing_past_corner_is_not_blocked() {
+	let cells = flat_floor()
+		.fill(Vec3(10, 10, 1), Vec3(15, 15, 2), Tile::full(Stone))
+		.build();
+	
+	// Slide along the y = 10 plane, right next to the face of the block
+	let mut entity = entity_at(Vec3(8.0, 10.0 - 0.35, 1.0));
+	run_scripted(&mut entity, &cells, 100, |_, e| e.movement.input = Vec3::X);
+	
+	assert!(entity.transform.position.x() > 12.0, "Entity snagged on corner at x = {}", entity.transform.position.x());
+	let h = entity.hitbox().max;
+	assert!(h.y() <= 10.0 + SURFACE_MARGIN);
+}
+
+
+// MARK: Cell boundaries
+
+#[test]
+fn rests_on_horizontal_cell_seam() {
+	let cells = Scene::new()
+		.fill(Vec3(-4, -4, 0), Vec3(3, 3, 0), Tile::full(Stone))
+		.build();
+	assert_eq!(cells.len(), 4);
+	
+	// Straddles all four cells
+	let mut entity = entity_at(Vec3(0.0, 0.0, 3.0));
+	run(&mut entity, &cells, 200);
+	
+	assert_near(entity.transform.position.z(), 1.0, "z");
+	assert_near(entity.transform.position.x(), 0.0, "x");
+	assert_near(entity.transform.position.y(), 0.0, "y");
+}
+
+#[test]
+fn walks_across_cell_seam() {
+	let width = CELL_WIDTH as isize;
+	let cells = Scene::new()
+		.fill(Vec3(width - 4, 0, 0), Vec3(width + 4, 3, 0), Tile::full(Stone))
+		.build();
+	
+	let mut entity = entity_at(Vec3(width as f64 - 2.0, 2.0, 1.0));
+	run_scripted(&mut entity, &cells, 60, |_, e| e.movement.input = Vec3::X);
+	
+	assert!(entity.transform.position.x() > width as f64 + 0.5, "Entity did not cross the seam, x = {}", entity.transform.position.x());
+	assert_near(entity.transform.position.z(), 1.0, "z");
+}
+
+#[test]
+fn lands_on_vertical_cell_seam() {
+	let height = CELL_HEIGHT as isize;
+	let cells = Scene::new()
+		.fill(Vec3(0, 0, height - 1), Vec3(3, 3, height - 1), Tile::full(Stone))
+		.cell(Vec3(0, 0, 1))
+		.build();
+	
+	let mut entity = entity_at(Vec3(2.0, 2.0, height as f64 + 6.0));
+	run(&mut entity, &cells, 200);
+	
+	assert_near(entity.transform.position.z(), height as f64, "z");
+}
+
+#[test]
+fn blocked_by_wall_in_neighboring_cell() {
+	let width = CELL_WIDTH as isize;
+	let cells = Scene::new()
+		.fill(Vec3(width - 4, 0, 0), Vec3(width + 4, 3, 0), Tile::full(Stone))
+		.fill(Vec3(width, 0, 1), Vec3(width, 3, 2), Tile::full(Stone))
+		.build();
+	
+	let mut entity = entity_at(Vec3(width as f64 - 2.0, 2.0, 1.0));
+	run_scripted(&mut entity, &cells, 200, |_, e| e.movement.input = Vec3::X);
+	
+	let h = entity.hitbox().max;
+	assert_near(h.x(), width as f64, "h.x");
+}
+
+
+// MARK: Slopes
+
+fn slope_s(tile: Tile, tile_pos: Vec3<isize>) -> f64 {
+	(tile_pos.dot(tile.direction.as_type::<isize>()) + tile.level as isize) as f64
+}
+
+// Distance of the entity's nearest corner above the slope plane of the given tile
+fn height_above_slope(entity: &TestEntity, tile: Tile, tile_pos: Vec3<isize>) -> f64 {
+	let hitbox = entity.hitbox();
+	let near_corner = Vec3::by_axis(|a| if tile.direction[a] >= 0 {hitbox.min[a]} else {hitbox.max[a]});
+	let normal = tile.direction.as_type::<f64>();
+	(near_corner.dot(normal) - slope_s(tile, tile_pos)) / normal.length()
+}
+
+#[test]
+fn lands_on_slope_without_penetrating() {
+	let ramp = Tile { material: Stone, fluid: Air, level: 0, direction: Vec3(-1, 0, 1) };
+	let ramp_pos = Vec3(8, 8, 1);
+	let cells = flat_floor()
+		.fill(ramp_pos, ramp_pos, ramp)
+		.build();
+	
+	let mut entity = entity_at(Vec3(8.5, 8.5, 2.5));
+	let mut touched = false;
+	run_scripted(&mut entity, &cells, 100, |_, e| {
+		let h = e.hitbox().max;
+		if h.x().floor() as isize == ramp_pos.x() {
+			assert!(height_above_slope(e, ramp, ramp_pos) > -EPSILON, "Entity sank into slope at {:?}", e.transform.position);
+		}
+		touched |= has_contact(&contacts(e, &cells), ramp.direction.as_type::<f64>().normalize());
+	});
+	
+	assert!(touched, "Entity never touched the slope surface");
+}
+
+#[test]
+fn slope_contact_normals_point_upward() {
+	let ramp = Tile { material: Stone, fluid: Air, level: 1, direction: Vec3(1, 0, 1) };
+	let cells = flat_floor()
+		.fill(Vec3(8, 4, 1), Vec3(8, 11, 1), ramp)
+		.build();
+	
+	let mut entity = entity_at(Vec3(8.5, 8.0, 4.0));
+	run_scripted(&mut entity, &cells, 200, |_, e| {
+		for contact in contacts(e, &cells) {
+			assert!(contact.normal.z() > 0.0, "Contact normal {:?} points downward", contact.normal);
+		}
+	});
+	
+	assert!(entity.transform.position.z() >= 1.0 - EPSILON && entity.transform.position.z() <= 2.0 + EPSILON, "Entity left the slope layer, z = {}", entity.transform.position.z());
+}
+
+#[test]
+fn walks_up_gentle_slope_onto_ledge() {
+	let cells = flat_floor()
+		.legend('1', Tile { material: Stone, fluid: Air, level: 0, direction: Vec3(-1, 0, 2) })
+		.legend('2', Tile { material: Stone, fluid: Air, level: 1, direction: Vec3(-1, 0, 2) })
+		.layer(1, Vec2(0, 6), &[
+			"......12########",
+			"......12########",
+			"......12########",
+			"......12########",
+		])
+		.build();
+	
+	let mut entity = entity_at(Vec3(3.0, 8.0, 1.0));
+	run_scripted(&mut entity, &cells, 300, |_, e| e.movement.input = Vec3::X);
+	
+	assert!(entity.transform.position.x() > 9.0, "Entity could not climb the slope, stuck at {:?}", entity.transform.position);
+	assert_near(entity.transform.position.z(), 2.0, "z");
+}
+
+#[test]
+fn steep_slope_blocks_walking() {
+	let cells = flat_floor()
+		.layer(1, Vec2(0, 6), &[
+			"......>#########",
+			"......>#########",
+			"......>#########",
+			"......>#########",
+		])
+		.build();
+	
+	let mut entity = entity_at(Vec3(3.0, 8.0, 1.0));
+	run_scripted(&mut entity, &cells, 300, |_, e| e.movement.input = Vec3::X);
+	
+	// Friction on a 45 degree ramp is currently enough to stop a walking entity part way up
+	let h = entity.hitbox().max;
+	assert!(h.x() > 6.0 && h.x() < 7.0, "Entity should be stopped on the ramp, h.x = {}", h.x());
+	assert!(entity.transform.position.z() > 1.0 && entity.transform.position.z() < 2.0);
+}
+
+#[test]
+fn scene_legend_places_custom_tiles() {
+	let pillar = Tile { material: Brick, fluid: Air, level: 1, direction: Vec3::ZERO };
+	let cells = Scene::new()
+		.legend('B', pillar)
+		.layer(0, Vec2(-1, -1), &["B.", ".#"])
+		.build();
+	
+	let tile_at = |pos: Vec3<isize>| cells[&(pos >> CELL_SIZE_BITS)].tiles[(pos & CELL_MASK).as_type()];
+	assert_eq!(tile_at(Vec3(-1, -1, 0)), pillar);
+	assert_eq!(tile_at(Vec3(0, -1, 0)), Tile::empty(Air));
+	assert_eq!(tile_at(Vec3(0, 0, 0)), Tile::full(Stone));
+}
+
+
+#[test]
+fn ray_hits_slope_surface() {
+	let ramp = Tile { material: Stone, fluid: Air, level: 0, direction: Vec3(-1, 0, 1) };
+	let cells = flat_floor()
+		.fill(Vec3(8, 8, 1), Vec3(8, 8, 1), ramp)
+		.build();
+	
+	// Straight down onto the ramp lands on its surface, not the top of the tile
+	let (tile_pos, hit) = raycast::cast_ray(&cells, Ray::new(Vec3(8.25, 8.5, 5.0), Vec3(0.0, 0.0, -10.0))).unwrap();
+	assert_eq!(tile_pos, Vec3(8, 8, 1));
+	assert!(ramp.slope_plane(tile_pos).evaluate(hit).abs() < 1e-9, "Hit {hit:?} is off the slope");
+	
+	// Next to it the floor is hit instead, and a ray that's too short hits nothing
+	assert_eq!(raycast::cast_ray(&cells, Ray::new(Vec3(6.5, 8.5, 5.0), Vec3(0.0, 0.0, -10.0))).map(|(t, _)| t), Some(Vec3(6, 8, 0)));
+	assert_eq!(raycast::cast_ray(&cells, Ray::new(Vec3(6.5, 8.5, 5.0), Vec3(0.0, 0.0, -2.0))), None);
+}
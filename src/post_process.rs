@@ -0,0 +1,410 @@
+use glium::{framebuffer::MultiOutputFrameBuffer, index::PrimitiveType, texture::{DepthTexture2d, Texture2d}, uniform, uniforms::{MagnifySamplerFilter, MinifySamplerFilter, Sampler, SamplerBehavior, SamplerWrapFunction, UniformValue, Uniforms}, Display, DrawParameters, Frame, IndexBuffer, Surface, VertexBuffer};
+
+use crate::*;
+
+
+pub const POST_PROCESS_PATH: &str = "config/post_process.cfg";
+pub const DEFAULT_POST_PROCESS: &str = include_str!("../assets/config/post_process.cfg");
+
+const SCREEN_SAMPLER: SamplerBehavior = SamplerBehavior {
+	wrap_function: (SamplerWrapFunction::Clamp, SamplerWrapFunction::Clamp, SamplerWrapFunction::Clamp),
+	minify_filter: MinifySamplerFilter::Nearest,
+	magnify_filter: MagnifySamplerFilter::Nearest,
+	depth_texture_comparison: None,
+	max_anisotropy: 1,
+};
+
+// Blue is interpolated between slices by hand, so only red and green rely on the filtering
+const LUT_SAMPLER: SamplerBehavior = SamplerBehavior {
+	wrap_function: (SamplerWrapFunction::Clamp, SamplerWrapFunction::Clamp, SamplerWrapFunction::Clamp),
+	minify_filter: MinifySamplerFilter::Linear,
+	magnify_filter: MagnifySamplerFilter::Linear,
+	depth_texture_comparison: None,
+	max_anisotropy: 1,
+};
+
+
+
+// MARK: Settings
+
+#[derive(Clone, Debug, PartialEq)]
+pub enum PostPass {
+	AmbientOcclusion { strength: f32 },
+	Outline { thickness: f32, depth_threshold: f32, normal_threshold: f32, color: Vec3<f32> },
+	Fog { start: f32, end: f32, color: Vec3<f32> },
+	ColorGrade { lut: String, strength: f32 },
+	Vignette { strength: f32, radius: f32, softness: f32 },
+	Pixelate { size: f32 },
+}
+
+impl PostPass {
+	// The pass with its default parameters
+	pub fn from_name(name: &str) -> Option<Self> {
+		match name {
+			"AmbientOcclusion" => Some(Self::AmbientOcclusion { strength: 1.0 }),
+			"Outline" => Some(Self::Outline { thickness: 1.0, depth_threshold: 0.5, normal_threshold: 0.5, color: Vec3(0.0, 0.0, 0.0) }),
+			"Fog" => Some(Self::Fog { start: 60.0, end: 140.0, color: Vec3(0.55, 0.65, 0.8) }),
+			"ColorGrade" => Some(Self::ColorGrade { lut: "lut_identity".into(), strength: 1.0 }),
+			"Vignette" => Some(Self::Vignette { strength: 0.35, radius: 0.6, softness: 0.5 }),
+			"Pixelate" => Some(Self::Pixelate { size: 3.0 }),
+			_ => None,
+		}
+	}
+	
+	pub fn name(&self) -> &'static str {
+		match self {
+			Self::AmbientOcclusion { .. } => "AmbientOcclusion",
+			Self::Outline { .. } => "Outline",
+			Self::Fog { .. } => "Fog",
+			Self::ColorGrade { .. } => "ColorGrade",
+			Self::Vignette { .. } => "Vignette",
+			Self::Pixelate { .. } => "Pixelate",
+		}
+	}
+	
+	// Fragment shader in src/shaders, drawn over the screen with the default vertex shader
+	pub fn shader(&self) -> &'static str {
+		match self {
+			Self::AmbientOcclusion { .. } => "ambient_occlusion",
+			Self::Outline { .. } => "outline",
+			Self::Fog { .. } => "fog",
+			Self::ColorGrade { .. } => "color_grade",
+			Self::Vignette { .. } => "vignette",
+			Self::Pixelate { .. } => "pixelate",
+		}
+	}
+	
+	pub fn set(&mut self, parameter: &str, value: &str) -> Result<(), String> {
+		match (&mut *self, parameter) {
+			(Self::AmbientOcclusion { strength }, "strength")
+			| (Self::ColorGrade { strength, .. }, "strength")
+			| (Self::Vignette { strength, .. }, "strength") => *strength = parse_number(parameter, value)?,
+			(Self::Outline { thickness, .. }, "thickness") => *thickness = parse_number(parameter, value)?,
+			(Self::Outline { depth_threshold, .. }, "depth") => *depth_threshold = parse_number(parameter, value)?,
+			(Self::Outline { normal_threshold, .. }, "normal") => *normal_threshold = parse_number(parameter, value)?,
+			(Self::Outline { color, .. }, "color") | (Self::Fog { color, .. }, "color") => *color = parse_color(value)?,
+			(Self::Fog { start, .. }, "start") => *start = parse_number(parameter, value)?,
+			(Self::Fog { end, .. }, "end") => *end = parse_number(parameter, value)?,
+			(Self::ColorGrade { lut, .. }, "lut") => *lut = value.to_string(),
+			(Self::Vignette { radius, .. }, "radius") => *radius = parse_number(parameter, value)?,
+			(Self::Vignette { softness, .. }, "softness") => *softness = parse_number(parameter, value)?,
+			(Self::Pixelate { size }, "size") => *size = parse_number(parameter, value)?.max(1.0),
+			(pass, _) => return Err(format!("{} has no parameter {parameter:?}", pass.name())),
+		}
+		Ok(())
+	}
+}
+
+fn parse_number(parameter: &str, value: &str) -> Result<f32, String> {
+	value.parse().map_err(|_| format!("expected a number for {parameter}, got {value:?}"))
+}
+
+fn parse_color(value: &str) -> Result<Vec3<f32>, String> {
+	let components = value.split_whitespace().map(str::parse).collect::<Result<Vec<f32>, _>>();
+	match components.as_deref() {
+		Ok(&[r, g, b]) => Ok(Vec3(r, g, b)),
+		_ => Err(format!("expected three numbers for color, got {value:?}")),
+	}
+}
+
+
+#[derive(Clone, Debug, PartialEq)]
+pub struct PostSettingsError {
+	pub line: usize,
+	pub message: String,
+}
+
+impl std::fmt::Display for PostSettingsError {
+	fn fmt(&self, f: &mut std::fmt::Formatter<'_>) -> std::fmt::Result {
+		write!(f, "line {}: {}", self.line, self.message)
+	}
+}
+
+#[derive(Clone, Debug, Default, PartialEq)]
+pub struct PostSettings {
+	pub passes: Vec<PostPass>,
+}
+
+impl PostSettings {
+	pub fn default_settings() -> Self {
+		Self::parse(DEFAULT_POST_PROCESS).expect("Default post-processing settings failed to parse")
+	}
+	
+	// Falls back to the default passes if the file is missing or broken
+	pub fn load_or_default(assets: &Assets, path: &str) -> Self {
+		match assets.read_to_string(path) {
+			Ok(text) => Self::parse(&text).unwrap_or_else(|e| {
+				eprintln!("Error in {path}, using default post-processing: {e}");
+				Self::default_settings()
+			}),
+			Err(_) => Self::default_settings(),
+		}
+	}
+	
+	pub fn parse(text: &str) -> Result<Self, PostSettingsError> {
+		let mut settings = Self::default();
+		
+		for (i, line) in text.lines().enumerate() {
+			let error = |message: String| PostSettingsError { line: i + 1, message };
+			
+			let line = line.split('#').next().unwrap().trim();
+			if line.is_empty() { continue }
+			
+			let (name, parameters) = line.split_once('=').unwrap_or((line, ""));
+			let name = name.trim();
+			let mut pass = PostPass::from_name(name).ok_or_else(|| error(format!("unknown pass {name:?}")))?;
+			
+			for parameter in parameters.split(',').map(str::trim).filter(|p| !p.is_empty()) {
+				let (parameter, value) = parameter.split_once(char::is_whitespace).ok_or_else(|| error(format!("expected a value for {parameter}")))?;
+				pass.set(parameter, value.trim()).map_err(error)?;
+			}
+			
+			settings.passes.push(pass);
+		}
+		
+		Ok(settings)
+	}
+}
+
+
+
+// MARK: Render Targets
+
+// What the scene is drawn into, and the pair of textures the passes bounce between before the last one draws to the window
+pub struct RenderTargets {
+	pub color: Texture2d,
+	pub data: Texture2d, // Normals of what was drawn, with alpha 1 wherever anything was
+	pub depth: DepthTexture2d,
+	ping_pong: [Texture2d; 2],
+}
+
+impl RenderTargets {
+	pub fn new(display: &Display, (width, height): (u32, u32)) -> Self {
+		let (width, height) = (width.max(1), height.max(1));
+		Self {
+			color: Texture2d::empty(display, width, height).unwrap(),
+			data: Texture2d::empty(display, width, height).unwrap(),
+			depth: DepthTexture2d::empty(display, width, height).unwrap(),
+			ping_pong: [(); 2].map(|_| Texture2d::empty(display, width, height).unwrap()),
+		}
+	}
+	
+	pub fn size(&self) -> (u32, u32) {
+		self.color.dimensions()
+	}
+	
+	// Recreates every target when the window size changes
+	pub fn resize(&mut self, display: &Display, size: (u32, u32)) {
+		if size != self.size() {
+			*self = Self::new(display, size);
+		}
+	}
+	
+	pub fn scene_framebuffer(&self, display: &Display) -> MultiOutputFrameBuffer<'_> {
+		MultiOutputFrameBuffer::with_depth_buffer(display, [
+			("color", &self.color),
+			("data", &self.data),
+		], &self.depth).unwrap()
+	}
+}
+
+
+
+// MARK: Chain
+
+// Uniforms every pass gets, whether or not its shader uses them
+struct ScreenUniforms<'a> {
+	source: &'a Texture2d,
+	data: &'a Texture2d,
+	depth: &'a DepthTexture2d,
+	resolution: [f32; 2],
+	aspect_ratio: f32,
+	tile_depth_inverse: f32,
+	perspective: bool,
+}
+
+impl Uniforms for ScreenUniforms<'_> {
+	fn visit_values<'a, F: FnMut(&str, UniformValue<'a>)>(&'a self, mut f: F) {
+		f("screen_texture", UniformValue::Texture2d(self.source, Some(SCREEN_SAMPLER)));
+		f("data_texture", UniformValue::Texture2d(self.data, Some(SCREEN_SAMPLER)));
+		f("depth_texture", UniformValue::DepthTexture2d(self.depth, Some(SCREEN_SAMPLER)));
+		f("resolution", UniformValue::Vec2(self.resolution));
+		f("aspect_ratio", UniformValue::Float(self.aspect_ratio));
+		f("tile_depth_inverse", UniformValue::Float(self.tile_depth_inverse));
+		f("perspective", UniformValue::Bool(self.perspective));
+		f("near_plane", UniformValue::Float(NEAR_PLANE));
+	}
+}
+
+struct PassUniforms<'a, U> {
+	screen: ScreenUniforms<'a>,
+	pass: U,
+}
+
+impl<U: Uniforms> Uniforms for PassUniforms<'_, U> {
+	fn visit_values<'a, F: FnMut(&str, UniformValue<'a>)>(&'a self, mut f: F) {
+		self.screen.visit_values(&mut f);
+		self.pass.visit_values(f);
+	}
+}
+
+
+struct LoadedPass {
+	pass: PostPass,
+	program: ShaderProgram,
+	lut: Option<Handle<Texture2d>>,
+}
+
+pub struct PostChain {
+	passes: Vec<LoadedPass>,
+	vertex_buffer: VertexBuffer<Vec2<f32>>,
+	index_buffer: IndexBuffer<u8>,
+}
+
+impl PostChain {
+	// Passes whose LUT can't be loaded are left out, but a shader that doesn't compile fails the whole chain
+	pub fn new(display: &Display, assets: &mut Assets, settings: &PostSettings) -> Result<Self, ShaderError> {
+		let mut passes = Vec::new();
+		for pass in &settings.passes {
+			let lut = match pass {
+				PostPass::ColorGrade { lut, .. } => match assets.linear_texture(display, lut) {
+					Ok(texture) => Some(texture),
+					Err(e) => {
+						eprintln!("Could not load color grading LUT {lut}, skipping the pass: {e}");
+						continue
+					}
+				},
+				_ => None,
+			};
+			passes.push(LoadedPass { pass: pass.clone(), program: ShaderProgram::new(display, assets, "default", pass.shader(), &[])?, lut });
+		}
+		
+		Ok(Self {
+			passes,
+			vertex_buffer: VertexBuffer::new(display, &[Vec2(0.0f32, 0.0), Vec2(1.0, 0.0), Vec2(1.0, 1.0), Vec2(0.0, 1.0)]).unwrap(),
+			index_buffer: IndexBuffer::new(display, PrimitiveType::TrianglesList, &[0, 1, 2, 0, 2, 3u8]).unwrap(),
+		})
+	}
+	
+	// Recompiles every pass, keeping the programs that fail as they were
+	pub fn reload_shaders(&mut self, display: &Display, assets: &Assets) -> Vec<ShaderError> {
+		self.passes.iter_mut().filter_map(|pass| pass.program.reload(display, assets).err()).collect()
+	}
+	
+	// Reloads the LUTs loaded from texture `name`, keeping the old one if the file can't be read
+	pub fn reload_texture(&mut self, display: &Display, assets: &mut Assets, name: &str) -> Result<(), AssetError> {
+		for pass in &mut self.passes {
+			if let (PostPass::ColorGrade { lut, .. }, Some(texture)) = (&pass.pass, &mut pass.lut) {
+				if lut == name {
+					*texture = assets.linear_texture(display, name)?;
+				}
+			}
+		}
+		Ok(())
+	}
+	
+	pub fn passes(&self) -> impl Iterator<Item = &PostPass> {
+		self.passes.iter().map(|p| &p.pass)
+	}
+	
+	// Runs every pass over the scene in `targets`, the last one drawing to `frame`
+	pub fn draw(&self, frame: &mut Frame, targets: &RenderTargets, camera: &Camera) {
+		if self.passes.is_empty() {
+			blit_texture(frame, &targets.color);
+			return
+		}
+		
+		let (width, height) = targets.size();
+		let mut source = &targets.color;
+		for (i, pass) in self.passes.iter().enumerate() {
+			let screen = ScreenUniforms {
+				source,
+				data: &targets.data,
+				depth: &targets.depth,
+				resolution: [width as f32, height as f32],
+				aspect_ratio: camera.aspect_ratio,
+				tile_depth_inverse: 1.0 / camera.tile_depth,
+				perspective: camera.mode.is_perspective(),
+			};
+			
+			if i + 1 == self.passes.len() {
+				self.draw_pass(frame, pass, screen);
+			} else {
+				let target = &targets.ping_pong[i % 2];
+				self.draw_pass(&mut target.as_surface(), pass, screen);
+				source = target;
+			}
+		}
+	}
+	
+	fn draw_pass(&self, surface: &mut impl Surface, pass: &LoadedPass, screen: ScreenUniforms) {
+		let parameters = DrawParameters::default();
+		let (vertices, indices) = (&self.vertex_buffer, &self.index_buffer);
+		match (&pass.pass, &pass.lut) {
+			(PostPass::AmbientOcclusion { strength }, _) => surface.draw(vertices, indices, &pass.program, &PassUniforms { screen, pass: uniform! {
+				strength: *strength,
+			} }, &parameters),
+			(PostPass::Outline { thickness, depth_threshold, normal_threshold, color }, _) => surface.draw(vertices, indices, &pass.program, &PassUniforms { screen, pass: uniform! {
+				thickness: *thickness,
+				depth_threshold: *depth_threshold,
+				normal_threshold: *normal_threshold,
+				outline_color: *color,
+			} }, &parameters),
+			(PostPass::Fog { start, end, color }, _) => surface.draw(vertices, indices, &pass.program, &PassUniforms { screen, pass: uniform! {
+				fog_start: *start,
+				fog_end: *end,
+				fog_color: *color,
+			} }, &parameters),
+			(PostPass::ColorGrade { strength, .. }, Some(lut)) => surface.draw(vertices, indices, &pass.program, &PassUniforms { screen, pass: uniform! {
+				lut: Sampler(&**lut, LUT_SAMPLER),
+				strength: *strength,
+			} }, &parameters),
+			(PostPass::ColorGrade { .. }, None) => unreachable!("Color grading passes are only loaded with a LUT"),
+			(PostPass::Vignette { strength, radius, softness }, _) => surface.draw(vertices, indices, &pass.program, &PassUniforms { screen, pass: uniform! {
+				strength: *strength,
+				radius: *radius,
+				softness: *softness,
+			} }, &parameters),
+			(PostPass::Pixelate { size }, _) => surface.draw(vertices, indices, &pass.program, &PassUniforms { screen, pass: uniform! {
+				pixel_size: *size,
+			} }, &parameters),
+		}.unwrap();
+	}
+}
+
+
+
+#[cfg(test)]
+mod tests {
+	use super::*;
+	
+	#[test]
+	fn default_settings_parse() {
+		let settings = PostSettings::default_settings();
+		assert_eq!(settings.passes.iter().map(PostPass::name).collect::<Vec<_>>(), ["AmbientOcclusion", "Outline", "Fog", "Vignette"]);
+		assert_eq!(settings.passes[1], PostPass::Outline { thickness: 1.0, depth_threshold: 0.5, normal_threshold: 0.5, color: Vec3(0.05, 0.05, 0.08) });
+	}
+	
+	#[test]
+	fn passes_keep_order_and_defaults() {
+		let settings = PostSettings::parse("Pixelate = size 4\nFog = end 30 # comment\nVignette\nPixelate =").unwrap();
+		assert_eq!(settings.passes, [
+			PostPass::Pixelate { size: 4.0 },
+			PostPass::Fog { start: 60.0, end: 30.0, color: Vec3(0.55, 0.65, 0.8) },
+			PostPass::from_name("Vignette").unwrap(),
+			PostPass::Pixelate { size: 3.0 },
+		]);
+		
+		let settings = PostSettings::parse("ColorGrade = lut sepia, strength 0.5").unwrap();
+		assert_eq!(settings.passes, [PostPass::ColorGrade { lut: "sepia".into(), strength: 0.5 }]);
+	}
+	
+	#[test]
+	fn parse_errors_point_at_line() {
+		assert_eq!(PostSettings::parse("Fog\n\nBloom = strength 1").unwrap_err().line, 3);
+		assert_eq!(PostSettings::parse("Fog = density 2").unwrap_err().line, 1);
+		assert_eq!(PostSettings::parse("Fog = start").unwrap_err().line, 1);
+		assert_eq!(PostSettings::parse("Fog = start far").unwrap_err().line, 1);
+		assert_eq!(PostSettings::parse("Outline\nOutline = color 1 0").unwrap_err().line, 2);
+	}
+}
@@ -0,0 +1,276 @@
+use std::{collections::HashSet, ops::Deref};
+
+use glium::{program::{ProgramCreationError, ProgramCreationInput, ShaderType}, Display, Program};
+
+use crate::*;
+
+
+// Within the assets
+pub const SHADER_DIRECTORY: &str = "shaders";
+
+
+#[derive(Clone, Debug, PartialEq, Eq)]
+pub struct SourceLine {
+	pub file: String,
+	pub line: usize,
+}
+
+#[derive(Debug)]
+pub enum ShaderError {
+	Read(AssetError),
+	Include { file: String, line: usize, message: String },
+	Compile { file: String, log: String }, // With line numbers already pointing into the original files
+	Program(ProgramCreationError),
+}
+
+impl std::fmt::Display for ShaderError {
+	fn fmt(&self, f: &mut std::fmt::Formatter<'_>) -> std::fmt::Result {
+		match self {
+			Self::Read(error) => write!(f, "{error}"),
+			Self::Include { file, line, message } => write!(f, "{file}:{line}: {message}"),
+			Self::Compile { file, log } => write!(f, "could not compile {file}:\n{log}"),
+			Self::Program(error) => write!(f, "{error}"),
+		}
+	}
+}
+
+
+
+// MARK: Preprocessor
+
+// GLSL with `#include "file"` lines expanded and extra `#define`s added after `#version`, remembering where every line came from.
+// Each file is only included once, even if it's included again inside an `#ifdef` or by another include.
+#[derive(Clone, Debug)]
+pub struct ShaderSource {
+	pub text: String,
+	lines: Vec<SourceLine>, // Original location of each line of `text`
+}
+
+impl ShaderSource {
+	pub fn load(assets: &Assets, file: &str, defines: &[&str]) -> Result<Self, ShaderError> {
+		Self::preprocess(file, defines, |file| assets.read_to_string(&format!("{SHADER_DIRECTORY}/{file}")))
+	}
+	
+	// `defines` are what follows `#define`, so either a name or a name and a value
+	pub fn preprocess(file: &str, defines: &[&str], mut read: impl FnMut(&str) -> Result<String, AssetError>) -> Result<Self, ShaderError> {
+		let mut source = Self { text: String::new(), lines: Vec::new() };
+		source.append(file, Some(defines), &mut read, &mut HashSet::new())?;
+		Ok(source)
+	}
+	
+	fn append(&mut self, file: &str, mut defines: Option<&[&str]>, read: &mut impl FnMut(&str) -> Result<String, AssetError>, included: &mut HashSet<String>) -> Result<(), ShaderError> {
+		included.insert(file.to_string());
+		let text = read(file).map_err(ShaderError::Read)?;
+		
+		// Without a `#version` the defines go first, as nothing else has to come before them
+		if !text.lines().any(|line| line.trim_start().starts_with("#version")) {
+			self.push_defines(defines.take(), file, 1);
+		}
+		
+		for (i, line) in text.lines().enumerate() {
+			let trimmed = line.trim_start();
+			if let Some(rest) = trimmed.strip_prefix("#include") {
+				let name = rest.trim().strip_prefix('"').and_then(|name| name.strip_suffix('"'))
+					.ok_or_else(|| ShaderError::Include { file: file.to_string(), line: i + 1, message: "expected #include \"file\"".into() })?;
+				if !included.contains(name) {
+					self.append(name, None, read, included).map_err(|e| match e {
+						ShaderError::Read(error) => ShaderError::Include { file: file.to_string(), line: i + 1, message: error.to_string() },
+						e => e,
+					})?;
+				}
+				continue
+			}
+			
+			self.push(line, file, i + 1);
+			if trimmed.starts_with("#version") {
+				self.push_defines(defines.take(), file, i + 1);
+			}
+		}
+		
+		Ok(())
+	}
+	
+	fn push(&mut self, line: &str, file: &str, number: usize) {
+		self.text += line;
+		self.text.push('\n');
+		self.lines.push(SourceLine { file: file.to_string(), line: number });
+	}
+	
+	fn push_defines(&mut self, defines: Option<&[&str]>, file: &str, number: usize) {
+		for define in defines.unwrap_or_default() {
+			self.push(&format!("#define {define}"), file, number);
+		}
+	}
+	
+	// Where line `line` of the preprocessed text, counting from 1, came from
+	pub fn original_line(&self, line: usize) -> Option<&SourceLine> {
+		self.lines.get(line.checked_sub(1)?)
+	}
+	
+	// Rewrites the line numbers in a compiler log to point into the original files.
+	// Drivers write them as `0:12(5)` (Mesa), `0:12:` (AMD, Intel) or `0(12)` (NVIDIA), 0 being the source string.
+	pub fn map_log(&self, log: &str) -> String {
+		log.lines().map(|line| self.map_log_line(line)).collect::<Vec<_>>().join("\n")
+	}
+	
+	fn map_log_line(&self, line: &str) -> String {
+		let bytes = line.as_bytes();
+		for start in 0..bytes.len() {
+			if start > 0 && bytes[start - 1].is_ascii_alphanumeric() { continue }
+			let Some((length, number)) = parse_log_location(&line[start..]) else { continue };
+			if let Some(source) = self.original_line(number) {
+				return format!("{}{}:{}{}", &line[..start], source.file, source.line, &line[start + length..])
+			}
+			break
+		}
+		line.to_string()
+	}
+}
+
+// The length of the `string:line` or `string(line)` at the start of `text`, and the line
+fn parse_log_location(text: &str) -> Option<(usize, usize)> {
+	let digits = |s: &str| s.bytes().take_while(u8::is_ascii_digit).count();
+	let n = digits(text);
+	if n == 0 { return None }
+	
+	let rest = &text[n..];
+	if let Some(rest) = rest.strip_prefix(':') {
+		let m = digits(rest);
+		if m > 0 && matches!(rest.as_bytes().get(m), Some(b'(' | b':')) {
+			return Some((n + 1 + m, rest[..m].parse().ok()?))
+		}
+	} else if let Some(rest) = rest.strip_prefix('(') {
+		let m = digits(rest);
+		if m > 0 && rest.as_bytes().get(m) == Some(&b')') {
+			return Some((n + 2 + m, rest[..m].parse().ok()?))
+		}
+	}
+	None
+}
+
+
+
+// MARK: Programs
+
+// Builds a program from `shaders/{vert_shader}.vert` and `{frag_shader}.frag`, with `defines` added to both
+pub fn compile_program(display: &Display, assets: &Assets, vert_shader: &str, frag_shader: &str, defines: &[&str]) -> Result<Program, ShaderError> {
+	let (vert_file, frag_file) = (format!("{vert_shader}.vert"), format!("{frag_shader}.frag"));
+	let vertex = ShaderSource::load(assets, &vert_file, defines)?;
+	let fragment = ShaderSource::load(assets, &frag_file, defines)?;
+	
+	Program::new(display, ProgramCreationInput::SourceCode {
+		vertex_shader: &vertex.text,
+		tessellation_control_shader: None,
+		tessellation_evaluation_shader: None,
+		geometry_shader: None,
+		fragment_shader: &fragment.text,
+		transform_feedback_varyings: None,
+		outputs_srgb: false,
+		uses_point_size: false,
+	}).map_err(|e| match e {
+		ProgramCreationError::CompilationError(log, ShaderType::Vertex) => ShaderError::Compile { file: vert_file, log: vertex.map_log(&log) },
+		ProgramCreationError::CompilationError(log, ShaderType::Fragment) => ShaderError::Compile { file: frag_file, log: fragment.map_log(&log) },
+		e => ShaderError::Program(e),
+	})
+}
+
+
+// A program that remembers what it was built from, so it can be rebuilt when the files change
+pub struct ShaderProgram {
+	vert_shader: String,
+	frag_shader: String,
+	defines: Vec<String>,
+	program: Program,
+}
+
+impl ShaderProgram {
+	pub fn new(display: &Display, assets: &Assets, vert_shader: &str, frag_shader: &str, defines: &[&str]) -> Result<Self, ShaderError> {
+		Ok(Self {
+			vert_shader: vert_shader.to_string(),
+			frag_shader: frag_shader.to_string(),
+			defines: defines.iter().map(|d| d.to_string()).collect(),
+			program: compile_program(display, assets, vert_shader, frag_shader, defines)?,
+		})
+	}
+	
+	// Keeps the current program if the new one doesn't compile
+	pub fn reload(&mut self, display: &Display, assets: &Assets) -> Result<(), ShaderError> {
+		let defines = self.defines.iter().map(String::as_str).collect::<Vec<_>>();
+		self.program = compile_program(display, assets, &self.vert_shader, &self.frag_shader, &defines)?;
+		Ok(())
+	}
+}
+
+impl Deref for ShaderProgram {
+	type Target = Program;
+	fn deref(&self) -> &Program { &self.program }
+}
+
+
+
+#[cfg(test)]
+mod tests {
+	use std::collections::HashMap;
+	
+	use super::*;
+	
+	fn files(files: &[(&str, &str)]) -> impl FnMut(&str) -> Result<String, AssetError> {
+		let files = files.iter().map(|(name, text)| (name.to_string(), text.to_string())).collect::<HashMap<_, _>>();
+		move |name| files.get(name).cloned().ok_or_else(|| AssetError::Io { path: name.to_string(), error: std::io::ErrorKind::NotFound.into() })
+	}
+	
+	#[test]
+	fn includes_expand_once_and_defines_follow_version() {
+		let source = ShaderSource::preprocess("main.frag", &["OUTLINE", "SAMPLES 4"], files(&[
+			("main.frag", "#version 150\n#include \"common.glsl\"\n#include \"light.glsl\"\nvoid main() {}"),
+			("common.glsl", "float a;"),
+			("light.glsl", "  #include \"common.glsl\"\nfloat b;"),
+		])).unwrap();
+		
+		assert_eq!(source.text, "#version 150\n#define OUTLINE\n#define SAMPLES 4\nfloat a;\nfloat b;\nvoid main() {}\n");
+		assert_eq!(source.original_line(4), Some(&SourceLine { file: "common.glsl".into(), line: 1 }));
+		assert_eq!(source.original_line(5), Some(&SourceLine { file: "light.glsl".into(), line: 2 }));
+		assert_eq!(source.original_line(6), Some(&SourceLine { file: "main.frag".into(), line: 4 }));
+		assert_eq!(source.original_line(7), None);
+	}
+	
+	#[test]
+	fn include_errors_point_at_line() {
+		let error = ShaderSource::preprocess("main.frag", &[], files(&[("main.frag", "#version 150\n\n#include \"missing.glsl\"")])).unwrap_err();
+		assert!(matches!(error, ShaderError::Include { ref file, line: 3, .. } if file == "main.frag"), "{error}");
+		
+		let error = ShaderSource::preprocess("main.frag", &[], files(&[("main.frag", "#include <common.glsl>")])).unwrap_err();
+		assert!(matches!(error, ShaderError::Include { line: 1, .. }), "{error}");
+		
+		// Cycles stop at files that are already included
+		let source = ShaderSource::preprocess("a.glsl", &[], files(&[("a.glsl", "#include \"b.glsl\"\na"), ("b.glsl", "#include \"a.glsl\"\nb")])).unwrap();
+		assert_eq!(source.text, "b\na\n");
+	}
+	
+	#[test]
+	fn compile_logs_map_to_original_files() {
+		let source = ShaderSource::preprocess("main.frag", &["DEBUG"], files(&[
+			("main.frag", "#version 150\n#include \"common.glsl\"\nvoid main() {\n\tfoo;\n}"),
+			("common.glsl", "float a;\nfloat b;"),
+		])).unwrap();
+		
+		// foo is on line 6 of the preprocessed text
+		assert_eq!(source.map_log("0:6(2): error: `foo' undeclared"), "main.frag:4(2): error: `foo' undeclared");
+		assert_eq!(source.map_log("ERROR: 0:4: 'b' : redefinition\nERROR: 1 compilation errors"), "ERROR: common.glsl:2: 'b' : redefinition\nERROR: 1 compilation errors");
+		assert_eq!(source.map_log("0(3) : error C0000: syntax error"), "common.glsl:1 : error C0000: syntax error");
+		assert_eq!(source.map_log("0:99(1): error"), "0:99(1): error");
+	}
+	
+	#[test]
+	fn shaders_preprocess() {
+		let directory = std::path::Path::new(env!("CARGO_MANIFEST_DIR")).join("assets");
+		let assets = Assets::new(AssetSource::Directory(directory.clone()));
+		for entry in std::fs::read_dir(directory.join(SHADER_DIRECTORY)).unwrap() {
+			let name = entry.unwrap().file_name().into_string().unwrap();
+			if name.ends_with(".vert") || name.ends_with(".frag") {
+				let source = ShaderSource::load(&assets, &name, &[]).unwrap_or_else(|e| panic!("{e}"));
+				assert!(source.text.starts_with("#version"), "{name}");
+			}
+		}
+	}
+}
@@ -2,7 +2,8 @@ use num_traits::Zero;
 use crate::*;
 
 
-#[derive(Copy, Clone, Debug, Eq, PartialEq)]
+#[derive(Copy, Clone, Debug, Eq, PartialEq, Hash)]
+#[cfg_attr(feature = "serde", derive(serde::Serialize, serde::Deserialize))]
 pub enum Material {
 	Grass,
 	Mud,
@@ -14,6 +15,7 @@ pub enum Material {
 }
 
 #[derive(Copy, Clone, Debug, Eq, PartialEq)]
+#[cfg_attr(feature = "serde", derive(serde::Serialize, serde::Deserialize))]
 pub enum Fluid {
 	Air,
 	Water,
@@ -23,7 +25,39 @@ pub use Material::*;
 pub use Fluid::*;
 
 
+// Which way a face of a tile points, for picking its texture
+#[derive(Copy, Clone, Debug, Eq, PartialEq, Hash)]
+pub enum TileFace {
+	Top,
+	Side,
+	Bottom,
+	Slope, // Partial tiles' surfaces that face upwards
+}
+
+impl TileFace {
+	pub const ALL: [Self; 4] = [Self::Top, Self::Side, Self::Bottom, Self::Slope];
+	
+	pub fn of(d: Direction) -> Self {
+		match d {
+			PZ => Self::Top,
+			NZ => Self::Bottom,
+			_ => Self::Side,
+		}
+	}
+	
+	// Slopes facing down look like ceilings, and ones that are straight up and down like walls
+	pub fn of_slope(direction: Vec3<i8>) -> Self {
+		match direction.z() {
+			z if z > 0 => Self::Slope,
+			0 => Self::Side,
+			_ => Self::Bottom,
+		}
+	}
+}
+
+
 #[derive(Copy, Clone, Debug, PartialEq)]
+#[cfg_attr(feature = "serde", derive(serde::Serialize, serde::Deserialize))]
 pub struct MaterialProperties {
 	pub friction_constant: f64, // "smooth" friction
 	pub friction_linear: f64, // "rough" friction
@@ -56,17 +90,34 @@ impl MaterialProperties {
 
 
 impl Material {
-	pub fn get_uv(&self) -> Vec2<u16> {
+	pub const ALL: [Self; 7] = [Grass, Mud, Dirt, Stone, Wood, Brick, Tiles];
+	
+	pub fn name(&self) -> &'static str {
 		match self {
-			Grass => Vec2(1, 0),
-			Mud   => Vec2(2, 0),
-			Dirt  => Vec2(3, 0),
-			Stone => Vec2(4, 0),
-			Wood  => Vec2(0, 1),
-			Brick => Vec2(1, 1),
-			Tiles => Vec2(2, 1),
+			Grass => "grass",
+			Mud   => "mud",
+			Dirt  => "dirt",
+			Stone => "stone",
+			Wood  => "wood",
+			Brick => "brick",
+			Tiles => "tiles",
+		}
+	}
+	
+	pub fn from_name(name: &str) -> Option<Self> {
+		Self::ALL.into_iter().find(|material| material.name() == name)
+	}
+	
+	// Image in the tile textures used for each face
+	pub fn texture(&self, face: TileFace) -> &'static str {
+		match (self, face) {
+			(Grass, TileFace::Top | TileFace::Slope) => "grass_top",
+			(Grass, TileFace::Side) => "grass_side",
+			(Grass, TileFace::Bottom) => "dirt",
+			_ => self.name(),
 		}
 	}
+	
 	pub fn get_properties(&self) -> MaterialProperties {
 		MaterialProperties {
 			friction_constant: 0.5,
@@ -82,6 +133,7 @@ impl Material {
 
 
 #[derive(Copy, Clone, Debug, Eq, PartialEq)]
+#[cfg_attr(feature = "serde", derive(serde::Serialize, serde::Deserialize))]
 pub struct Tile {
 	pub material: Material,
 	pub fluid: Fluid,
@@ -121,6 +173,11 @@ impl Tile {
 		self.direction.is_zero() && self.level != 0
 	}
 	
+	// Surface of a partial tile placed at `tile_pos`, with the solid part behind it
+	pub fn slope_plane(&self, tile_pos: Vec3<isize>) -> Plane<f64> {
+		Plane::new(self.direction.as_type(), (tile_pos.dot(self.direction.as_type::<isize>()) + self.level as isize) as f64)
+	}
+	
 	 // Invalid for empty tiles
 	pub fn includes_corner(&self, corner: Vec3<i8>) -> bool {
 		self.direction.dot(corner) <= self.level
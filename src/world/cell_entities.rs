@@ -0,0 +1,68 @@
+use crate::*;
+
+
+
+// The cell an entity at `position` belongs to. Only one layer of cells is ever loaded, so above or below it
+// the entity still belongs to the cell in that column, or it could never come back.
+pub fn cell_of(position: Vec3<f64>) -> Vec3<isize> {
+	(position.floor_to::<isize>() >> CELL_SIZE_BITS).with_z(0)
+}
+
+impl World {
+	// Brings the cells loaded since the last tick to life: the entities stored with each are put back, and one that's
+	// never been loaded before is filled from the spawn table
+	pub(super) fn activate_loaded_cells(&mut self) {
+		let mut new_cells = self.cells.keys().filter(|location| !self.active_cells.contains(location)).copied().collect::<Vec<_>>();
+		new_cells.sort_by_key(|location| (location.z(), location.y(), location.x()));
+		
+		for location in new_cells {
+			for saved in self.saved_entities.remove(&location).unwrap_or_default() {
+				self.entities.put_back(saved);
+			}
+			if self.spawned_cells.insert(location) {
+				self.spawn_in_cell(location);
+			}
+			self.active_cells.insert(location);
+		}
+	}
+	
+	// Entities are only simulated while the cell they're in is loaded. Any in another cell, whether it was unloaded under
+	// them, they walked or fell out of the loaded ones, or they were put there, are stored with it until it loads.
+	// Local players are left alone, as cells stay loaded around them.
+	pub(super) fn store_stray_entities(&mut self) {
+		let strays = self.entities.transforms.iter()
+			.filter(|(id, transform)| !self.active_cells.contains(&cell_of(transform.position)) && !matches!(self.entities.controllers.get(*id), Some(Controller::Local(_))))
+			.map(|(id, transform)| (id, cell_of(transform.position)))
+			.collect::<Vec<_>>();
+		
+		for (id, location) in strays {
+			let saved = self.entities.take(id).unwrap();
+			self.saved_entities.entry(location).or_default().push(saved);
+		}
+	}
+	
+	// Entities stored with a cell that isn't loaded, in the order they'll be put back
+	pub fn saved_entities(&self, location: Vec3<isize>) -> &[SavedEntity] {
+		self.saved_entities.get(&location).map_or(&[], Vec::as_slice)
+	}
+	
+	pub(super) fn hash_cell_entities(&self, hasher: &mut StateHasher) {
+		for cells in [&self.active_cells, &self.spawned_cells] {
+			let mut locations = cells.iter().collect::<Vec<_>>();
+			locations.sort_by_key(|location| (location.z(), location.y(), location.x()));
+			hasher.write_u64(locations.len() as u64);
+			for location in locations {
+				location.map(|v| hasher.write_i64(v as i64));
+			}
+		}
+		
+		let mut saved = self.saved_entities.iter().collect::<Vec<_>>();
+		saved.sort_by_key(|(location, _)| (location.z(), location.y(), location.x()));
+		hasher.write_u64(saved.len() as u64);
+		for (location, entities) in saved {
+			location.map(|v| hasher.write_i64(v as i64));
+			hasher.write_u64(entities.len() as u64);
+			entities.iter().for_each(|entity| entity.hash_state(hasher));
+		}
+	}
+}
@@ -1,6 +1,8 @@
 use crate::*;
 
 
+#[cfg_attr(feature = "serde", derive(serde::Serialize, serde::Deserialize))]
+#[cfg_attr(feature = "serde", serde(default))]
 pub struct GeneratorSettings {
 	pub seed: u64,
 	pub large_size: f64,
@@ -11,6 +13,24 @@ pub struct GeneratorSettings {
 	pub center: f64,
 }
 
+impl GeneratorSettings {
+	pub fn new(seed: u64) -> Self {
+		Self {
+			seed,
+			large_size: 64.0,
+			small_size: 4.0,
+			octave_size: 2.0,
+			octave_weight: 2.0,
+			height_scale: 16.0,
+			center: 16.0,
+		}
+	}
+}
+
+impl Default for GeneratorSettings {
+	fn default() -> Self { Self::new(0) }
+}
+
 
 pub fn generate_cell(tiles: &mut CellTiles, location: Vec3<isize>, gen: &GeneratorSettings) {
 	for pos in Vec3Range::<usize, ZYX>::exclusive(Vec3::ZERO, Vec3(CELL_WIDTH, CELL_WIDTH, 1)) {
@@ -0,0 +1,146 @@
+use crate::*;
+
+
+// Block sizes of the coarser meshes, level 0 being the full resolution mesh
+pub const LOD_FACTORS: [usize; 3] = [1, 2, 4];
+
+
+pub struct LodMesh {
+	pub vertices: Vec<ModelVertex>,
+	pub indices: Vec<ModelIndex>,
+	pub mesh_buffers: Option<(VertexBuffer<ModelVertex>, IndexBuffer<ModelIndex>)>,
+}
+
+
+// Tiles of a cell merged into cubes of `factor` tiles a side
+pub struct CoarseTiles {
+	pub factor: usize,
+	pub size: Vec3<usize>,
+	pub tiles: Vec<Tile>,
+}
+
+impl CoarseTiles {
+	pub fn get(&self, pos: Vec3<isize>) -> Option<Tile> {
+		if pos.x() < 0 || pos.y() < 0 || pos.z() < 0 { return None }
+		let pos = pos.as_type::<usize>();
+		if pos.x() >= self.size.x() || pos.y() >= self.size.y() || pos.z() >= self.size.z() { return None }
+		Some(self.tiles[(pos.z() * self.size.y() + pos.y()) * self.size.x() + pos.x()])
+	}
+}
+
+
+// A block becomes solid if at least half of its tiles are, taking the most common material among them.
+// Otherwise it's empty with the most common fluid. Ties go to whichever came first.
+pub fn downsample(tiles: &CellTiles, factor: usize) -> CoarseTiles {
+	let size = CELL_SIZE.as_type::<usize>() / factor;
+	let mut coarse = Vec::with_capacity(size.x() * size.y() * size.z());
+	
+	for block in Vec3Range::<usize, ZYX>::exclusive(Vec3::ZERO, size) {
+		let mut materials: Vec<(Material, usize)> = vec![];
+		let mut fluids: Vec<(Fluid, usize)> = vec![];
+		
+		for offset in Vec3Range::<usize, ZYX>::exclusive(Vec3::ZERO, Vec3::all(factor)) {
+			let tile = tiles[block * factor + offset];
+			match tile_is_mostly_solid(tile) {
+				true => count(&mut materials, tile.material),
+				false => count(&mut fluids, tile.fluid),
+			}
+		}
+		
+		let solid_count = materials.iter().map(|(_, n)| n).sum::<usize>();
+		coarse.push(match solid_count * 2 >= factor.pow(3) {
+			true => Tile::full(most_common(&materials)),
+			false => Tile::empty(most_common(&fluids)),
+		});
+	}
+	
+	CoarseTiles { factor, size, tiles: coarse }
+}
+
+// Partial tiles count as solid if they include the tile's center
+fn tile_is_mostly_solid(tile: Tile) -> bool {
+	match tile.state() {
+		TileState::Empty => false,
+		TileState::Full => true,
+		TileState::Partial => tile.direction.x() + tile.direction.y() + tile.direction.z() <= 2 * tile.level,
+	}
+}
+
+fn count<T: PartialEq>(counts: &mut Vec<(T, usize)>, value: T) {
+	match counts.iter_mut().find(|(v, _)| *v == value) {
+		Some((_, n)) => *n += 1,
+		None => counts.push((value, 1)),
+	}
+}
+
+fn most_common<T: Copy>(counts: &[(T, usize)]) -> T {
+	counts.iter().fold(None, |best: Option<&(T, usize)>, c| match best {
+		Some(b) if b.1 >= c.1 => Some(b),
+		_ => Some(c),
+	}).unwrap().0
+}
+
+
+// Faces on the cell boundary are always added, even against solid neighbors. A full detail neighbor leaves out its
+// faces against our solid tiles, so where those are lost to an empty block a skirt facing inward takes their place.
+pub fn build_lod_mesh(tiles: &CellTiles, factor: usize, uvs: &TileUvs) -> LodMesh {
+	let coarse = downsample(tiles, factor);
+	let mut vertices = vec![];
+	let mut indices = vec![];
+	
+	for pos in Vec3Range::<usize, ZYX>::exclusive(Vec3::ZERO, coarse.size) {
+		let tile = coarse.tiles[(pos.z() * coarse.size.y() + pos.y()) * coarse.size.x() + pos.x()];
+		if tile.is_empty() { continue }
+		
+		for d in [PX, PY, PZ, NX, NY, NZ] {
+			let covered = coarse.get(pos.as_type::<isize>() + Vec3::<isize>::unit(d)).is_some_and(|t| !t.is_empty());
+			if covered { continue }
+			
+			let first = vertices.len();
+			add_face(&mut vertices, &mut indices, pos, d, tile, uvs);
+			for vertex in &mut vertices[first..] {
+				vertex.position *= factor as f32;
+			}
+		}
+	}
+	
+	// Skirts
+	for d in [PX, PY, PZ, NX, NY, NZ] {
+		let boundary = if d.is_positive() { CELL_SIZE[d.axis()] as usize - 1 } else { 0 };
+		for pos in Vec3Range::<usize, ZYX>::exclusive(Vec3::ZERO, CELL_SIZE.as_type::<usize>().with(d.axis(), 1)) {
+			let pos = pos.with(d.axis(), boundary);
+			let tile = tiles[pos];
+			if !tile_has_full_face(d, tile) { continue }
+			if !coarse.get((pos / factor).as_type()).unwrap().is_empty() { continue }
+			
+			let first = vertices.len();
+			add_face(&mut vertices, &mut indices, pos, -d, Tile::full(tile.material), uvs);
+			for vertex in &mut vertices[first..] {
+				vertex.position += Vec3::<f32>::unit(d);
+			}
+		}
+	}
+	
+	LodMesh { vertices, indices, mesh_buffers: None }
+}
+
+impl Cell {
+	pub fn build_lod_meshes(&mut self, uvs: &TileUvs) {
+		self.lod_meshes = LOD_FACTORS[1..].iter().map(|factor| build_lod_mesh(&self.tiles, *factor, uvs)).collect();
+		self.update_mesh_buffers = true;
+	}
+	
+	// Buffers for the given level of detail, falling back to finer ones that are ready
+	pub fn mesh_buffers(&self, lod: usize) -> Option<&(VertexBuffer<ModelVertex>, IndexBuffer<ModelIndex>)> {
+		(1..=lod).rev()
+			.filter_map(|lod| self.lod_meshes.get(lod - 1)?.mesh_buffers.as_ref())
+			.next()
+			.or(self.mesh_buffers.as_ref())
+	}
+}
+
+
+// Each level is used up to `lod_distance` tiles further than the one before
+pub fn lod_for_distance(distance: f64, lod_distance: f64) -> usize {
+	((distance / lod_distance).max(0.0) as usize).min(LOD_FACTORS.len() - 1)
+}
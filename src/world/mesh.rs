@@ -1,8 +1,6 @@
 use crate::*;
 
 
-const TILE_TEXTURE_ATLAS_SIZE: u32 = 16;
-
 const FACE_UVS: [Vec2<i8>; 4] = [
 	Vec2(0, 0),
 	Vec2(0, 1),
@@ -10,12 +8,6 @@ const FACE_UVS: [Vec2<i8>; 4] = [
 	Vec2(1, 0),
 ];
 
-const UV_MARGIN: f32 = 0.0001;
-
-fn uv_unbleed(uv: Vec2<f32>) -> Vec2<f32> {
-	(uv - Vec2(0.5, 0.5)) * (1.0 - UV_MARGIN) + Vec2(0.5, 0.5)
-}
-
 fn uv_to_face_mesh<T: Copy + num_traits::Zero + num_traits::One + std::ops::Sub<T, Output = T>>(uv: Vec2<T>, d: Direction) -> Vec3<T> {
 	match d {
 		PX => Vec3(T::one(), T::one() - uv.x(), T::one() - uv.y()),
@@ -40,11 +32,11 @@ fn face_mesh_to_uv<T: Copy + num_traits::One + std::ops::Sub<T, Output = T>>(pos
 
 
 // The given tile should have the specified face drawn if it has that face
-fn add_face(vertices: &mut Vec<ModelVertex>, indices: &mut Vec<ModelIndex>, pos: Vec3<usize>, d: Direction, tile: Tile) {
+pub fn add_face(vertices: &mut Vec<ModelVertex>, indices: &mut Vec<ModelIndex>, pos: Vec3<usize>, d: Direction, tile: Tile, uvs: &TileUvs) {
 	if tile.is_empty() { return }
 	
 	let pos = pos.as_type::<f32>();
-	let uv_corner = tile.material.get_uv().as_type::<f32>();
+	let uv_rect = uvs.get(tile.material, TileFace::of(d));
 	
 	let mut v = vec![];
 	
@@ -73,12 +65,12 @@ fn add_face(vertices: &mut Vec<ModelVertex>, indices: &mut Vec<ModelIndex>, pos:
 	vertices.append(&mut v.iter().map(|uv| ModelVertex {
 		position: pos + uv_to_face_mesh(*uv, d),
 		normal: Vec3::<f32>::unit(d),
-		uv: (uv_corner + uv_unbleed(*uv)) / TILE_TEXTURE_ATLAS_SIZE as f32,
+		uv: uv_rect.at(*uv),
 	}).collect());
 }
 
 
-fn tile_has_full_face(d: Direction, tile: Tile) -> bool {
+pub fn tile_has_full_face(d: Direction, tile: Tile) -> bool {
 	match tile.state() {
 		TileState::Empty => false,
 		TileState::Full => true,
@@ -93,7 +85,7 @@ fn tile_has_full_face(d: Direction, tile: Tile) -> bool {
 
 
 
-pub fn build_cell_mesh(cell: &mut Cell, location: Vec3<isize>, cells: &mut HashMap<Vec3<isize>, Cell>) {
+pub fn build_cell_mesh(cell: &mut Cell, location: Vec3<isize>, cells: &mut HashMap<Vec3<isize>, Cell>, uvs: &TileUvs) {
 	for pos in Vec3Range::<usize, ZYX>::exclusive(Vec3::ZERO, CELL_SIZE.as_type()) {
 		let tile = cell.tiles[pos];
 		
@@ -111,7 +103,7 @@ pub fn build_cell_mesh(cell: &mut Cell, location: Vec3<isize>, cells: &mut HashM
 					other_cell.tiles[pos.with(d.axis(), if d.is_positive() {0} else {CELL_SIZE[d.axis()] as usize - 1})]
 				} else { continue }
 			) {
-				add_face(&mut cell.vertices, &mut cell.indices, pos, d, tile);
+				add_face(&mut cell.vertices, &mut cell.indices, pos, d, tile, uvs);
 			}
 		}
 		
@@ -143,7 +135,6 @@ pub fn build_cell_mesh(cell: &mut Cell, location: Vec3<isize>, cells: &mut HashM
 			}
 			
 			let pos = pos.as_type::<f32>();
-			let uv_corner = tile.material.get_uv().as_type::<f32>();
 			
 			let index_base = cell.vertices.len() as ModelIndex;
 			let index_iter = match v.len() {
@@ -164,13 +155,14 @@ pub fn build_cell_mesh(cell: &mut Cell, location: Vec3<isize>, cells: &mut HashM
 			});
 			
 			let uv_direction = *[PX, PY, PZ, NX, NY, NZ].iter().map(|d| (d, tile.direction.dot(Vec3::<i8>::unit(*d)))).max_by(|a, b| a.1.cmp(&b.1)).unwrap().0;
+			let uv_rect = uvs.get(tile.material, TileFace::of_slope(tile.direction));
 			
 			for vertex in v {
 				let vertex = Vec3::by_axis(|a| if tile.direction[a] >= 0 {vertex[a]} else {1.0 - vertex[a]});
 				cell.vertices.push(ModelVertex {
 					position: pos + vertex,
 					normal: tile.direction.as_type::<f32>().normalize(),
-					uv: (uv_corner + uv_unbleed(face_mesh_to_uv(vertex, uv_direction))) / TILE_TEXTURE_ATLAS_SIZE as f32,
+					uv: uv_rect.at(face_mesh_to_uv(vertex, uv_direction)),
 				});
 			}
 		}
@@ -188,7 +180,7 @@ pub fn build_cell_mesh(cell: &mut Cell, location: Vec3<isize>, cells: &mut HashM
 				if tile.is_empty() { continue }
 				
 				if !tile_has_full_face(-d, cell.tiles[this_tile_pos]) {
-					add_face(&mut other_cell.vertices, &mut other_cell.indices, other_tile_pos, d, tile);
+					add_face(&mut other_cell.vertices, &mut other_cell.indices, other_tile_pos, d, tile, uvs);
 				}
 			}
 			
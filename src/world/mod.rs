@@ -1,11 +1,23 @@
+use std::collections::HashSet;
+
 use crate::*;
 
 pub mod perlin;
 pub mod generator;
 pub mod mesh;
+pub mod lod;
+pub mod state_hash;
+pub mod navigation;
+pub mod spawning;
+pub mod cell_entities;
+#[cfg(test)] mod tests;
 // pub use perlin::*;
 pub use generator::*;
 pub use mesh::*;
+pub use lod::*;
+pub use state_hash::*;
+pub use navigation::*;
+pub use spawning::*;
 
 
 
@@ -21,6 +33,12 @@ pub const CELL_SIZE_BITS: Vec3<u16> = Vec3(CELL_WIDTH_BITS, CELL_WIDTH_BITS, CEL
 pub const CELL_SIZE: Vec3<isize> = Vec3(CELL_WIDTH as isize, CELL_WIDTH as isize, CELL_HEIGHT as isize);
 pub const CELL_MASK: Vec3<isize> = Vec3(CELL_XY_MASK, CELL_XY_MASK, CELL_Z_MASK);
 
+pub const TICK_RATE: f64 = 100.0;
+pub const TICK_DT: f64 = 1.0 / TICK_RATE;
+
+pub const LOAD_DISTANCE: f64 = 4.5;
+pub const UNLOAD_DISTANCE: f64 = 5.5;
+
 pub type CellTiles = [[[Tile; CELL_WIDTH]; CELL_WIDTH]; CELL_HEIGHT];
 
 
@@ -42,32 +60,104 @@ pub struct Cell {
 	pub vertices: Vec<ModelVertex>,
 	pub indices: Vec<ModelIndex>,
 	pub mesh_buffers: Option<(VertexBuffer<ModelVertex>, IndexBuffer<ModelIndex>)>,
+	pub lod_meshes: Vec<LodMesh>,
 	pub update_mesh_buffers: bool,
 	pub unload: bool,
+	pub tiles_hash: u64,
+}
+
+impl Cell {
+	pub fn new(tiles: Box<CellTiles>) -> Self {
+		Self {
+			tiles_hash: hash_tiles(&tiles),
+			tiles,
+			vertices: vec![],
+			indices: vec![],
+			mesh_buffers: None,
+			lod_meshes: vec![],
+			update_mesh_buffers: false,
+			unload: false,
+		}
+	}
+	
+	pub fn empty_tiles() -> Box<CellTiles> {
+		let ptr = Box::into_raw(vec![[[Tile::default(); CELL_WIDTH]; CELL_WIDTH]; CELL_HEIGHT].into_boxed_slice()) as *mut CellTiles;
+		unsafe { Box::from_raw(ptr) }
+	}
+	
+	// The space covered by the cell at `location`, in tiles
+	pub fn bounds(location: Vec3<isize>) -> Aabb<f64> {
+		let min = (location << CELL_SIZE_BITS).as_type::<f64>();
+		Aabb::new(min, min + CELL_SIZE.as_type::<f64>())
+	}
+	
+	// Must be called after editing tiles so that the world state hash stays in sync
+	pub fn update_tiles_hash(&mut self) {
+		self.tiles_hash = hash_tiles(&self.tiles);
+	}
 }
 
 
 
 pub struct World {
 	pub cells: HashMap<Vec3<isize>, Cell>,
-	pub entities: Vec<Entity>,
+	pub entities: Entities,
+	pub tick: u64,
+	pub spawn_table: SpawnTable,
+	active_cells: HashSet<Vec3<isize>>, // Loaded cells that have had their entities put back
+	spawned_cells: HashSet<Vec3<isize>>, // Every cell the spawn table has filled, loaded or not
+	saved_entities: HashMap<Vec3<isize>, Vec<SavedEntity>>, // Entities in cells that aren't loaded
 	generator_settings: GeneratorSettings,
+	tile_uvs: TileUvs,
 }
 
 impl World {
-	pub fn new() -> Self {
+	pub fn new(seed: u64) -> Self {
 		Self {
 			cells: HashMap::new(),
-			entities: vec![],
-			generator_settings: GeneratorSettings {
-				seed: (std::time::SystemTime::now().duration_since(std::time::UNIX_EPOCH).unwrap().as_millis() & (u64::MAX as u128)) as u64,
-				large_size: 64.0,
-				small_size: 4.0,
-				octave_size: 2.0,
-				octave_weight: 2.0,
-				height_scale: 16.0,
-				center: 16.0,
-			}
+			entities: Entities::default(),
+			tick: 0,
+			spawn_table: SpawnTable::default(),
+			active_cells: HashSet::new(),
+			spawned_cells: HashSet::new(),
+			saved_entities: HashMap::new(),
+			generator_settings: GeneratorSettings::new(seed),
+			tile_uvs: TileUvs::default(),
+		}
+	}
+	
+	pub fn seed(&self) -> u64 {
+		self.generator_settings.seed
+	}
+	
+	pub fn tile_uvs(&self) -> &TileUvs {
+		&self.tile_uvs
+	}
+	
+	// Rebuilds the meshes of every loaded cell with the new texture coordinates
+	pub fn set_tile_uvs(&mut self, uvs: TileUvs) {
+		self.tile_uvs = uvs;
+		
+		// Added back one at a time like when they were loaded, so each boundary face is only added once
+		for (location, mut cell) in std::mem::take(&mut self.cells) {
+			cell.vertices.clear();
+			cell.indices.clear();
+			build_cell_mesh(&mut cell, location, &mut self.cells, &self.tile_uvs);
+			cell.build_lod_meshes(&self.tile_uvs);
+			self.cells.insert(location, cell);
+		}
+	}
+	
+	// Throws away every cell along with any edits, so the terrain generates again from the seed. Everything that went with
+	// the old terrain goes too: the entities stored with its cells and the ones the spawner put in it, which it fills
+	// the new cells with again. Other entities stay where they are.
+	pub fn regenerate(&mut self) {
+		self.cells.clear();
+		self.active_cells.clear();
+		self.spawned_cells.clear();
+		self.saved_entities.clear();
+		for id in self.entities.spawned.ids().collect::<Vec<_>>() {
+			self.entities.despawn(id);
 		}
 	}
 	
@@ -88,29 +178,87 @@ impl World {
 	pub fn load(&mut self, location: Vec3<isize>) {
 		if self.cells.contains_key(&location) { return }
 		
-		let mut tiles = {
-			let ptr = Box::into_raw(vec![[[Tile::default(); CELL_WIDTH]; CELL_WIDTH]; CELL_HEIGHT].into_boxed_slice()) as *mut CellTiles;
-			unsafe { Box::from_raw(ptr) }
-		};
+		let mut tiles = Cell::empty_tiles();
 		
 		generate_cell(&mut tiles, location, &self.generator_settings);
 		
-		let mut cell = Cell {
-			tiles,
-			vertices: vec![],
-			indices: vec![],
-			mesh_buffers: None,
-			update_mesh_buffers: false,
-			unload: false,
-		};
+		let mut cell = Cell::new(tiles);
 		
-		build_cell_mesh(&mut cell, location, &mut self.cells);
+		build_cell_mesh(&mut cell, location, &mut self.cells, &self.tile_uvs);
+		cell.build_lod_meshes(&self.tile_uvs);
 		
 		self.cells.insert(location, cell);
 	}
 	
+	// Entities in the unloaded cells are stored with them
 	pub fn unload_flagged(&mut self) {
-		self.cells.retain(|_pos, cell| !cell.unload);
+		let active_cells = &mut self.active_cells;
+		self.cells.retain(|pos, cell| {
+			if cell.unload { active_cells.remove(pos); }
+			!cell.unload
+		});
+		self.store_stray_entities();
+	}
+	
+	// Loads cells around each of the given positions and unloads the ones that have gotten too far from all of them
+	pub fn update_loaded_cells(&mut self, centers: &[Vec3<f64>]) {
+		let cell_positions = centers.iter().map(|center| center.scale_divide(CELL_SIZE.as_type::<f64>()) - Vec3(0.5, 0.5, 0.5)).collect::<Vec<_>>();
+		
+		for (pos, cell) in &mut self.cells {
+			if cell_positions.iter().all(|cell_position| (pos.x() as f64 - cell_position.x()).abs() > 0.5 + UNLOAD_DISTANCE
+			|| (pos.y() as f64 - cell_position.y()).abs() > 0.5 + UNLOAD_DISTANCE) {
+				cell.unload = true;
+			}
+		}
+		
+		self.unload_flagged();
+		
+		for cell_position in cell_positions {
+			for pos in Vec3Range::<isize, ZYX>::inclusive(
+				(cell_position + Vec3(0.5 - LOAD_DISTANCE, 0.5 - LOAD_DISTANCE, 0.0)).floor_to().with_z(0),
+				(cell_position + Vec3(0.5 + LOAD_DISTANCE, 0.5 + LOAD_DISTANCE, 0.0)).floor_to().with_z(0)
+			) {
+				self.get_or_load_cell(pos);
+			}
+		}
+	}
+	
+	// Advances the simulation by one fixed timestep. Given the same seed and the same entity inputs
+	// before every tick, the resulting state is always the same.
+	pub fn step(&mut self) {
+		// Cells are kept loaded around every local player
+		let players = self.entities.controllers.iter()
+			.filter(|(_, controller)| matches!(controller, Controller::Local(_)))
+			.filter_map(|(id, _)| Some(self.entities.transforms.get(id)?.position))
+			.collect::<Vec<_>>();
+		if !players.is_empty() {
+			self.update_loaded_cells(&players);
+		}
+		self.activate_loaded_cells();
+		self.store_stray_entities();
+		
+		run_systems(&mut self.entities, &self.cells, TICK_DT);
+		
+		self.tick += 1;
+	}
+	
+	// Hash of all simulation state, used to detect divergence between runs that should be identical
+	pub fn state_hash(&self) -> u64 {
+		let mut hasher = StateHasher::new();
+		hasher.write_u64(self.tick);
+		
+		let mut locations = self.cells.keys().collect::<Vec<_>>();
+		locations.sort_by_key(|location| (location.z(), location.y(), location.x()));
+		hasher.write_u64(locations.len() as u64);
+		for location in locations {
+			location.map(|v| hasher.write_i64(v as i64));
+			hasher.write_u64(self.cells[location].tiles_hash);
+		}
+		
+		self.entities.hash_state(&mut hasher);
+		self.hash_cell_entities(&mut hasher);
+		
+		hasher.finish()
 	}
 	
 	pub fn place_player(&mut self, position: Vec3<f64>) -> Vec3<f64> {
@@ -135,6 +283,13 @@ impl World {
 					VertexBuffer::new(display, &cell.vertices).unwrap(),
 					IndexBuffer::new(display, PrimitiveType::TrianglesList, &cell.indices).unwrap(),
 				));
+				// Coarser meshes don't depend on the neighbors, so they only need uploading after being rebuilt
+				for lod in cell.lod_meshes.iter_mut().filter(|lod| lod.mesh_buffers.is_none()) {
+					lod.mesh_buffers = Some((
+						VertexBuffer::new(display, &lod.vertices).unwrap(),
+						IndexBuffer::new(display, PrimitiveType::TrianglesList, &lod.indices).unwrap(),
+					));
+				}
 				cell.update_mesh_buffers = false;
 			}
 		}
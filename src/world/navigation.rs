@@ -0,0 +1,287 @@
+use std::{cmp::Reverse, collections::BinaryHeap};
+
+use crate::*;
+
+
+// Costs are whole numbers so that the search, and so the paths it finds, are the same on every machine
+const STRAIGHT_COST: u32 = 10;
+const DIAGONAL_COST: u32 = 14;
+const JUMP_COST: u32 = 10;
+const DROP_COST: u32 = 4; // Per tile
+const WATER_COST_FACTOR: u32 = 2;
+
+// Gives up after visiting this many tiles, so an unreachable goal doesn't search everything loaded
+const MAX_SEARCH_NODES: usize = 20_000;
+
+// Slopes steeper than this can't be walked up, see `steep_slope_blocks_walking` in the physics tests
+const MIN_WALKABLE_NORMAL_Z: f64 = 0.75;
+
+// Small differences in floor height, like between the halves of a slope, don't need a jump
+const HEIGHT_TOLERANCE: f64 = 0.05;
+
+// How close to a waypoint counts as reaching it, and how close to a ledge to jump from
+const ARRIVE_DISTANCE: f64 = 0.25;
+const JUMP_DISTANCE: f64 = 1.1;
+
+const NEIGHBORS: [(isize, isize); 8] = [(1, 0), (0, 1), (-1, 0), (0, -1), (1, 1), (-1, 1), (-1, -1), (1, -1)];
+
+
+// What the path has to fit
+#[derive(Copy, Clone, Debug, PartialEq)]
+pub struct NavAgent {
+	pub size: Vec3<f64>,
+	pub step_height: f64, // Rises that can be walked up
+	pub jump_height: f64, // Rises that can be jumped up
+	pub max_drop: isize, // In tiles
+	pub can_swim: bool,
+}
+
+impl NavAgent {
+	pub fn new(size: Vec3<f64>) -> Self {
+		// Physics doesn't step up ledges, but a jump clears one tile
+		Self { size, step_height: 0.0, jump_height: 1.2, max_drop: 3, can_swim: true }
+	}
+	
+	// Tiles wide and tall
+	fn footprint(&self) -> (isize, isize) {
+		((self.size.x().max(self.size.y()) - 1e-9).ceil().max(1.0) as isize, (self.size.z() - 1e-9).ceil().max(1.0) as isize)
+	}
+}
+
+
+#[derive(Copy, Clone, Debug, PartialEq)]
+#[cfg_attr(feature = "serde", derive(serde::Serialize, serde::Deserialize))]
+pub struct Waypoint {
+	pub position: Vec3<f64>, // Where the entity's feet go
+	pub jump: bool, // Has to be jumped up to from the one before
+}
+
+#[derive(Clone, Debug, Default, PartialEq)]
+#[cfg_attr(feature = "serde", derive(serde::Serialize, serde::Deserialize))]
+pub struct Path {
+	pub waypoints: Vec<Waypoint>,
+}
+
+
+
+// MARK: Search
+
+// A* over the tiles an entity could stand in, from and to the tiles containing the given feet positions.
+// None when the goal can't be reached, or isn't somewhere the agent could stand.
+pub fn find_path(cells: &HashMap<Vec3<isize>, Cell>, agent: &NavAgent, start: Vec3<f64>, goal: Vec3<f64>) -> Option<Path> {
+	let grid = NavGrid { cells, agent: *agent, footprint: agent.footprint() };
+	let start = grid.ground_below(start)?;
+	let goal = grid.ground_below(goal)?;
+	
+	let heuristic = |node: Vec3<isize>| {
+		let (dx, dy) = ((node.x() - goal.x()).unsigned_abs() as u32, (node.y() - goal.y()).unsigned_abs() as u32);
+		DIAGONAL_COST * dx.min(dy) + STRAIGHT_COST * dx.abs_diff(dy)
+	};
+	
+	let mut open = BinaryHeap::new();
+	let mut costs = HashMap::from([(start, 0u32)]);
+	let mut came_from = HashMap::<Vec3<isize>, (Vec3<isize>, bool)>::new();
+	open.push(Reverse((heuristic(start), 0u32, ordered(start))));
+	
+	let mut visited = 0;
+	while let Some(Reverse((_, cost, node))) = open.pop() {
+		let node = Vec3(node.2, node.1, node.0);
+		if node == goal {
+			return Some(grid.build_path(&came_from, start, goal))
+		}
+		if cost > costs[&node] { continue }
+		
+		visited += 1;
+		if visited > MAX_SEARCH_NODES { return None }
+		
+		for (next, step_cost, jump) in grid.neighbors(node) {
+			let next_cost = cost + step_cost;
+			if costs.get(&next).is_none_or(|c| next_cost < *c) {
+				costs.insert(next, next_cost);
+				came_from.insert(next, (node, jump));
+				open.push(Reverse((next_cost + heuristic(next), next_cost, ordered(next))));
+			}
+		}
+	}
+	
+	None
+}
+
+// Ties between equally good tiles are broken by position, never by hash map order
+fn ordered(node: Vec3<isize>) -> (isize, isize, isize) {
+	(node.z(), node.y(), node.x())
+}
+
+
+struct NavGrid<'a> {
+	cells: &'a HashMap<Vec3<isize>, Cell>,
+	agent: NavAgent,
+	footprint: (isize, isize),
+}
+
+impl NavGrid<'_> {
+	// Unloaded tiles can't be walked through or stood on
+	fn tile(&self, position: Vec3<isize>) -> Option<Tile> {
+		self.cells.get(&(position >> CELL_SIZE_BITS)).map(|cell| cell.tiles[(position & CELL_MASK).as_type()])
+	}
+	
+	fn walkable_slope(tile: Tile) -> bool {
+		tile.state() == TileState::Partial && tile.direction.as_type::<f64>().normalize().z() >= MIN_WALKABLE_NORMAL_Z
+	}
+	
+	// The columns the agent covers when standing at `node`, which is the one nearest the middle
+	fn columns(&self, node: Vec3<isize>) -> impl Iterator<Item = Vec2<isize>> {
+		let width = self.footprint.0;
+		let min = Vec2(node.x(), node.y()) - Vec2::all((width - 1) / 2);
+		(0..width * width).map(move |i| min + Vec2(i % width, i / width))
+	}
+	
+	fn center(&self, node: Vec3<isize>) -> Vec2<f64> {
+		let width = self.footprint.0;
+		(Vec2(node.x(), node.y()) - Vec2::all((width - 1) / 2)).as_type::<f64>() + Vec2::all(width as f64 / 2.0)
+	}
+	
+	// Nothing solid from `bottom` up to `top` in the agent's columns, except walkable slopes at the bottom, which it stands in
+	fn clear(&self, node: Vec3<isize>, bottom: isize, top: isize) -> bool {
+		self.columns(node).all(|column| (bottom..top).all(|z| match self.tile(column.with_z(z)) {
+			Some(tile) => tile.is_empty() || (z == bottom && Self::walkable_slope(tile)),
+			None => false,
+		}))
+	}
+	
+	fn standable(&self, node: Vec3<isize>) -> bool {
+		let height = self.footprint.1;
+		let supported = self.columns(node).any(|column| {
+			self.tile(column.with_z(node.z())).is_some_and(Self::walkable_slope)
+			|| self.tile(column.with_z(node.z() - 1)).is_some_and(|tile| tile.is_full())
+		});
+		// Feet in a slope can be up to a tile higher, so it needs room for that
+		let in_slope = self.tile(node).is_some_and(Self::walkable_slope);
+		let swimming_allowed = self.agent.can_swim || !self.in_water(node);
+		supported && swimming_allowed && self.clear(node, node.z(), node.z() + height + in_slope as isize)
+	}
+	
+	fn in_water(&self, node: Vec3<isize>) -> bool {
+		self.columns(node).any(|column| self.tile(column.with_z(node.z())).is_some_and(|tile| tile.fluid == Water))
+	}
+	
+	// Height of the floor the agent stands on at `point`, which is somewhere on the edge of its tile
+	fn floor_height(&self, node: Vec3<isize>, point: Vec2<f64>) -> f64 {
+		match self.tile(node).filter(|tile| Self::walkable_slope(*tile)) {
+			Some(tile) => {
+				let plane = tile.slope_plane(node);
+				let z = (plane.offset - plane.normal.x() * point.x() - plane.normal.y() * point.y()) / plane.normal.z();
+				z.clamp(node.z() as f64, node.z() as f64 + 1.0)
+			}
+			None => node.z() as f64,
+		}
+	}
+	
+	// Feet position at the middle of the agent's columns
+	fn feet(&self, node: Vec3<isize>) -> Vec3<f64> {
+		let center = self.center(node);
+		center.with_z(self.floor_height(node, center))
+	}
+	
+	// The tile a position's feet are in, or the first one below it that can be stood in
+	fn ground_below(&self, position: Vec3<f64>) -> Option<Vec3<isize>> {
+		let node = (position + Vec3(0.0, 0.0, 0.01)).floor_to::<isize>();
+		(0..=self.agent.max_drop.max(1)).map(|drop| node - Vec3(0, 0, drop)).find(|node| self.standable(*node))
+	}
+	
+	// Each tile that can be reached from `node` in one move, with its cost and whether it takes a jump
+	fn neighbors(&self, node: Vec3<isize>) -> Vec<(Vec3<isize>, u32, bool)> {
+		let height = self.footprint.1;
+		let water_factor = |node| if self.in_water(node) { WATER_COST_FACTOR } else { 1 };
+		let mut neighbors = vec![];
+		
+		for (dx, dy) in NEIGHBORS {
+			let diagonal = dx != 0 && dy != 0;
+			let column = node + Vec3(dx, dy, 0);
+			
+			// No cutting corners: both sides of a diagonal have to be free at this height
+			if diagonal && !(self.standable(node + Vec3(dx, 0, 0)) && self.standable(node + Vec3(0, dy, 0))) { continue }
+			
+			let edge = (self.center(node) + self.center(column)) / 2.0;
+			let from_height = self.floor_height(node, edge);
+			
+			// The tile above can be a rise of less than one, when coming off the top of a slope
+			let max_rise = self.agent.jump_height.max(self.agent.step_height).floor() as isize + 1;
+			for dz in (-self.agent.max_drop..=max_rise).rev() {
+				let next = column + Vec3(0, 0, dz);
+				if diagonal && dz != 0 { continue }
+				if !self.standable(next) { continue }
+				
+				let rise = self.floor_height(next, edge) - from_height;
+				let jump = rise > self.agent.step_height + HEIGHT_TOLERANCE;
+				if jump && rise > self.agent.jump_height { continue }
+				
+				// Room overhead to jump up, or to fall down into the lower tile
+				if dz > 0 && !self.clear(node, node.z(), node.z() + height + dz) { continue }
+				if dz < 0 && !self.clear(next, next.z(), node.z() + height) { continue }
+				
+				let cost = if diagonal { DIAGONAL_COST } else { STRAIGHT_COST } * water_factor(next)
+					+ if jump { JUMP_COST } else { 0 }
+					+ if dz < 0 { DROP_COST * dz.unsigned_abs() as u32 } else { 0 };
+				neighbors.push((next, cost, jump));
+				
+				// Only the highest floor in a column can be reached, as anything below it is covered
+				break
+			}
+		}
+		
+		neighbors
+	}
+	
+	fn build_path(&self, came_from: &HashMap<Vec3<isize>, (Vec3<isize>, bool)>, start: Vec3<isize>, goal: Vec3<isize>) -> Path {
+		let mut waypoints = vec![];
+		let mut node = goal;
+		while node != start {
+			let (previous, jump) = came_from[&node];
+			waypoints.push(Waypoint { position: self.feet(node), jump });
+			node = previous;
+		}
+		waypoints.reverse();
+		Path { waypoints }
+	}
+}
+
+
+
+// MARK: Following
+
+// Steers along a path one waypoint at a time
+#[derive(Clone, Debug, Default, PartialEq)]
+#[cfg_attr(feature = "serde", derive(serde::Serialize, serde::Deserialize))]
+pub struct PathFollower {
+	pub path: Path,
+	pub next: usize,
+}
+
+impl PathFollower {
+	pub fn new(path: Path) -> Self {
+		Self { path, next: 0 }
+	}
+	
+	pub fn finished(&self) -> bool {
+		self.next >= self.path.waypoints.len()
+	}
+	
+	// Movement input and whether to jump, for an entity with its feet at `position`.
+	// Jumps are only asked for on the ground, so they happen once rather than every tick of the approach.
+	pub fn steer(&mut self, position: Vec3<f64>, contact: ContactState) -> (Vec3<f64>, bool) {
+		let offset = |waypoint: &Waypoint| (waypoint.position - position).with_z(0.0);
+		while self.path.waypoints.get(self.next).is_some_and(|waypoint| offset(waypoint).length() < ARRIVE_DISTANCE) {
+			self.next += 1;
+		}
+		
+		let Some(waypoint) = self.path.waypoints.get(self.next) else { return (Vec3::ZERO, false) };
+		let offset = offset(waypoint);
+		if !waypoint.jump { return (offset.normalize(), false) }
+		
+		// Pushing into the ledge on the way up holds the entity against it, so it waits until its feet are above it
+		let jump = contact == ContactState::Grounded && offset.length() < JUMP_DISTANCE;
+		let rising = contact == ContactState::Airborne && position.z() < waypoint.position.z();
+		(if rising { Vec3::ZERO } else { offset.normalize() }, jump)
+	}
+}
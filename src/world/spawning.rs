@@ -0,0 +1,314 @@
+use crate::*;
+
+
+pub const SPAWNS_PATH: &str = "config/spawns.cfg";
+pub const DEFAULT_SPAWNS: &str = include_str!("../../assets/config/spawns.cfg");
+
+// Random spots tried in each new cell
+const SPAWN_ATTEMPTS: u64 = 3;
+
+const DEFAULT_SIZE: Vec3<f64> = Vec3(0.70, 0.70, 1.75);
+
+pub const MAX_LIGHT: u8 = 15;
+// Light taken away by each solid tile above a spot
+const LIGHT_PER_COVER: u8 = 4;
+// Feet at least this high are in the highlands
+const HIGHLAND_HEIGHT: isize = 20;
+
+
+
+// MARK: Rules
+
+// The kind of place a spot is in, going by what it stands on and how high it is
+#[derive(Copy, Clone, Debug, PartialEq, Eq)]
+pub enum Biome {
+	Meadow,
+	Rocky,
+	Wetland,
+	Highland,
+	Built,
+}
+
+impl Biome {
+	pub fn classify(surface: Material, fluid: Fluid, height: isize) -> Self {
+		match (surface, fluid) {
+			(Wood | Brick | Tiles, _) => Self::Built,
+			(Mud, _) | (_, Water) => Self::Wetland,
+			_ if height >= HIGHLAND_HEIGHT => Self::Highland,
+			(Stone, _) => Self::Rocky,
+			(Grass | Dirt, _) => Self::Meadow,
+		}
+	}
+	
+	pub fn from_name(name: &str) -> Option<Self> {
+		match name {
+			"meadow" => Some(Self::Meadow),
+			"rocky" => Some(Self::Rocky),
+			"wetland" => Some(Self::Wetland),
+			"highland" => Some(Self::Highland),
+			"built" => Some(Self::Built),
+			_ => None,
+		}
+	}
+}
+
+// One kind of entity the spawner makes, and where it can go
+#[derive(Clone, Debug, PartialEq)]
+pub struct SpawnRule {
+	pub name: String,
+	pub surface: Vec<Material>, // Any material if empty
+	pub fluid: Fluid,
+	pub height: (isize, isize), // Of the feet, inclusive
+	pub biomes: Vec<Biome>, // Any biome if empty
+	pub light: (u8, u8), // Inclusive
+	pub weight: u32,
+	pub cap: usize,
+	pub size: Vec3<f64>,
+	pub sprites: String,
+	pub wander: Option<f64>,
+}
+
+impl SpawnRule {
+	pub fn new(name: &str) -> Self {
+		Self {
+			name: name.to_string(),
+			surface: vec![],
+			fluid: Air,
+			height: (isize::MIN, isize::MAX),
+			biomes: vec![],
+			light: (0, MAX_LIGHT),
+			weight: 1,
+			cap: 8,
+			size: DEFAULT_SIZE,
+			sprites: "player".to_string(),
+			wander: None,
+		}
+	}
+	
+	pub fn set(&mut self, parameter: &str, value: &str) -> Result<(), String> {
+		match parameter {
+			"surface" => self.surface = value.split_whitespace()
+				.map(|name| Material::from_name(name).ok_or_else(|| format!("unknown material {name:?}")))
+				.collect::<Result<_, _>>()?,
+			"fluid" => self.fluid = match value {
+				"air" => Air,
+				"water" => Water,
+				_ => return Err(format!("expected air or water for fluid, got {value:?}")),
+			},
+			"height" => match parse_numbers(parameter, value)?.as_slice() {
+				&[low, high] => self.height = (low as isize, high as isize),
+				_ => return Err(format!("expected two numbers for height, got {value:?}")),
+			},
+			"biome" => self.biomes = value.split_whitespace()
+				.map(|name| Biome::from_name(name).ok_or_else(|| format!("unknown biome {name:?}")))
+				.collect::<Result<_, _>>()?,
+			"light" => match parse_numbers(parameter, value)?.as_slice() {
+				&[low, high] if 0.0 <= low && low <= high && high <= MAX_LIGHT as f64 => self.light = (low as u8, high as u8),
+				_ => return Err(format!("expected two numbers from 0 to {MAX_LIGHT} for light, got {value:?}")),
+			},
+			"weight" => self.weight = value.parse().map_err(|_| format!("expected a whole number for weight, got {value:?}"))?,
+			"cap" => self.cap = value.parse().map_err(|_| format!("expected a whole number for cap, got {value:?}"))?,
+			"size" => match parse_numbers(parameter, value)?.as_slice() {
+				&[x, y, z] => self.size = Vec3(x, y, z),
+				_ => return Err(format!("expected three numbers for size, got {value:?}")),
+			},
+			"sprites" => self.sprites = value.to_string(),
+			"wander" => self.wander = Some(parse_numbers(parameter, value)?.first().copied().ok_or_else(|| "expected a radius for wander".to_string())?),
+			_ => return Err(format!("{} has no parameter {parameter:?}", self.name)),
+		}
+		Ok(())
+	}
+	
+	pub fn fits(&self, spot: &SpawnSpot) -> bool {
+		let z = spot.position.z().floor() as isize;
+		(self.surface.is_empty() || self.surface.contains(&spot.surface))
+		&& self.fluid == spot.fluid
+		&& (self.height.0..=self.height.1).contains(&z)
+		&& (self.biomes.is_empty() || self.biomes.contains(&spot.biome))
+		&& (self.light.0..=self.light.1).contains(&spot.light)
+	}
+	
+	pub fn hash_state(&self, hasher: &mut StateHasher) {
+		hasher.write_str(&self.name);
+		hasher.write_u64(self.surface.len() as u64);
+		self.surface.iter().for_each(|material| hasher.write_u8(*material as u8));
+		hasher.write_u8(self.fluid as u8);
+		hasher.write_i64(self.height.0 as i64);
+		hasher.write_i64(self.height.1 as i64);
+		hasher.write_u64(self.biomes.len() as u64);
+		self.biomes.iter().for_each(|biome| hasher.write_u8(*biome as u8));
+		hasher.write_u8(self.light.0);
+		hasher.write_u8(self.light.1);
+		hasher.write_u64(self.weight as u64);
+		hasher.write_u64(self.cap as u64);
+		hasher.write_vec3(self.size);
+		hasher.write_str(&self.sprites);
+		hasher.write_bool(self.wander.is_some());
+		hasher.write_f64(self.wander.unwrap_or(0.0));
+	}
+}
+
+fn parse_numbers(parameter: &str, value: &str) -> Result<Vec<f64>, String> {
+	value.split_whitespace().map(str::parse).collect::<Result<_, _>>().map_err(|_| format!("expected numbers for {parameter}, got {value:?}"))
+}
+
+
+#[derive(Clone, Debug, PartialEq)]
+pub struct SpawnTableError {
+	pub line: usize,
+	pub message: String,
+}
+
+impl std::fmt::Display for SpawnTableError {
+	fn fmt(&self, f: &mut std::fmt::Formatter<'_>) -> std::fmt::Result {
+		write!(f, "line {}: {}", self.line, self.message)
+	}
+}
+
+// Everything the spawner can make. Empty by default, so a world only has the entities put in it.
+#[derive(Clone, Debug, Default, PartialEq)]
+pub struct SpawnTable {
+	pub rules: Vec<SpawnRule>,
+}
+
+impl SpawnTable {
+	pub fn default_table() -> Self {
+		Self::parse(DEFAULT_SPAWNS).expect("Default spawn table failed to parse")
+	}
+	
+	// Falls back to the default table if the file is missing or broken
+	pub fn load_or_default(assets: &Assets, path: &str) -> Self {
+		match assets.read_to_string(path) {
+			Ok(text) => Self::parse(&text).unwrap_or_else(|e| {
+				eprintln!("Error in {path}, using default spawn table: {e}");
+				Self::default_table()
+			}),
+			Err(_) => Self::default_table(),
+		}
+	}
+	
+	pub fn parse(text: &str) -> Result<Self, SpawnTableError> {
+		let mut table = Self::default();
+		
+		for (i, line) in text.lines().enumerate() {
+			let error = |message: String| SpawnTableError { line: i + 1, message };
+			
+			let line = line.split('#').next().unwrap().trim();
+			if line.is_empty() { continue }
+			
+			let (name, parameters) = line.split_once('=').unwrap_or((line, ""));
+			let name = name.trim();
+			if table.rule(name).is_some() {
+				return Err(error(format!("{name} is already defined")))
+			}
+			let mut rule = SpawnRule::new(name);
+			
+			for parameter in parameters.split(',').map(str::trim).filter(|p| !p.is_empty()) {
+				let (parameter, value) = parameter.split_once(char::is_whitespace).ok_or_else(|| error(format!("expected a value for {parameter}")))?;
+				rule.set(parameter, value.trim()).map_err(error)?;
+			}
+			
+			table.rules.push(rule);
+		}
+		
+		Ok(table)
+	}
+	
+	pub fn rule(&self, name: &str) -> Option<&SpawnRule> {
+		self.rules.iter().find(|rule| rule.name == name)
+	}
+	
+	// Stored in input recordings, since a replay only matches with the same table
+	pub fn state_hash(&self) -> u64 {
+		let mut hasher = StateHasher::new();
+		hasher.write_u64(self.rules.len() as u64);
+		self.rules.iter().for_each(|rule| rule.hash_state(&mut hasher));
+		hasher.finish()
+	}
+}
+
+
+
+// MARK: Spawning
+
+// Somewhere in a cell an entity could be put
+#[derive(Copy, Clone, Debug, PartialEq)]
+pub struct SpawnSpot {
+	pub position: Vec3<f64>, // Feet, in the middle of the tile
+	pub surface: Material,
+	pub fluid: Fluid,
+	pub biome: Biome,
+	pub light: u8, // MAX_LIGHT under open sky, less for each solid tile above
+}
+
+// Every spot in one column of a cell: on a full tile, with two free tiles above it
+pub fn spawn_spots(tiles: &CellTiles, location: Vec3<isize>, column: Vec2<usize>) -> Vec<SpawnSpot> {
+	let origin = (location << CELL_SIZE_BITS).as_type::<f64>();
+	(1..CELL_HEIGHT - 1)
+		.filter(|z| tiles[column.with_z(z - 1)].is_full() && tiles[column.with_z(*z)].is_empty() && tiles[column.with_z(z + 1)].is_empty())
+		.map(|z| {
+			let position = origin + column.with_z(z).as_type::<f64>() + Vec3(0.5, 0.5, 0.0);
+			let surface = tiles[column.with_z(z - 1)].material;
+			let fluid = tiles[column.with_z(z)].fluid;
+			let cover = (z..CELL_HEIGHT).filter(|above| !tiles[column.with_z(*above)].is_empty()).count();
+			SpawnSpot {
+				position,
+				surface,
+				fluid,
+				biome: Biome::classify(surface, fluid, position.z() as isize),
+				light: MAX_LIGHT.saturating_sub(cover.min(MAX_LIGHT as usize) as u8 * LIGHT_PER_COVER),
+			}
+		})
+		.collect()
+}
+
+// The same for the same seed, cell and attempt every time
+fn spawn_random(seed: u64, location: Vec3<isize>, attempt: u64, roll: u64) -> u64 {
+	let mut hasher = StateHasher::new();
+	hasher.write_u64(seed);
+	location.map(|v| hasher.write_i64(v as i64));
+	hasher.write_u64(attempt);
+	hasher.write_u64(roll);
+	hasher.finish()
+}
+
+impl World {
+	// The ones stored with unloaded cells count too, or a cap would be exceeded just by walking around
+	fn spawned_count(&self, rule: &str) -> usize {
+		let alive = self.entities.spawned.iter().filter(|(_, spawned)| spawned.rule == rule).count();
+		let stored = self.saved_entities.values().flatten().filter(|saved| saved.spawned.as_ref().is_some_and(|spawned| spawned.rule == rule)).count();
+		alive + stored
+	}
+	
+	// Puts entities from the spawn table in a cell loaded for the first time
+	pub(super) fn spawn_in_cell(&mut self, location: Vec3<isize>) {
+		let seed = self.seed();
+		for attempt in 0..SPAWN_ATTEMPTS {
+			let random = |roll| spawn_random(seed, location, attempt, roll);
+			let column = Vec2(random(0) as usize % CELL_WIDTH, random(1) as usize % CELL_WIDTH);
+			let spots = spawn_spots(&self.cells[&location].tiles, location, column);
+			if spots.is_empty() { continue }
+			let spot = spots[random(2) as usize % spots.len()];
+			
+			let alive = |rule: &SpawnRule| self.spawned_count(&rule.name);
+			let candidates = self.spawn_table.rules.iter().filter(|rule| rule.fits(&spot) && alive(rule) < rule.cap).collect::<Vec<_>>();
+			let total = candidates.iter().map(|rule| rule.weight as u64).sum::<u64>();
+			if total == 0 { continue }
+			
+			let mut pick = random(3) % total;
+			let rule = candidates.into_iter().find(|rule| {
+				let found = pick < rule.weight as u64;
+				pick = pick.saturating_sub(rule.weight as u64);
+				found
+			}).unwrap().clone();
+			
+			let id = self.entities.spawn_character(spot.position, rule.size, SpriteSet::None);
+			let behavior = match rule.wander {
+				Some(radius) => AiBehavior::Wander { home: spot.position, radius },
+				None => AiBehavior::Idle,
+			};
+			self.entities.controllers.insert(id, Controller::Ai(Ai::new(vec![behavior])));
+			self.entities.spawned.insert(id, Spawned { rule: rule.name });
+		}
+	}
+}
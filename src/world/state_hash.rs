@@ -0,0 +1,60 @@
+use crate::*;
+
+
+// 64 bit FNV-1a. Unlike std's DefaultHasher the output is fixed across platforms and compiler versions,
+// so hashes can be compared between replays and between machines.
+#[derive(Copy, Clone, Debug)]
+pub struct StateHasher(u64);
+
+impl StateHasher {
+	const OFFSET_BASIS: u64 = 0xcbf29ce484222325;
+	const PRIME: u64 = 0x00000100000001b3;
+	
+	pub fn new() -> Self {
+		Self(Self::OFFSET_BASIS)
+	}
+	
+	pub fn write(&mut self, bytes: &[u8]) {
+		for byte in bytes {
+			self.0 ^= *byte as u64;
+			self.0 = self.0.wrapping_mul(Self::PRIME);
+		}
+	}
+	
+	pub fn write_u8(&mut self, v: u8) { self.write(&[v]) }
+	pub fn write_i8(&mut self, v: i8) { self.write(&v.to_le_bytes()) }
+	pub fn write_u64(&mut self, v: u64) { self.write(&v.to_le_bytes()) }
+	pub fn write_i64(&mut self, v: i64) { self.write(&v.to_le_bytes()) }
+	pub fn write_bool(&mut self, v: bool) { self.write_u8(v as u8) }
+	pub fn write_str(&mut self, v: &str) { self.write_u64(v.len() as u64); self.write(v.as_bytes()) }
+	
+	// Hashes the exact bit pattern, so 0.0 and -0.0 are different
+	pub fn write_f64(&mut self, v: f64) { self.write_u64(v.to_bits()) }
+	pub fn write_vec3(&mut self, v: Vec3<f64>) { v.map(|c| self.write_f64(c)); }
+	
+	pub fn finish(&self) -> u64 {
+		self.0
+	}
+}
+
+impl Default for StateHasher {
+	fn default() -> Self {
+		Self::new()
+	}
+}
+
+
+pub fn hash_tile(hasher: &mut StateHasher, tile: Tile) {
+	hasher.write_u8(tile.material as u8);
+	hasher.write_u8(tile.fluid as u8);
+	hasher.write_i8(tile.level);
+	tile.direction.map(|v| hasher.write_i8(v));
+}
+
+pub fn hash_tiles(tiles: &CellTiles) -> u64 {
+	let mut hasher = StateHasher::new();
+	for pos in Vec3Range::<usize, ZYX>::exclusive(Vec3::ZERO, CELL_SIZE.as_type()) {
+		hash_tile(&mut hasher, tiles[pos]);
+	}
+	hasher.finish()
+}
@@ -0,0 +1,678 @@
+use crate::*;
+
+
+const PLAYER: EntityId = EntityId(0);
+
+fn world_with_player(seed: u64) -> World {
+	let mut world = World::new(seed);
+	let position = world.place_player(Vec3(0.5, 0.5, CELL_HEIGHT as f64));
+	let player = world.entities.spawn_character(position, Vec3(0.70, 0.70, 1.75), SpriteSet::None);
+	world.entities.controllers.insert(player, Controller::Local(0));
+	assert_eq!(player, PLAYER);
+	world
+}
+
+// Walks in a square and jumps every now and then
+fn scripted_input(tick: u64, movement: &mut Movement) {
+	movement.input = match (tick / 50) % 4 {
+		0 => Vec3(1.0, 0.0, 0.0),
+		1 => Vec3(0.0, 1.0, 0.0),
+		2 => Vec3(-1.0, 0.0, 0.0),
+		_ => Vec3(0.0, -1.0, 0.0),
+	};
+	movement.jump = tick.is_multiple_of(70);
+}
+
+fn run(world: &mut World, ticks: u64) -> Vec<u64> {
+	(0..ticks).map(|_| {
+		scripted_input(world.tick, world.entities.movement.get_mut(PLAYER).unwrap());
+		world.step();
+		world.state_hash()
+	}).collect()
+}
+
+
+#[test]
+fn same_seed_and_inputs_give_same_hashes() {
+	let mut a = world_with_player(1234);
+	let mut b = world_with_player(1234);
+	assert_eq!(a.state_hash(), b.state_hash());
+	
+	assert_eq!(run(&mut a, 300), run(&mut b, 300));
+	assert_eq!(a.entities.snapshot(PLAYER), b.entities.snapshot(PLAYER));
+}
+
+#[test]
+fn different_input_diverges() {
+	let mut a = world_with_player(1234);
+	let mut b = world_with_player(1234);
+	
+	let hashes_a = run(&mut a, 100);
+	b.entities.bodies.get_mut(PLAYER).unwrap().velocity += Vec3(0.0, 0.0, 1e-9);
+	let hashes_b = run(&mut b, 100);
+	
+	assert_ne!(hashes_a.last(), hashes_b.last());
+}
+
+#[test]
+fn tile_edits_change_hash_after_update() {
+	let mut world = world_with_player(1);
+	world.step();
+	let before = world.state_hash();
+	
+	let cell = world.cells.get_mut(&Vec3(0, 0, 0)).unwrap();
+	cell.tiles[Vec3(3, 3, 10)] = Tile::full(Brick);
+	assert_eq!(world.state_hash(), before);
+	
+	world.cells.get_mut(&Vec3(0, 0, 0)).unwrap().update_tiles_hash();
+	assert_ne!(world.state_hash(), before);
+}
+
+#[test]
+fn step_loads_cells_around_player() {
+	let mut world = world_with_player(1);
+	world.step();
+	
+	let range = LOAD_DISTANCE.ceil() as isize;
+	assert!(world.cells.contains_key(&Vec3(range - 1, range - 1, 0)));
+	assert!(world.cells.contains_key(&Vec3(-range, -range, 0)));
+	assert!(!world.cells.contains_key(&Vec3(range + 2, 0, 0)));
+}
+
+#[test]
+fn cells_stay_loaded_around_every_player() {
+	let mut world = world_with_player(1);
+	let far = Vec3(20.0 * CELL_WIDTH as f64 + 0.5, 0.5, CELL_HEIGHT as f64);
+	let second = world.entities.spawn_character(far, Vec3(0.70, 0.70, 1.75), SpriteSet::None);
+	world.entities.controllers.insert(second, Controller::Local(1));
+	world.step();
+	world.step();
+	
+	assert!(world.cells.contains_key(&Vec3(0, 0, 0)));
+	assert!(world.cells.contains_key(&Vec3(20, 0, 0)));
+	assert!(!world.cells.contains_key(&Vec3(10, 0, 0)));
+	
+	// Entities that aren't players don't keep anything loaded
+	world.entities.controllers.remove(second);
+	world.step();
+	assert!(!world.cells.contains_key(&Vec3(20, 0, 0)));
+}
+
+#[test]
+fn restored_snapshot_continues_identically() {
+	let mut a = world_with_player(1234);
+	run(&mut a, 120);
+	
+	// The terrain regenerates the same from the seed, so only the entity and the tick need restoring
+	let mut b = world_with_player(1234);
+	assert!(b.entities.restore(PLAYER, &a.entities.snapshot(PLAYER).unwrap()));
+	b.tick = a.tick;
+	
+	run(&mut a, 200);
+	run(&mut b, 200);
+	assert_eq!(a.entities.snapshot(PLAYER), b.entities.snapshot(PLAYER));
+}
+
+#[cfg(feature = "serde")]
+#[test]
+fn serde_round_trips() {
+	let mut world = world_with_player(1234);
+	run(&mut world, 50);
+	let snapshot = world.entities.snapshot(PLAYER).unwrap();
+	let json = serde_json::to_string(&snapshot).unwrap();
+	assert_eq!(serde_json::from_str::<EntitySnapshot>(&json).unwrap(), snapshot);
+	
+	let tile = Tile { material: Brick, fluid: Water, level: -3, direction: Vec3(-3, 3, 6) };
+	let json = serde_json::to_string(&tile).unwrap();
+	assert_eq!(serde_json::from_str::<Tile>(&json).unwrap(), tile);
+	
+	// Vectors are plain arrays, which keeps hand written files short
+	assert_eq!(serde_json::to_string(&Vec3(1, -2, 3)).unwrap(), "[1,-2,3]");
+	assert_eq!(serde_json::from_str::<Vec2<f32>>("[0.5, 2]").unwrap(), Vec2(0.5, 2.0));
+	
+	// Missing generator settings fall back to the defaults
+	let settings = serde_json::from_str::<GeneratorSettings>(r#"{ "seed": 5, "height_scale": 8.0 }"#).unwrap();
+	assert_eq!((settings.seed, settings.height_scale, settings.large_size), (5, 8.0, GeneratorSettings::default().large_size));
+}
+
+#[test]
+fn downsampling_takes_majority_material() {
+	let mut tiles = Cell::empty_tiles();
+	// Five stone and three dirt in the first 2x2x2 block, three solid tiles in the next one
+	for (pos, material) in [(Vec3(0, 0, 0), Stone), (Vec3(1, 0, 0), Dirt), (Vec3(0, 1, 0), Stone), (Vec3(1, 1, 0), Dirt), (Vec3(0, 0, 1), Stone), (Vec3(1, 0, 1), Stone), (Vec3(0, 1, 1), Dirt), (Vec3(1, 1, 1), Stone)] {
+		tiles[pos] = Tile::full(material);
+	}
+	for pos in [Vec3(2, 0, 0), Vec3(3, 0, 0), Vec3(2, 1, 0)] {
+		tiles[pos] = Tile::full(Brick);
+	}
+	tiles[Vec3(2, 0, 1)] = Tile::empty(Water);
+	tiles[Vec3(3, 0, 1)] = Tile::empty(Water);
+	
+	let coarse = downsample(&tiles, 2);
+	assert_eq!(coarse.size, Vec3(16, 16, 16));
+	assert_eq!(coarse.get(Vec3(0, 0, 0)), Some(Tile::full(Stone)));
+	assert_eq!(coarse.get(Vec3(1, 0, 0)), Some(Tile::empty(Air)));
+	assert_eq!(coarse.get(Vec3(16, 0, 0)), None);
+	
+	// Half solid is enough, and empty blocks keep the most common fluid
+	tiles[Vec3(3, 1, 0)] = Tile::full(Brick);
+	tiles[Vec3(2, 1, 1)] = Tile::empty(Water);
+	assert_eq!(downsample(&tiles, 2).get(Vec3(1, 0, 0)), Some(Tile::full(Brick)));
+	tiles[Vec3(3, 1, 0)] = Tile::empty(Water);
+	assert_eq!(downsample(&tiles, 2).get(Vec3(1, 0, 0)), Some(Tile::empty(Water)));
+}
+
+#[test]
+fn lod_meshes_are_coarser_with_boundary_walls() {
+	let mut tiles = Cell::empty_tiles();
+	for pos in Vec3Range::<usize, ZYX>::exclusive(Vec3::ZERO, Vec3(CELL_WIDTH, CELL_WIDTH, 4)) {
+		tiles[pos] = Tile::full(Dirt);
+	}
+	tiles[Vec3(5, 5, 4)] = Tile::full(Stone); // A lone bump that disappears at lower detail
+	
+	let mut cell = Cell::new(tiles);
+	build_cell_mesh(&mut cell, Vec3(0, 0, 0), &mut HashMap::new(), &TileUvs::default());
+	cell.build_lod_meshes(&TileUvs::default());
+	
+	for (lod, factor) in LOD_FACTORS.into_iter().enumerate().skip(1) {
+		let mesh = &cell.lod_meshes[lod - 1];
+		let top = mesh.vertices.iter().filter(|v| v.normal == Vec3(0.0, 0.0, 1.0)).collect::<Vec<_>>();
+		let sides = mesh.vertices.iter().filter(|v| v.normal.z() == 0.0).collect::<Vec<_>>();
+		
+		// One quad per block on top, all at the floor height
+		let blocks = CELL_WIDTH / factor;
+		assert_eq!(top.len(), blocks * blocks * 4, "factor {factor}");
+		assert!(top.iter().all(|v| v.position.z() == 4.0));
+		
+		// Walls around the cell boundary, even though a neighbor could cover them
+		assert_eq!(sides.len(), 4 * blocks * (4 / factor).max(1) * 4, "factor {factor}");
+		assert!(sides.iter().all(|v| v.position.x() == 0.0 || v.position.y() == 0.0 || v.position.x() == CELL_WIDTH as f32 || v.position.y() == CELL_WIDTH as f32));
+		assert!(mesh.indices.len() < cell.indices.len());
+	}
+	
+	assert_eq!(lod_for_distance(10.0, 48.0), 0);
+	assert_eq!(lod_for_distance(50.0, 48.0), 1);
+	assert_eq!(lod_for_distance(1000.0, 48.0), LOD_FACTORS.len() - 1);
+}
+
+#[test]
+fn only_coarser_meshes_get_boundary_skirts() {
+	let mut tiles = Cell::empty_tiles();
+	for pos in Vec3Range::<usize, ZYX>::exclusive(Vec3::ZERO, Vec3(CELL_WIDTH, CELL_WIDTH, 5)) {
+		tiles[pos] = Tile::full(Dirt);
+	}
+	let mut cells = HashMap::new();
+	let mut neighbor = Cell::new(tiles.clone());
+	build_cell_mesh(&mut neighbor, Vec3(1, 0, 0), &mut cells, &TileUvs::default());
+	neighbor.build_lod_meshes(&TileUvs::default());
+	cells.insert(Vec3(1, 0, 0), neighbor);
+	
+	// No walls between full detail cells
+	let mut cell = Cell::new(tiles);
+	build_cell_mesh(&mut cell, Vec3(0, 0, 0), &mut cells, &TileUvs::default());
+	assert_eq!(cell.vertices.iter().filter(|v| v.normal == Vec3(1.0, 0.0, 0.0)).count(), 0);
+	assert_eq!(cells[&Vec3(1, 0, 0)].vertices.iter().filter(|v| v.normal == Vec3(-1.0, 0.0, 0.0)).count(), 0);
+	
+	// The top layer is lost in blocks of 4, so a skirt facing into the neighbor stands in for the cell's wall
+	let skirts = |mesh: &LodMesh| mesh.vertices.iter().filter(|v| v.normal == Vec3(1.0, 0.0, 0.0) && v.position.x() == 0.0).cloned().collect::<Vec<_>>();
+	assert!(skirts(&cells[&Vec3(1, 0, 0)].lod_meshes[0]).is_empty());
+	let skirts = skirts(&cells[&Vec3(1, 0, 0)].lod_meshes[1]);
+	assert_eq!(skirts.len(), CELL_WIDTH * 4);
+	assert!(skirts.iter().all(|v| v.position.z() == 4.0 || v.position.z() == 5.0));
+}
+
+#[test]
+fn faces_use_their_own_textures() {
+	let uvs = TileUvs::from_iter(TileFace::ALL.into_iter().enumerate().map(|(i, face)| {
+		((Grass, face), UvRect { min: Vec2(i as f32 * 0.25, 0.0), max: Vec2((i + 1) as f32 * 0.25, 1.0) })
+	}));
+	let face_of_vertex = |vertex: &ModelVertex| match vertex.normal.z() {
+		1.0 => TileFace::Top,
+		-1.0 => TileFace::Bottom,
+		0.0 => TileFace::Side,
+		_ => TileFace::Slope,
+	};
+	
+	let mut tiles = Cell::empty_tiles();
+	tiles[Vec3(1, 1, 1)] = Tile::full(Grass);
+	tiles[Vec3(4, 1, 1)] = Tile { material: Grass, fluid: Air, level: 1, direction: Vec3(1, 0, 1) };
+	let mut cell = Cell::new(tiles);
+	build_cell_mesh(&mut cell, Vec3(0, 0, 0), &mut HashMap::new(), &uvs);
+	
+	let mut faces = vec![];
+	for vertex in &cell.vertices {
+		let face = face_of_vertex(vertex);
+		let rect = uvs.get(Grass, face);
+		assert!(vertex.uv.x() >= rect.min.x() && vertex.uv.x() <= rect.max.x(), "{face:?} {:?}", vertex.uv);
+		if !faces.contains(&face) { faces.push(face) }
+	}
+	assert_eq!(faces.len(), TileFace::ALL.len());
+}
+
+#[test]
+fn state_hasher_is_stable() {
+	// FNV-1a reference values
+	let hasher = StateHasher::new();
+	assert_eq!(hasher.finish(), 0xcbf29ce484222325);
+	
+	let mut hasher = StateHasher::new();
+	hasher.write(b"a");
+	assert_eq!(hasher.finish(), 0xaf63dc4c8601ec8c);
+}
+
+
+// MARK: Navigation
+
+// One cell with a stone floor at z = 0 and the given tiles on it
+fn floor_with(tiles: impl IntoIterator<Item = (Vec3<isize>, Tile)>) -> HashMap<Vec3<isize>, Cell> {
+	let mut cell = Cell::new(Cell::empty_tiles());
+	for pos in Vec3Range::<usize, ZYX>::exclusive(Vec3::ZERO, Vec3(CELL_WIDTH, CELL_WIDTH, 1)) {
+		cell.tiles[pos] = Tile::full(Stone);
+	}
+	for (pos, tile) in tiles {
+		cell.tiles[pos.as_type()] = tile;
+	}
+	HashMap::from([(Vec3(0, 0, 0), cell)])
+}
+
+// Every tile from `l` to `h` inclusive
+fn block(l: Vec3<isize>, h: Vec3<isize>, tile: Tile) -> Vec<(Vec3<isize>, Tile)> {
+	Vec3Range::<isize, ZYX>::inclusive(l, h).map(|pos| (pos, tile)).collect()
+}
+
+const AGENT_SIZE: Vec3<f64> = Vec3(0.70, 0.70, 1.75);
+
+#[test]
+fn paths_go_around_walls() {
+	let cells = floor_with(block(Vec3(10, 0, 1), Vec3(10, 20, 2), Tile::full(Brick)));
+	let path = find_path(&cells, &NavAgent::new(AGENT_SIZE), Vec3(5.5, 5.5, 1.0), Vec3(15.5, 5.5, 1.0)).unwrap();
+	
+	let waypoints = &path.waypoints;
+	assert_eq!(waypoints.last().unwrap().position, Vec3(15.5, 5.5, 1.0));
+	assert!(waypoints.iter().all(|waypoint| !waypoint.jump && waypoint.position.z() == 1.0));
+	assert!(waypoints.iter().any(|waypoint| waypoint.position.y() > 21.0), "Path went through the wall");
+	
+	// Searching again finds exactly the same path
+	assert_eq!(find_path(&cells, &NavAgent::new(AGENT_SIZE), Vec3(5.5, 5.5, 1.0), Vec3(15.5, 5.5, 1.0)), Some(path));
+	
+	// Nothing leads outside the loaded cells
+	assert_eq!(find_path(&cells, &NavAgent::new(AGENT_SIZE), Vec3(5.5, 5.5, 1.0), Vec3(40.5, 5.5, 1.0)), None);
+}
+
+#[test]
+fn ledges_need_a_jump() {
+	let cells = floor_with(block(Vec3(10, 0, 1), Vec3(31, 31, 1), Tile::full(Stone)));
+	let agent = NavAgent::new(AGENT_SIZE);
+	
+	let up = find_path(&cells, &agent, Vec3(5.5, 5.5, 1.0), Vec3(15.5, 5.5, 2.0)).unwrap();
+	assert_eq!(up.waypoints.iter().filter(|waypoint| waypoint.jump).count(), 1);
+	let down = find_path(&cells, &agent, Vec3(15.5, 5.5, 2.0), Vec3(5.5, 5.5, 1.0)).unwrap();
+	assert!(down.waypoints.iter().all(|waypoint| !waypoint.jump));
+	
+	// Dropping down is fine even for agents that can't jump back up
+	let no_jump = NavAgent { jump_height: 0.5, ..agent };
+	assert!(find_path(&cells, &no_jump, Vec3(5.5, 5.5, 1.0), Vec3(15.5, 5.5, 2.0)).is_none());
+	assert!(find_path(&cells, &no_jump, Vec3(15.5, 5.5, 2.0), Vec3(5.5, 5.5, 1.0)).is_some());
+}
+
+#[test]
+fn gentle_slopes_can_be_walked_up() {
+	let ledge = block(Vec3(10, 0, 1), Vec3(31, 31, 1), Tile::full(Stone));
+	let agent = NavAgent { jump_height: 0.0, ..NavAgent::new(AGENT_SIZE) };
+	
+	// Rising half a tile over each of two tiles
+	let gentle = [
+		block(Vec3(8, 0, 1), Vec3(8, 31, 1), Tile { material: Stone, fluid: Air, level: 0, direction: Vec3(-1, 0, 2) }),
+		block(Vec3(9, 0, 1), Vec3(9, 31, 1), Tile { material: Stone, fluid: Air, level: 1, direction: Vec3(-1, 0, 2) }),
+	];
+	let cells = floor_with(ledge.iter().copied().chain(gentle.concat()));
+	let path = find_path(&cells, &agent, Vec3(5.5, 5.5, 1.0), Vec3(15.5, 5.5, 2.0)).unwrap();
+	assert!(path.waypoints.iter().all(|waypoint| !waypoint.jump));
+	
+	// Heights along the slope follow its surface
+	let on_slope = path.waypoints.iter().find(|waypoint| waypoint.position.x() == 9.5).unwrap();
+	assert_eq!(on_slope.position.z(), 1.75);
+	
+	let steep = block(Vec3(9, 0, 1), Vec3(9, 31, 1), Tile { material: Stone, fluid: Air, level: 0, direction: Vec3(-1, 0, 1) });
+	let cells = floor_with(ledge.iter().copied().chain(steep));
+	assert!(find_path(&cells, &agent, Vec3(5.5, 5.5, 1.0), Vec3(15.5, 5.5, 2.0)).is_none());
+}
+
+#[test]
+fn paths_fit_the_agent() {
+	let agent = NavAgent::new(AGENT_SIZE);
+	
+	// Water across the whole cell can only be swum through
+	let cells = floor_with(block(Vec3(10, 0, 1), Vec3(12, 31, 1), Tile::empty(Water)));
+	assert!(find_path(&cells, &agent, Vec3(5.5, 5.5, 1.0), Vec3(15.5, 5.5, 1.0)).is_some());
+	assert!(find_path(&cells, &NavAgent { can_swim: false, ..agent }, Vec3(5.5, 5.5, 1.0), Vec3(15.5, 5.5, 1.0)).is_none());
+	
+	// A low ceiling only lets short agents under
+	let cells = floor_with(block(Vec3(10, 0, 2), Vec3(12, 31, 2), Tile::full(Stone)));
+	assert!(find_path(&cells, &agent, Vec3(5.5, 5.5, 1.0), Vec3(15.5, 5.5, 1.0)).is_none());
+	assert!(find_path(&cells, &NavAgent::new(Vec3(0.7, 0.7, 0.9)), Vec3(5.5, 5.5, 1.0), Vec3(15.5, 5.5, 1.0)).is_some());
+	
+	// A one tile gap only lets narrow agents through
+	let wall = block(Vec3(10, 0, 1), Vec3(10, 31, 3), Tile::full(Stone)).into_iter().filter(|(pos, _)| pos.y() != 5);
+	let cells = floor_with(wall);
+	assert!(find_path(&cells, &agent, Vec3(5.5, 5.5, 1.0), Vec3(15.5, 5.5, 1.0)).is_some());
+	assert!(find_path(&cells, &NavAgent::new(Vec3(1.5, 1.5, 1.75)), Vec3(5.5, 5.5, 1.0), Vec3(15.5, 5.5, 1.0)).is_none());
+}
+
+#[test]
+fn entities_follow_paths() {
+	let cells = floor_with(block(Vec3(10, 0, 1), Vec3(31, 31, 1), Tile::full(Stone)));
+	let goal = Vec3(15.5, 12.5, 2.0);
+	
+	let run = || {
+		let mut entities = Entities::default();
+		let id = entities.spawn_character(Vec3(4.5, 4.5, 1.0), AGENT_SIZE, SpriteSet::None);
+		let path = find_path(&cells, &NavAgent::new(AGENT_SIZE), Vec3(4.5, 4.5, 1.0), goal).unwrap();
+		entities.controllers.insert(id, Controller::Ai(Ai::new(vec![AiBehavior::FollowPath(PathFollower::new(path))])));
+		
+		for _ in 0..600 {
+			run_systems(&mut entities, &cells, TICK_DT);
+		}
+		(entities.snapshot(id).unwrap(), entities.controllers.get(id).cloned())
+	};
+	
+	// It keeps sliding a little after reaching the end
+	let (snapshot, controller) = run();
+	assert!((snapshot.position - goal).with_z(0.0).length() < 1.0, "Ended up at {:?}", snapshot.position);
+	assert!((snapshot.position.z() - 2.0).abs() < 1e-3, "Didn't get up the ledge, z = {}", snapshot.position.z());
+	assert!(matches!(controller, Some(Controller::Ai(ai)) if matches!(&ai.behaviors[0], AiBehavior::FollowPath(follower) if follower.finished())));
+	assert_eq!(run().0, snapshot);
+}
+
+#[test]
+fn npcs_are_deterministic() {
+	let world_with_npcs = || {
+		let mut world = world_with_player(11);
+		let start = world.entities.transforms.get(PLAYER).unwrap().position;
+		for (i, behaviors) in [
+			vec![AiBehavior::Wander { home: start, radius: 6.0 }],
+			vec![AiBehavior::Idle, AiBehavior::Follow { target: PLAYER, distance: 2.0 }],
+			vec![AiBehavior::Wander { home: start, radius: 3.0 }, AiBehavior::Flee { from: PLAYER, distance: 4.0 }],
+		].into_iter().enumerate() {
+			let position = world.place_player(start + Vec3(i as f64 * 2.0 - 2.0, 3.0, 0.0));
+			let npc = world.entities.spawn_character(position, Vec3(0.70, 0.70, 1.75), SpriteSet::None);
+			world.entities.controllers.insert(npc, Controller::Ai(Ai::new(behaviors)));
+		}
+		world
+	};
+	
+	let hashes = run(&mut world_with_npcs(), 400);
+	assert_eq!(run(&mut world_with_npcs(), 400), hashes);
+	assert_ne!(run(&mut world_with_player(11), 400), hashes);
+}
+
+
+// MARK: Spawning
+
+#[test]
+fn spawn_tables_parse() {
+	let table = SpawnTable::default_table();
+	assert_eq!(table.rules.iter().map(|rule| rule.name.as_str()).collect::<Vec<_>>(), ["Wanderer", "Sentry"]);
+	let wanderer = table.rule("Wanderer").unwrap();
+	assert_eq!((&wanderer.biomes[..], wanderer.light, wanderer.weight, wanderer.cap, wanderer.wander), (&[Biome::Meadow][..], (12, 15), 3, 6, Some(6.0)));
+	assert_eq!(table.rule("Sentry").unwrap().biomes, [Biome::Meadow, Biome::Rocky]);
+	
+	let table = SpawnTable::parse("Fish = fluid water, height 2 10, size 0.5 0.5 0.5 # comment\nBat = light 0 4, biome rocky highland").unwrap();
+	assert_eq!(table.rules[0].height, (2, 10));
+	assert_eq!(table.rules[0].size, Vec3(0.5, 0.5, 0.5));
+	assert_eq!(table.rules[1], SpawnRule { light: (0, 4), biomes: vec![Biome::Rocky, Biome::Highland], ..SpawnRule::new("Bat") });
+	
+	assert_eq!(SpawnTable::parse("A = surface lava").unwrap_err().line, 1);
+	assert_eq!(SpawnTable::parse("A\n\nA = cap 2").unwrap_err().line, 3);
+	assert_eq!(SpawnTable::parse("A = biome desert").unwrap_err().line, 1);
+	assert_eq!(SpawnTable::parse("A = light 4 20").unwrap_err().line, 1);
+	assert_eq!(SpawnTable::parse("A = light 8 2").unwrap_err().line, 1);
+	assert_eq!(SpawnTable::parse("A = height 4").unwrap_err().line, 1);
+}
+
+#[test]
+fn spawn_rules_fit_spots() {
+	let mut tiles = Cell::empty_tiles();
+	tiles[Vec3(0, 0, 0)] = Tile::full(Stone);
+	tiles[Vec3(0, 0, 1)] = Tile::empty(Water);
+	tiles[Vec3(0, 0, 2)] = Tile::full(Grass);
+	tiles[Vec3(0, 0, 9)] = Tile::full(Dirt);
+	
+	// Under the grass there's only room for one tile of water, so the only spots are on the grass and on the dirt roof
+	let spots = spawn_spots(&tiles, Vec3(1, 0, 0), Vec2(0, 0));
+	assert_eq!(spots.iter().map(|spot| spot.position).collect::<Vec<_>>(), [Vec3(32.5, 0.5, 3.0), Vec3(32.5, 0.5, 10.0)]);
+	assert_eq!((spots[0].surface, spots[0].fluid, spots[0].biome, spots[0].light), (Grass, Air, Biome::Meadow, MAX_LIGHT - 4));
+	assert_eq!((spots[1].surface, spots[1].light), (Dirt, MAX_LIGHT));
+	
+	let rule = |text| SpawnTable::parse(text).unwrap().rules.remove(0);
+	assert!(rule("A").fits(&spots[0]) && rule("A").fits(&spots[1]));
+	assert!(rule("A = surface grass, light 0 11").fits(&spots[0]));
+	assert!(!rule("A = surface grass, light 12 15").fits(&spots[0]));
+	assert!(!rule("A = biome rocky").fits(&spots[0]));
+	assert!(!rule("A = height 7 20").fits(&spots[0]));
+	assert!(!rule("A = fluid water").fits(&spots[1]));
+	
+	// With the grass gone the water is deep enough to stand in
+	tiles[Vec3(0, 0, 2)] = Tile::empty(Water);
+	let spots = spawn_spots(&tiles, Vec3(1, 0, 0), Vec2(0, 0));
+	assert_eq!((spots[0].position.z(), spots[0].surface, spots[0].fluid, spots[0].biome), (1.0, Stone, Water, Biome::Wetland));
+	assert!(rule("A = fluid water, surface stone, biome wetland").fits(&spots[0]));
+	
+	// Deep enough under cover it's dark
+	for z in 3..6 {
+		tiles[Vec3(0, 0, z)] = Tile::full(Stone);
+	}
+	assert_eq!(spawn_spots(&tiles, Vec3(1, 0, 0), Vec2(0, 0))[0].light, 0);
+}
+
+#[test]
+fn biomes_follow_surface_and_height() {
+	assert_eq!(Biome::classify(Grass, Air, 4), Biome::Meadow);
+	assert_eq!(Biome::classify(Stone, Air, 4), Biome::Rocky);
+	assert_eq!(Biome::classify(Grass, Air, 24), Biome::Highland);
+	assert_eq!(Biome::classify(Mud, Air, 24), Biome::Wetland);
+	assert_eq!(Biome::classify(Stone, Water, 4), Biome::Wetland);
+	assert_eq!(Biome::classify(Brick, Air, 24), Biome::Built);
+}
+
+fn world_with_spawns(seed: u64) -> World {
+	let mut world = world_with_player(seed);
+	world.spawn_table = SpawnTable::default_table();
+	world
+}
+
+fn spawned_in(world: &World, location: Vec3<isize>) -> Vec<(String, Vec3<f64>)> {
+	world.entities.spawned.iter()
+		.map(|(id, spawned)| (spawned.rule.clone(), world.entities.transforms.get(id).unwrap().position))
+		.filter(|(_, position)| cell_entities::cell_of(*position) == location)
+		.collect()
+}
+
+#[test]
+fn spawner_fills_new_cells() {
+	let mut world = world_with_spawns(4);
+	world.step();
+	
+	let table = SpawnTable::default_table();
+	let spawned = world.entities.spawned.iter().map(|(_, spawned)| spawned.rule.clone()).collect::<Vec<_>>();
+	assert!(!spawned.is_empty());
+	for rule in &table.rules {
+		assert!(spawned.iter().filter(|name| **name == rule.name).count() <= rule.cap);
+	}
+	for id in world.entities.spawned.ids().collect::<Vec<_>>() {
+		assert!(matches!(world.entities.controllers.get(id), Some(Controller::Ai(_))));
+		assert_eq!(world.get_block(world.entities.transforms.get(id).unwrap().position.floor_to::<isize>() - Vec3(0, 0, 1)), Tile::full(Grass));
+	}
+	
+	// Spawning happens once per cell, and only depends on the seed
+	world.step();
+	assert_eq!(world.entities.spawned.len(), spawned.len());
+	assert_eq!(run(&mut world_with_spawns(4), 100), run(&mut world_with_spawns(4), 100));
+	
+	// Without a table nothing spawns
+	let mut world = world_with_player(4);
+	world.step();
+	assert_eq!(world.entities.len(), 1);
+}
+
+#[test]
+fn spawns_are_kept_with_their_cells() {
+	let mut world = world_with_spawns(6);
+	world.step();
+	let location = world.entities.spawned.ids().map(|id| cell_entities::cell_of(world.entities.transforms.get(id).unwrap().position)).next().unwrap();
+	let before = spawned_in(&world, location);
+	let start = world.entities.transforms.get(PLAYER).unwrap().position;
+	
+	// Leaving takes them out of the world with the cell
+	world.entities.transforms.get_mut(PLAYER).unwrap().position = start + Vec3(1000.0, 0.0, 0.0);
+	world.step();
+	assert!(!world.cells.contains_key(&location));
+	assert!(spawned_in(&world, location).is_empty());
+	let saved = world.saved_entities(location);
+	assert_eq!(saved.iter().map(|spawn| (spawn.spawned.as_ref().unwrap().rule.clone(), spawn.transform.unwrap().position)).collect::<Vec<_>>(), before);
+	assert!(saved.iter().all(|spawn| matches!(spawn.controller, Some(Controller::Ai(_)))));
+	
+	// Coming back puts the same ones back instead of spawning new ones
+	world.entities.transforms.get_mut(PLAYER).unwrap().position = start;
+	world.step();
+	assert!(world.saved_entities(location).is_empty());
+	let after = spawned_in(&world, location);
+	assert_eq!(after.len(), before.len());
+	for ((rule, position), (rule_before, position_before)) in after.iter().zip(&before) {
+		assert_eq!(rule, rule_before);
+		assert!((*position - *position_before).length() < 0.1);
+	}
+}
+
+
+
+#[test]
+fn caps_count_stored_spawns() {
+	let mut world = world_with_player(6);
+	world.spawn_table = SpawnTable::parse("Loner = cap 1").unwrap();
+	world.step();
+	let start = world.entities.transforms.get(PLAYER).unwrap().position;
+	
+	// Each trip loads new cells, but the one spawned first is still around in storage
+	for trip in 1..4 {
+		world.entities.transforms.get_mut(PLAYER).unwrap().position = start + Vec3(1000.0 * trip as f64, 0.0, 0.0);
+		world.step();
+		let stored = world.saved_entities.values().flatten().filter(|saved| saved.spawned.is_some()).count();
+		assert_eq!(world.entities.spawned.len() + stored, 1, "trip {trip}");
+	}
+}
+
+// MARK: Cell entities
+
+#[test]
+fn entities_unload_with_their_cells() {
+	let mut world = world_with_player(3);
+	world.step();
+	let start = world.entities.transforms.get(PLAYER).unwrap().position;
+	let npc_at = |world: &mut World, offset| {
+		let position = world.place_player(start + offset);
+		world.entities.spawn_character(position, Vec3(0.70, 0.70, 1.75), SpriteSet::None)
+	};
+	let npc = npc_at(&mut world, Vec3(3.0, 0.0, 0.0));
+	let follower = npc_at(&mut world, Vec3(6.0, 0.0, 0.0));
+	world.entities.controllers.insert(npc, Controller::Ai(Ai::new(vec![AiBehavior::Idle])));
+	let follow = vec![AiBehavior::Follow { target: npc, distance: 2.0 }];
+	world.entities.controllers.insert(follower, Controller::Ai(Ai::new(follow.clone())));
+	world.step();
+	let location = cell_entities::cell_of(world.entities.transforms.get(npc).unwrap().position);
+	let resting = world.entities.transforms.get(npc).unwrap().position;
+	
+	world.entities.transforms.get_mut(PLAYER).unwrap().position = start + Vec3(1000.0, 0.0, 0.0);
+	world.step();
+	assert!(!world.entities.contains(npc) && !world.entities.contains(follower));
+	let saved = world.saved_entities(location);
+	let saved_npc = saved.iter().find(|saved| saved.id == npc).unwrap();
+	assert_eq!(saved_npc.transform.unwrap().position, resting);
+	
+	// They come back as they were, under the same ids, so the follower still has its target
+	world.entities.transforms.get_mut(PLAYER).unwrap().position = start;
+	world.step();
+	assert!(world.saved_entities(location).is_empty());
+	let Some(Controller::Ai(ai)) = world.entities.controllers.get(npc) else { panic!("{npc} wasn't put back") };
+	assert_eq!(ai.behaviors, vec![AiBehavior::Idle]);
+	assert!((world.entities.transforms.get(npc).unwrap().position - resting).length() < 0.1);
+	let Some(Controller::Ai(ai)) = world.entities.controllers.get(follower) else { panic!("{follower} wasn't put back") };
+	assert_eq!(ai.behaviors, follow);
+	
+	let distance = |world: &World| (world.entities.transforms.get(follower).unwrap().position - world.entities.transforms.get(npc).unwrap().position).length();
+	let before = distance(&world);
+	for _ in 0..120 {
+		world.step();
+	}
+	assert!(distance(&world) < before.min(3.0), "{} then {}", before, distance(&world));
+}
+
+#[test]
+fn entities_outside_loaded_cells_are_stored() {
+	let mut world = world_with_player(3);
+	world.step();
+	let start = world.entities.transforms.get(PLAYER).unwrap().position;
+	let far = start + Vec3(1000.0, 0.0, 0.0);
+	let location = cell_entities::cell_of(far);
+	let npc = world.entities.spawn_character(far, Vec3(0.70, 0.70, 1.75), SpriteSet::None);
+	// Cells above and below are never loaded, so these go with the cell of their column
+	let flying = world.entities.spawn_character(far + Vec3(0.0, 0.0, 100.0), Vec3(0.70, 0.70, 1.75), SpriteSet::None);
+	let sunk = world.entities.spawn_character(far - Vec3(0.0, 0.0, 40.0), Vec3(0.70, 0.70, 1.75), SpriteSet::None);
+	
+	// Not simulated, so it doesn't fall while its cell is away
+	world.step();
+	assert!(!world.entities.contains(npc));
+	assert_eq!(location.z(), 0);
+	assert_eq!(world.saved_entities(location).iter().map(|saved| saved.id).collect::<Vec<_>>(), [npc, flying, sunk]);
+	assert_eq!(world.saved_entities(location)[0].transform.unwrap().position, far);
+	
+	// Going there puts it back, and the spawner still gets a go at the cell
+	world.spawn_table = SpawnTable::default_table();
+	world.entities.transforms.get_mut(PLAYER).unwrap().position = far + Vec3(2.0, 0.0, 0.0);
+	world.step();
+	assert!(world.saved_entities(location).is_empty());
+	assert_eq!(world.entities.transforms.iter().filter(|(id, _)| !world.entities.spawned.contains(*id)).count(), 4);
+	assert!(world.entities.contains(flying) && world.entities.contains(sunk));
+}
+
+#[test]
+fn regenerating_starts_entities_over() {
+	let mut world = world_with_spawns(6);
+	world.step();
+	let start = world.entities.transforms.get(PLAYER).unwrap().position;
+	let placed = world.entities.spawn_character(start + Vec3(2.0, 0.0, 0.0), Vec3(0.70, 0.70, 1.75), SpriteSet::None);
+	let spawned_before = world.entities.spawned.ids().collect::<Vec<_>>();
+	assert!(!spawned_before.is_empty());
+	
+	// Leave some behind in storage, then come back to new terrain
+	world.entities.transforms.get_mut(PLAYER).unwrap().position = start + Vec3(1000.0, 0.0, 0.0);
+	world.step();
+	assert!(!world.saved_entities.is_empty());
+	world.entities.transforms.get_mut(PLAYER).unwrap().position = start;
+	world.regenerate();
+	assert!(world.saved_entities.is_empty() && world.entities.spawned.is_empty());
+	world.step();
+	
+	// The spawner fills the new cells, and nothing stored with the old terrain is put back
+	assert!(!world.entities.spawned.is_empty());
+	assert!(spawned_before.iter().all(|id| !world.entities.contains(*id)));
+	assert!(!world.entities.contains(placed));
+	assert!(world.entities.contains(PLAYER));
+}
+
+#[cfg(feature = "serde")]
+#[test]
+fn saved_entities_serialize() {
+	let mut world = world_with_spawns(6);
+	world.step();
+	let location = world.entities.spawned.ids().map(|id| cell_entities::cell_of(world.entities.transforms.get(id).unwrap().position)).next().unwrap();
+	let start = world.entities.transforms.get(PLAYER).unwrap().position;
+	world.entities.transforms.get_mut(PLAYER).unwrap().position = start + Vec3(1000.0, 0.0, 0.0);
+	world.step();
+	
+	let saved = world.saved_entities(location);
+	assert!(!saved.is_empty());
+	let json = serde_json::to_string(saved).unwrap();
+	let loaded = serde_json::from_str::<Vec<SavedEntity>>(&json).unwrap();
+	assert_eq!(loaded, saved);
+}